@@ -0,0 +1,208 @@
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use clap::Args;
+use serde_json::json;
+use tokio::fs;
+use url::Url;
+use urlwasher::{
+    clearurls,
+    rule_layers::{self, RuleLayer},
+    DirtyUrlRule, RedirectWashPolicy, UrlWasher, UrlWasherConfig,
+};
+
+use crate::report::OutputFormat;
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// File with one URL per line, blank lines and lines starting with '#' are skipped.
+    #[arg(long, value_name = "FILE")]
+    corpus: PathBuf,
+    /// ClearURLs database (`data.min.json`) composed after built-in rules.
+    #[arg(long, value_name = "FILE")]
+    clearurls: Option<PathBuf>,
+    /// How many URLs that matched no rule are listed.
+    #[arg(long, default_value_t = 20)]
+    max_unmatched: usize,
+}
+
+struct BenchResult {
+    urls: usize,
+    invalid: usize,
+    changed: usize,
+    elapsed: Duration,
+    /// Rule name to number of URLs it matched, unmatched rules have 0.
+    rule_hits: BTreeMap<String, usize>,
+    unmatched: Vec<String>,
+}
+
+/// Washes corpus without unrolling short links, so only rule engine is measured.
+pub async fn run(
+    config: &UrlWasherConfig,
+    rule_layer: Option<RuleLayer>,
+    format: OutputFormat,
+    args: BenchArgs,
+) -> anyhow::Result<bool> {
+    let mut layers = vec![RuleLayer::builtin()];
+    if let Some(path) = &args.clearurls {
+        let json = fs::read(path)
+            .await
+            .with_context(|| format!("read {}", path.display()))?;
+        layers.push(clearurls::import(&json).context("import ClearURLs rules")?);
+    }
+    layers.extend(rule_layer);
+    let rules = rule_layers::compose(&layers).rules;
+    let corpus = fs::read_to_string(&args.corpus)
+        .await
+        .with_context(|| format!("read {}", args.corpus.display()))?;
+    let result = measure(config, rules, &corpus).await?;
+    match format {
+        OutputFormat::Text => print_text(&result, args.max_unmatched),
+        OutputFormat::Json => println!("{}", json_report(&result, args.max_unmatched)),
+    }
+    Ok(true)
+}
+
+async fn measure(
+    config: &UrlWasherConfig,
+    rules: Vec<DirtyUrlRule>,
+    corpus: &str,
+) -> anyhow::Result<BenchResult> {
+    let mut config = config.clone();
+    config
+        .redirect_policy
+        .iter_mut()
+        .for_each(|(_, redirect_policy)| *redirect_policy = RedirectWashPolicy::Ignore);
+    let url_washer = UrlWasher::new(config).with_rules(rules.clone());
+
+    let mut result = BenchResult {
+        urls: 0,
+        invalid: 0,
+        changed: 0,
+        elapsed: Duration::ZERO,
+        rule_hits: rules.iter().map(|rule| (rule.name.clone(), 0)).collect(),
+        unmatched: Vec::new(),
+    };
+    let mut urls = Vec::new();
+    for line in corpus.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match Url::parse(line) {
+            Ok(url) => urls.push(url),
+            Err(_) => result.invalid += 1,
+        }
+    }
+    result.urls = urls.len();
+
+    let started_at = Instant::now();
+    for url in &urls {
+        if url_washer
+            .wash(url)
+            .await?
+            .is_some_and(|washed| washed != *url)
+        {
+            result.changed += 1;
+        }
+    }
+    result.elapsed = started_at.elapsed();
+
+    for url in &urls {
+        match rules.iter().find(|rule| rule.matches(url)) {
+            Some(rule) => *result.rule_hits.entry(rule.name.clone()).or_default() += 1,
+            None => result.unmatched.push(url.to_string()),
+        }
+    }
+    Ok(result)
+}
+
+fn urls_per_sec(result: &BenchResult) -> f64 {
+    result.urls as f64 / result.elapsed.as_secs_f64().max(f64::EPSILON)
+}
+
+fn print_text(result: &BenchResult, max_unmatched: usize) {
+    println!(
+        "{} url(s) washed in {:.3}s ({:.0} url(s)/s), {} changed, {} invalid skipped",
+        result.urls,
+        result.elapsed.as_secs_f64(),
+        urls_per_sec(result),
+        result.changed,
+        result.invalid
+    );
+    let mut rule_hits = result.rule_hits.iter().collect::<Vec<_>>();
+    rule_hits.sort_by(|(_, a), (_, b)| b.cmp(a));
+    println!("\nRule hits:");
+    for (rule, hits) in rule_hits {
+        println!("{hits:>8}  {rule}");
+    }
+    println!("\n{} url(s) matched no rule:", result.unmatched.len());
+    for url in result.unmatched.iter().take(max_unmatched) {
+        println!("  {url}");
+    }
+    if result.unmatched.len() > max_unmatched {
+        println!("  ... and {} more", result.unmatched.len() - max_unmatched);
+    }
+}
+
+fn json_report(result: &BenchResult, max_unmatched: usize) -> serde_json::Value {
+    json!({
+        "urls": result.urls,
+        "invalid": result.invalid,
+        "changed": result.changed,
+        "elapsed_ms": result.elapsed.as_secs_f64() * 1000.0,
+        "urls_per_sec": urls_per_sec(result),
+        "rule_hits": result.rule_hits,
+        "unmatched_count": result.unmatched.len(),
+        "unmatched": result.unmatched.iter().take(max_unmatched).collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use urlwasher::{rule_set, UrlWasherConfig};
+
+    use super::{json_report, measure};
+
+    const CORPUS: &str = "# comment
+https://youtu.be/lSwnPoo9ZK0?si=TRACKING
+
+https://youtu.be/lSwnPoo9ZK0
+not a url
+https://example.com/a
+https://example.org/b
+";
+
+    #[tokio::test]
+    async fn counts_rule_hits_and_unmatched_urls() {
+        let result = measure(&UrlWasherConfig::default(), rule_set().clone(), CORPUS)
+            .await
+            .unwrap();
+        assert_eq!(result.urls, 4);
+        assert_eq!(result.invalid, 1);
+        assert_eq!(result.changed, 1);
+        assert_eq!(result.rule_hits["youtu.be"], 2);
+        assert_eq!(result.rule_hits["twitter.com"], 0);
+        assert_eq!(
+            result.unmatched,
+            vec!["https://example.com/a", "https://example.org/b"]
+        );
+    }
+
+    #[tokio::test]
+    async fn limits_unmatched_urls_of_json_report() {
+        let result = measure(&UrlWasherConfig::default(), rule_set().clone(), CORPUS)
+            .await
+            .unwrap();
+        let report = json_report(&result, 1);
+        assert_eq!(report["unmatched_count"], 2);
+        assert_eq!(
+            report["unmatched"],
+            serde_json::json!(["https://example.com/a"])
+        );
+        assert_eq!(report["rule_hits"]["youtu.be"], 2);
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use clap::{Args, Subcommand};
+use tokio::{fs, io::AsyncWriteExt};
+use urldebloater_proto::directory::{self, DirectoryEntry, MixerDirectory, SignedDirectory};
+
+#[derive(Args)]
+pub struct DirectoryArgs {
+    #[command(subcommand)]
+    command: DirectoryCommand,
+}
+
+#[derive(Subcommand)]
+enum DirectoryCommand {
+    /// Generate directory signing key and print its public key.
+    Keygen {
+        /// Where the key is written, existing file is never overwritten.
+        key: PathBuf,
+    },
+    /// Sign directory of mixer instances and print it.
+    Sign {
+        /// Key generated by `urlwash directory keygen`.
+        #[arg(long)]
+        key: PathBuf,
+        /// JSON array of entries served by mixer `/directory-entry` endpoint.
+        entries: PathBuf,
+    },
+}
+
+pub async fn run(args: DirectoryArgs) -> anyhow::Result<bool> {
+    match args.command {
+        DirectoryCommand::Keygen { key } => {
+            let (pkcs8, public_key) = directory::generate_key()?;
+            let mut file = fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&key)
+                .await
+                .with_context(|| format!("create {}", key.display()))?;
+            file.write_all(&pkcs8).await.context("write key")?;
+            println!("{public_key}");
+        }
+        DirectoryCommand::Sign { key, entries } => {
+            let pkcs8 = fs::read(&key)
+                .await
+                .with_context(|| format!("read {}", key.display()))?;
+            let entries = fs::read(&entries)
+                .await
+                .with_context(|| format!("read {}", entries.display()))?;
+            let instances: Vec<DirectoryEntry> =
+                serde_json::from_slice(&entries).context("deserialize entries")?;
+            let issued_at = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .context("read system time")?
+                .as_secs();
+            let directory = MixerDirectory {
+                issued_at,
+                instances,
+            };
+            let signed = SignedDirectory::sign(&directory, &pkcs8)?;
+            println!("{}", serde_json::to_string(&signed)?);
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,167 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::Args;
+use tokio::fs;
+use urlwasher::text_washer::{TextWasher, WashedUrl};
+
+use crate::report::{self, OutputFormat};
+
+#[derive(Args)]
+pub struct FixArgs {
+    /// Glob patterns of files to wash, e.g. '**/*.md'.
+    #[arg(required = true)]
+    patterns: Vec<String>,
+    /// Write washed content back to files, otherwise only report what would change.
+    #[arg(long)]
+    in_place: bool,
+    /// Suffix of backup copy written before file is modified.
+    #[arg(long, default_value = ".bak")]
+    backup_suffix: String,
+    /// Do not write backups of modified files.
+    #[arg(long)]
+    no_backup: bool,
+}
+
+/// Returns false if some of the files could not be washed.
+pub async fn run(
+    text_washer: TextWasher,
+    format: OutputFormat,
+    args: FixArgs,
+) -> anyhow::Result<bool> {
+    let verb = if args.in_place { "washed" } else { "to wash" };
+    let mut all_washed = true;
+    let mut changed_files = 0;
+    let mut changed_urls = 0;
+    for path in matching_files(&args.patterns)? {
+        match fix_file(&text_washer, &path, &args).await {
+            Ok(washed_urls) if washed_urls.is_empty() => {}
+            Ok(washed_urls) => {
+                if format == OutputFormat::Json {
+                    for washed_url in &washed_urls {
+                        let mut report = washed_url.report.clone();
+                        report.file = Some(path.display().to_string());
+                        report::print(&report);
+                    }
+                } else {
+                    println!("{}: {} url(s) {verb}", path.display(), washed_urls.len());
+                }
+                changed_files += 1;
+                changed_urls += washed_urls.len();
+            }
+            Err(err) => {
+                eprintln!("urlwash: could not wash {}: {err:#}", path.display());
+                all_washed = false;
+            }
+        }
+    }
+    if format == OutputFormat::Text {
+        println!("{changed_urls} url(s) {verb} in {changed_files} file(s)");
+    }
+    Ok(all_washed)
+}
+
+fn matching_files(patterns: &[String]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for pattern in patterns {
+        for path in glob::glob(pattern).with_context(|| format!("invalid pattern '{pattern}'"))? {
+            let path = path.context("read matched path")?;
+            if path.is_file() && !files.contains(&path) {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+async fn fix_file(
+    text_washer: &TextWasher,
+    path: &Path,
+    args: &FixArgs,
+) -> anyhow::Result<Vec<WashedUrl>> {
+    let dirty_text = fs::read_to_string(path).await.context("read file")?;
+    let washed = text_washer.wash_detailed(&dirty_text).await;
+    if washed.washed_urls.is_empty() || !args.in_place {
+        return Ok(washed.washed_urls);
+    }
+    if !args.no_backup {
+        let mut backup_path = path.as_os_str().to_owned();
+        backup_path.push(&args.backup_suffix);
+        fs::write(&backup_path, &dirty_text)
+            .await
+            .context("write backup")?;
+    }
+    fs::write(path, washed.text).await.context("write file")?;
+    Ok(washed.washed_urls)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tokio::fs;
+    use urlwasher::text_washer::TextWasher;
+
+    use super::{fix_file, matching_files, FixArgs};
+
+    const DIRTY: &str = "watch https://youtu.be/lSwnPoo9ZK0?si=TRACKING\n";
+    const CLEAN: &str = "watch https://youtu.be/lSwnPoo9ZK0\n";
+
+    async fn dirty_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("urlwash-fix-{}", std::process::id()));
+        fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join(name);
+        fs::write(&path, DIRTY).await.unwrap();
+        path
+    }
+
+    fn fix_args(in_place: bool, no_backup: bool) -> FixArgs {
+        FixArgs {
+            patterns: Vec::new(),
+            in_place,
+            backup_suffix: ".bak".to_string(),
+            no_backup,
+        }
+    }
+
+    #[tokio::test]
+    async fn only_reports_urls_without_in_place() {
+        let path = dirty_file("report.md").await;
+        let washed_urls = fix_file(&TextWasher::default(), &path, &fix_args(false, false))
+            .await
+            .unwrap();
+        assert_eq!(washed_urls.len(), 1);
+        assert_eq!(washed_urls[0].report.removed_params, vec!["si"]);
+        assert_eq!(fs::read_to_string(&path).await.unwrap(), DIRTY);
+    }
+
+    #[tokio::test]
+    async fn washes_in_place_with_backup() {
+        let path = dirty_file("in_place.md").await;
+        fix_file(&TextWasher::default(), &path, &fix_args(true, false))
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&path).await.unwrap(), CLEAN);
+        let backup = path.with_extension("md.bak");
+        assert_eq!(fs::read_to_string(&backup).await.unwrap(), DIRTY);
+    }
+
+    #[tokio::test]
+    async fn washes_in_place_without_backup() {
+        let path = dirty_file("no_backup.md").await;
+        fix_file(&TextWasher::default(), &path, &fix_args(true, true))
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&path).await.unwrap(), CLEAN);
+        assert!(!path.with_extension("md.bak").exists());
+    }
+
+    #[tokio::test]
+    async fn matches_each_file_once() {
+        let path = dirty_file("matched.md").await;
+        let pattern = path.display().to_string();
+        let files = matching_files(&[pattern.clone(), pattern]).unwrap();
+        assert_eq!(files, vec![path]);
+        assert!(matching_files(&["[".to_string()]).is_err());
+    }
+}
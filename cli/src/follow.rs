@@ -0,0 +1,187 @@
+use std::{io::SeekFrom, path::PathBuf, pin::pin, time::Duration};
+
+use anyhow::Context;
+use futures::{Stream, StreamExt};
+use tokio::{
+    fs::File,
+    io::{self, AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::mpsc,
+    time::sleep,
+};
+use tokio_stream::wrappers::{LinesStream, ReceiverStream};
+use tracing::error;
+use urlwasher::text_washer::TextWasher;
+
+use crate::report::OutputFormat;
+
+/// Lines washed concurrently, output order stays the same as input order.
+const MAX_LINES_IN_FLIGHT: usize = 16;
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Washes lines from stdin as they arrive until stdin is closed.
+pub async fn wash_stdin_lines(text_washer: TextWasher, format: OutputFormat) -> anyhow::Result<()> {
+    let lines = LinesStream::new(BufReader::new(io::stdin()).lines()).filter_map(|line| async {
+        line.inspect_err(|err| error!("Could not read stdin: {err}"))
+            .ok()
+    });
+    wash_lines(&text_washer, format, lines, &mut io::stdout()).await
+}
+
+/// Washes lines appended to file, like `tail -f`.
+pub async fn wash_followed_file(
+    text_washer: TextWasher,
+    format: OutputFormat,
+    path: PathBuf,
+) -> anyhow::Result<()> {
+    let mut file = File::open(&path).await.context("open followed file")?;
+    let mut position = file
+        .seek(SeekFrom::End(0))
+        .await
+        .context("seek followed file")?;
+    let (lines_tx, lines_rx) = mpsc::channel(MAX_LINES_IN_FLIGHT);
+    tokio::spawn(async move {
+        let mut pending = Vec::new();
+        let mut buf = vec![0; 8 * 1024];
+        loop {
+            let len = file.metadata().await.map(|metadata| metadata.len());
+            if len.is_ok_and(|len| len < position) {
+                // truncated (e.g. rotated by copytruncate), start over
+                position = match file.seek(SeekFrom::Start(0)).await {
+                    Ok(position) => position,
+                    Err(err) => return error!("Could not seek followed file: {err}"),
+                };
+                pending.clear();
+            }
+            let read = match file.read(&mut buf).await {
+                Ok(read) => read,
+                Err(err) => return error!("Could not read followed file: {err}"),
+            };
+            if read == 0 {
+                sleep(FOLLOW_POLL_INTERVAL).await;
+                continue;
+            }
+            position += read as u64;
+            pending.extend_from_slice(&buf[..read]);
+            for line in drain_lines(&mut pending) {
+                if lines_tx.send(line).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    wash_lines(
+        &text_washer,
+        format,
+        ReceiverStream::new(lines_rx),
+        &mut io::stdout(),
+    )
+    .await
+}
+
+/// Removes complete lines from `pending`, leaving unfinished last line there.
+fn drain_lines(pending: &mut Vec<u8>) -> Vec<String> {
+    let mut lines = Vec::new();
+    while let Some(newline) = pending.iter().position(|byte| *byte == b'\n') {
+        let line = pending.drain(..=newline).collect::<Vec<_>>();
+        let line = String::from_utf8_lossy(&line[..newline]);
+        lines.push(line.strip_suffix('\r').unwrap_or(&line).to_string());
+    }
+    lines
+}
+
+async fn wash_lines(
+    text_washer: &TextWasher,
+    format: OutputFormat,
+    lines: impl Stream<Item = String>,
+    output: &mut (impl AsyncWrite + Unpin),
+) -> anyhow::Result<()> {
+    let mut washed_lines = pin!(lines
+        .map(|line| async move { text_washer.wash_detailed(&line).await })
+        .buffered(MAX_LINES_IN_FLIGHT));
+    while let Some(washed) = washed_lines.next().await {
+        match format {
+            OutputFormat::Text => {
+                output
+                    .write_all(format!("{}\n", washed.text).as_bytes())
+                    .await
+                    .context("write output")?;
+            }
+            OutputFormat::Json => {
+                for washed_url in &washed.washed_urls {
+                    let json =
+                        serde_json::to_string(&washed_url.report).context("serialize report")?;
+                    output
+                        .write_all(format!("{json}\n").as_bytes())
+                        .await
+                        .context("write output")?;
+                }
+            }
+        }
+        output.flush().await.context("flush output")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+    use urldebloater_proto::report::WashReport;
+    use urlwasher::text_washer::TextWasher;
+
+    use super::{drain_lines, wash_lines};
+    use crate::report::OutputFormat;
+
+    fn lines() -> impl futures::Stream<Item = String> {
+        stream::iter([
+            "first https://youtu.be/lSwnPoo9ZK0?si=TRACKING".to_string(),
+            "nothing to wash".to_string(),
+            "https://x.com/a/status/1?s=46".to_string(),
+        ])
+    }
+
+    #[test]
+    fn drains_complete_lines() {
+        let mut pending = b"first\r\nsecond\nunfinished".to_vec();
+        assert_eq!(drain_lines(&mut pending), vec!["first", "second"]);
+        assert_eq!(pending, b"unfinished");
+        assert!(drain_lines(&mut pending).is_empty());
+    }
+
+    #[tokio::test]
+    async fn washes_lines_in_order() {
+        let mut output = Vec::new();
+        wash_lines(
+            &TextWasher::default(),
+            OutputFormat::Text,
+            lines(),
+            &mut output,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "first https://youtu.be/lSwnPoo9ZK0\nnothing to wash\nhttps://x.com/a/status/1\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_washed_urls_of_lines() {
+        let mut output = Vec::new();
+        wash_lines(
+            &TextWasher::default(),
+            OutputFormat::Json,
+            lines(),
+            &mut output,
+        )
+        .await
+        .unwrap();
+        let reports = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<WashReport>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].removed_params, vec!["si"]);
+        assert_eq!(reports[1].rule.as_deref(), Some("twitter.com"));
+    }
+}
@@ -0,0 +1,386 @@
+use std::{path::PathBuf, process::ExitCode, sync::Arc};
+
+use anyhow::{anyhow, Context};
+use clap::{Parser, Subcommand, ValueEnum};
+use report::OutputFormat;
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+use tracing_subscriber::EnvFilter;
+use url::Url;
+use urlwasher::{
+    doh::DohProvider,
+    rule_file,
+    rule_layers::{self, RuleLayer},
+    shorteners::ShortenersConfig,
+    text_washer::TextWasher,
+    RedirectWashPolicy, UrlWasher, UrlWasherConfig, WashReport,
+};
+
+mod bench;
+mod directory;
+mod fix;
+mod follow;
+mod report;
+
+/// Remove tracking params from URLs.
+///
+/// Washes URLs given as arguments, or all URLs found in text from stdin when there are none.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// URLs to wash, each clean URL is printed on its own line.
+    urls: Vec<String>,
+    /// Wash lines appended to file as they are written, like `tail -f`.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["urls", "lines"])]
+    follow: Option<PathBuf>,
+    /// Wash stdin line by line as lines arrive instead of waiting for end of input.
+    #[arg(long, conflicts_with = "urls")]
+    lines: bool,
+    /// How to unroll short links that need request to their service.
+    #[arg(long, global = true, value_enum, default_value_t = RedirectPolicy::Locally)]
+    redirect_policy: RedirectPolicy,
+    /// Mixer instance used by `--redirect-policy via-mixer`.
+    #[arg(long, global = true)]
+    mixer: Option<Url>,
+    /// Api key of private mixer instance set by `--mixer`.
+    #[arg(long, global = true, requires = "mixer")]
+    mixer_api_key: Option<String>,
+    /// SOCKS5 proxy of tor daemon used by `--redirect-policy via-tor` [default: socks5h://127.0.0.1:9050].
+    #[arg(long, global = true)]
+    tor_proxy: Option<Url>,
+    /// Resolve domains of unrolled short links with DNS over HTTPS of provider, instead of system resolver.
+    #[arg(long, global = true, value_enum)]
+    doh: Option<Doh>,
+    /// Output format.
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    /// Report params that look like trackers on URLs without rules (JSON format only).
+    #[arg(long, global = true)]
+    detect_trackers: bool,
+    /// Look for redirect scripts in pages of short links that do not redirect otherwise.
+    #[arg(long, global = true)]
+    script_redirects: bool,
+    /// Refuse short links redirecting off their own site, unless rule allows destination site.
+    #[arg(long, global = true)]
+    same_site_redirects: bool,
+    /// Leave URLs inside markdown code spans and fenced code blocks unchanged.
+    #[arg(long, global = true)]
+    skip_code: bool,
+    /// JSON file with custom rules, merged with built-in ones.
+    #[arg(long, global = true, value_name = "FILE")]
+    rules: Option<PathBuf>,
+    /// Apply every rule matching URL in order, instead of only the first one.
+    #[arg(long, global = true)]
+    all_matching_rules: bool,
+    /// Remove common cross-site trackers (`utm_*`, `fbclid`, `gclid`...) from URLs without rules.
+    #[arg(long, global = true)]
+    universal_trackers: bool,
+    /// Sort query params of every URL by name, so the same link always yields the same output.
+    #[arg(long, global = true)]
+    sort_params: bool,
+    /// Never wash URLs on this domain or its subdomains, can be repeated.
+    #[arg(long = "ignore-domain", global = true, value_name = "DOMAIN")]
+    ignored_domains: Vec<String>,
+    /// Wash only URLs on this domain or its subdomains, can be repeated.
+    #[arg(long = "only-domain", global = true, value_name = "DOMAIN")]
+    only_domains: Vec<String>,
+    /// Also wash URLs with this scheme (e.g. app deep links embedding http URLs), can be repeated.
+    #[arg(long = "scheme", global = true, value_name = "SCHEME")]
+    additional_schemes: Vec<String>,
+    /// Also wash URLs nested in query params, e.g. in `next` param of login pages.
+    #[arg(long, global = true)]
+    nested_urls: bool,
+    /// Unroll links of known URL shorteners without rules (bit.ly, t.co, ...).
+    #[arg(long, global = true)]
+    shorteners: bool,
+    /// With --shorteners, also unroll links that look short, e.g. https://xy.io/aB3dE.
+    #[arg(long, global = true, requires = "shorteners")]
+    detect_shorteners: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Wash URLs inside files matching glob patterns.
+    Fix(fix::FixArgs),
+    /// Run rules over a corpus of URLs, reporting throughput, hits per rule and unmatched URLs.
+    Bench(bench::BenchArgs),
+    /// Manage signed directory of public mixer instances.
+    Directory(directory::DirectoryArgs),
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum RedirectPolicy {
+    /// Do not unroll short links.
+    Ignore,
+    /// Unroll short links from this machine.
+    Locally,
+    /// Unroll short links using mixer instance.
+    ViaMixer,
+    /// Unroll short links through local tor daemon.
+    ViaTor,
+    /// Unroll short links from this machine, using mixer instance if it fails.
+    LocallyThenMixer,
+    /// Unroll short links using mixer instance, from this machine if mixer fails.
+    MixerThenLocally,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum Doh {
+    Cloudflare,
+    Google,
+    Quad9,
+}
+
+impl From<Doh> for DohProvider {
+    fn from(doh: Doh) -> Self {
+        match doh {
+            Doh::Cloudflare => DohProvider::Cloudflare,
+            Doh::Google => DohProvider::Google,
+            Doh::Quad9 => DohProvider::Quad9,
+        }
+    }
+}
+
+impl From<RedirectPolicy> for RedirectWashPolicy {
+    fn from(policy: RedirectPolicy) -> Self {
+        match policy {
+            RedirectPolicy::Ignore => RedirectWashPolicy::Ignore,
+            RedirectPolicy::Locally => RedirectWashPolicy::Locally,
+            RedirectPolicy::ViaMixer => RedirectWashPolicy::ViaMixer,
+            RedirectPolicy::ViaTor => RedirectWashPolicy::ViaTor,
+            RedirectPolicy::LocallyThenMixer => RedirectWashPolicy::LocallyThenMixer,
+            RedirectPolicy::MixerThenLocally => RedirectWashPolicy::MixerThenLocally,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+
+    let args = Args::parse();
+    exit_code(run(args).await)
+}
+
+/// Fails if some of the URLs could not be washed or command failed.
+fn exit_code(result: anyhow::Result<bool>) -> ExitCode {
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("urlwash: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Returns false if some of the URLs could not be washed.
+async fn run(args: Args) -> anyhow::Result<bool> {
+    let policy = args.redirect_policy;
+    if args.mixer.is_none() {
+        if let Some(name) = match policy {
+            RedirectPolicy::ViaMixer => Some("via-mixer"),
+            RedirectPolicy::LocallyThenMixer => Some("locally-then-mixer"),
+            RedirectPolicy::MixerThenLocally => Some("mixer-then-locally"),
+            _ => None,
+        } {
+            return Err(anyhow!("--mixer is required by --redirect-policy {name}"));
+        }
+    }
+    let rule_layer = args.rules.as_deref().map(rule_file::load).transpose()?;
+    let config = washer_config(&args, rule_layer.as_ref());
+    let mut url_washer = UrlWasher::builder(config.clone()).build()?;
+    if let Some(rule_layer) = &rule_layer {
+        let rule_set = rule_layers::compose(&[RuleLayer::builtin(), rule_layer.clone()]);
+        for conflict in &rule_set.conflicts {
+            eprintln!("urlwash: {conflict}");
+        }
+        url_washer = url_washer.with_rules(rule_set.rules);
+    }
+    let url_washer = Arc::new(url_washer);
+    let text_washer = TextWasher {
+        url_washer: url_washer.clone(),
+        skip_code: args.skip_code,
+    };
+    match args.command {
+        Some(Command::Fix(fix_args)) => return fix::run(text_washer, args.format, fix_args).await,
+        Some(Command::Bench(bench_args)) => {
+            return bench::run(&config, rule_layer, args.format, bench_args).await
+        }
+        Some(Command::Directory(directory_args)) => return directory::run(directory_args).await,
+        None => {}
+    }
+    if let Some(path) = args.follow {
+        follow::wash_followed_file(text_washer, args.format, path).await?;
+        return Ok(true);
+    }
+    if args.lines {
+        follow::wash_stdin_lines(text_washer, args.format).await?;
+        return Ok(true);
+    }
+    if args.urls.is_empty() {
+        wash_stdin(text_washer, args.format).await?;
+        return Ok(true);
+    }
+
+    let mut all_washed = true;
+    for dirty in &args.urls {
+        match wash_url(&url_washer, dirty).await {
+            Ok(report) if args.format == OutputFormat::Json => report::print(&report),
+            Ok(report) => println!("{}", report.cleaned),
+            Err(err) => {
+                eprintln!("urlwash: could not wash '{dirty}': {err:#}");
+                println!("{dirty}");
+                all_washed = false;
+            }
+        }
+    }
+    Ok(all_washed)
+}
+
+fn washer_config(args: &Args, rule_layer: Option<&RuleLayer>) -> UrlWasherConfig {
+    let mut config = UrlWasherConfig {
+        mixer_instance: args.mixer.clone(),
+        mixer_api_key: args.mixer_api_key.clone(),
+        detect_unknown_trackers: args.detect_trackers,
+        detect_script_redirects: args.script_redirects,
+        same_site_redirects: args.same_site_redirects,
+        dns_over_https: args.doh.map(Into::into),
+        apply_all_matching_rules: args.all_matching_rules,
+        strip_universal_trackers: args.universal_trackers,
+        ignored_domains: args.ignored_domains.clone(),
+        only_domains: args.only_domains.clone(),
+        additional_schemes: args.additional_schemes.clone(),
+        wash_nested_urls: args.nested_urls,
+        shorteners: ShortenersConfig {
+            enabled: args.shorteners,
+            detect_unknown: args.detect_shorteners,
+            ..Default::default()
+        },
+        sort_query_params: args.sort_params,
+        ..Default::default()
+    };
+    if let Some(tor_proxy) = &args.tor_proxy {
+        config.tor_proxy = tor_proxy.clone();
+    }
+    config
+        .redirect_policy
+        .iter_mut()
+        .for_each(|(_, redirect_policy)| *redirect_policy = args.redirect_policy.into());
+    if let Some(rule_layer) = rule_layer {
+        config.set_redirect_policy(&rule_layer.rules, args.redirect_policy.into());
+    }
+    config
+}
+
+async fn wash_url(url_washer: &UrlWasher, dirty: &str) -> anyhow::Result<WashReport> {
+    let url = Url::parse(dirty).context("invalid url")?;
+    url_washer.wash_with_report(&url).await
+}
+
+async fn wash_stdin(text_washer: TextWasher, format: OutputFormat) -> anyhow::Result<()> {
+    let mut dirty_text = String::new();
+    io::stdin()
+        .read_to_string(&mut dirty_text)
+        .await
+        .context("read stdin")?;
+    if format == OutputFormat::Json {
+        let washed = text_washer.wash_detailed(&dirty_text).await;
+        for washed_url in &washed.washed_urls {
+            report::print(&washed_url.report);
+        }
+        for unchanged in &washed.possible_trackers {
+            report::print(&report::possible_trackers_report(unchanged));
+        }
+        return Ok(());
+    }
+    let clean_text = text_washer.wash(&dirty_text).await;
+    let mut stdout = io::stdout();
+    stdout
+        .write_all(clean_text.as_bytes())
+        .await
+        .context("write stdout")?;
+    stdout.flush().await.context("flush stdout")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::ExitCode;
+
+    use anyhow::anyhow;
+    use clap::{CommandFactory, Parser};
+    use urlwasher::RedirectWashPolicy;
+
+    use super::{exit_code, run, washer_config, Args};
+
+    #[test]
+    fn verifies_args() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn rejects_conflicting_flags() {
+        for args in [
+            &["urlwash", "--follow", "chat.log", "https://youtu.be/a"][..],
+            &["urlwash", "--follow", "chat.log", "--lines"],
+            &["urlwash", "--lines", "https://youtu.be/a"],
+            &["urlwash", "--mixer-api-key", "key"],
+            &["urlwash", "--detect-shorteners"],
+        ] {
+            assert!(Args::try_parse_from(args).is_err(), "{args:?}");
+        }
+    }
+
+    #[test]
+    fn configures_washer_from_flags() {
+        let args = Args::parse_from([
+            "urlwash",
+            "--redirect-policy",
+            "via-tor",
+            "--sort-params",
+            "--ignore-domain",
+            "intranet.example",
+            "--shorteners",
+            "--detect-shorteners",
+        ]);
+        let config = washer_config(&args, None);
+        assert!(!config.redirect_policy.is_empty());
+        assert!(config
+            .redirect_policy
+            .values()
+            .all(|policy| *policy == RedirectWashPolicy::ViaTor));
+        assert!(config.sort_query_params);
+        assert_eq!(config.ignored_domains, vec!["intranet.example"]);
+        assert!(config.shorteners.enabled && config.shorteners.detect_unknown);
+    }
+
+    #[tokio::test]
+    async fn requires_mixer_for_mixer_policies() {
+        for policy in ["via-mixer", "locally-then-mixer", "mixer-then-locally"] {
+            let args =
+                Args::parse_from(["urlwash", "--redirect-policy", policy, "https://youtu.be/a"]);
+            let err = run(args).await.unwrap_err();
+            assert!(err.to_string().contains("--mixer is required"), "{err}");
+        }
+    }
+
+    #[tokio::test]
+    async fn fails_if_some_urls_could_not_be_washed() {
+        let args = Args::parse_from(["urlwash", "https://youtu.be/a?si=x", "https://x.com/a?s=1"]);
+        assert!(run(args).await.unwrap());
+
+        let args = Args::parse_from(["urlwash", "https://youtu.be/a?si=x", "not a url"]);
+        assert!(!run(args).await.unwrap());
+    }
+
+    #[test]
+    fn exits_with_failure_unless_all_washed() {
+        assert_eq!(exit_code(Ok(true)), ExitCode::SUCCESS);
+        assert_eq!(exit_code(Ok(false)), ExitCode::FAILURE);
+        assert_eq!(exit_code(Err(anyhow!("broken"))), ExitCode::FAILURE);
+    }
+}
@@ -0,0 +1,91 @@
+use clap::ValueEnum;
+use urldebloater_proto::report::WashReport;
+use urlwasher::text_washer::UrlTrackers;
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputFormat {
+    /// Clean URLs or text.
+    Text,
+    /// One JSON object per washed URL describing what changed.
+    Json,
+}
+
+/// Report of URL left unchanged, listing its params that look like trackers.
+pub fn possible_trackers_report(url_trackers: &UrlTrackers) -> WashReport {
+    WashReport {
+        original: url_trackers.url.clone(),
+        cleaned: url_trackers.url.clone(),
+        possible_trackers: url_trackers
+            .trackers
+            .iter()
+            .map(|tracker| tracker.param.clone())
+            .collect(),
+        ..Default::default()
+    }
+}
+
+pub fn print(report: &WashReport) {
+    match serde_json::to_string(report) {
+        Ok(json) => println!("{json}"),
+        Err(err) => eprintln!("urlwash: could not serialize report: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use urldebloater_proto::report::WashReport;
+    use urlwasher::{
+        analyzer::{PossibleTracker, TrackerHint},
+        text_washer::UrlTrackers,
+    };
+
+    use super::possible_trackers_report;
+
+    #[test]
+    fn serializes_report_without_empty_optional_fields() {
+        let report = WashReport {
+            original: "https://x.com/a/status/1?s=46".to_string(),
+            cleaned: "https://x.com/a/status/1".to_string(),
+            rule: Some("twitter.com".to_string()),
+            removed_params: vec!["s".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"original":"https://x.com/a/status/1?s=46","cleaned":"https://x.com/a/status/1","rule":"twitter.com","removed_params":["s"],"resolution_hops":0}"#
+        );
+    }
+
+    #[test]
+    fn serializes_file_and_programs_of_report() {
+        let report = WashReport {
+            file: Some("docs/index.md".to_string()),
+            original: "https://vm.tiktok.com/a".to_string(),
+            cleaned: "https://www.tiktok.com/@b/video/1".to_string(),
+            rule: Some("vm.tiktok.com".to_string()),
+            resolution_hops: 2,
+            programs: vec!["resolve_redirection".to_string()],
+            cached: true,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.starts_with(r#"{"file":"docs/index.md","#), "{json}");
+        assert!(json
+            .ends_with(r#""resolution_hops":2,"programs":["resolve_redirection"],"cached":true}"#));
+        assert_eq!(serde_json::from_str::<WashReport>(&json).unwrap(), report);
+    }
+
+    #[test]
+    fn reports_possible_trackers_of_unchanged_url() {
+        let report = possible_trackers_report(&UrlTrackers {
+            url: "https://shop.example/item?ref_id=a8f3e9c2b7d14f06".to_string(),
+            trackers: vec![PossibleTracker {
+                param: "ref_id".to_string(),
+                hint: TrackerHint::HighEntropy,
+            }],
+        });
+        assert_eq!(report.original, report.cleaned);
+        assert_eq!(report.rule, None);
+        assert_eq!(report.possible_trackers, vec!["ref_id"]);
+    }
+}
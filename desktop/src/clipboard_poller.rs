@@ -1,17 +1,28 @@
-use std::time::Duration;
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+    time::Duration,
+};
 use tokio::time::sleep;
 
+/// How many of our own recent writes are remembered.
+const OWN_WRITES_CAPACITY: usize = 16;
+
 pub struct ClipboardPoller {
     last_text: String,
+    /// Fingerprints of texts written by [`ClipboardPoller::set_text`].
+    own_writes: VecDeque<u64>,
 }
 
 impl ClipboardPoller {
     pub fn new() -> ClipboardPoller {
         Self {
             last_text: String::new(),
+            own_writes: VecDeque::with_capacity(OWN_WRITES_CAPACITY),
         }
     }
 
+    /// Waits for text copied by something else than this poller.
     pub async fn poll(&mut self, arboard: &mut arboard::Clipboard) -> Result<&str, arboard::Error> {
         loop {
             sleep(Duration::from_millis(200)).await;
@@ -20,8 +31,12 @@ impl ClipboardPoller {
                 Err(arboard::Error::ContentNotAvailable) => continue,
                 Err(err) => return Err(err),
             };
-            if self.last_text != new_text {
-                self.last_text = new_text;
+            if self.last_text == new_text {
+                continue;
+            }
+            let written_by_us = self.own_writes.contains(&fingerprint(&new_text));
+            self.last_text = new_text;
+            if !written_by_us {
                 return Ok(&self.last_text);
             }
         }
@@ -32,7 +47,21 @@ impl ClipboardPoller {
         arboard: &mut arboard::Clipboard,
         text: String,
     ) -> Result<(), arboard::Error> {
+        if self.own_writes.len() == OWN_WRITES_CAPACITY {
+            self.own_writes.pop_front();
+        }
+        self.own_writes.push_back(fingerprint(&text));
         self.last_text = text;
         arboard.set_text(&self.last_text)
     }
 }
+
+/// Hash of text that survives normalization done by clipboard managers
+/// and other software (line endings, trailing whitespace).
+fn fingerprint(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for line in text.trim_end().lines() {
+        line.trim_end().hash(&mut hasher);
+    }
+    hasher.finish()
+}
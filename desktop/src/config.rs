@@ -2,7 +2,12 @@ use anyhow::Context;
 use futures::Future;
 use serde::{Deserialize, Serialize};
 use tokio::{fs, time::Instant};
-use urlwasher::UrlWasherConfig;
+use urlwasher::{
+    rule_layers::{self, RuleLayer},
+    DirtyUrlRule, UrlWasherConfig, WashingProgram,
+};
+
+use crate::mixer_directory::MixerDirectoryConfig;
 
 const CONFIG_FILE: &str = "config.json";
 
@@ -12,6 +17,69 @@ pub struct AppConfig {
     pub enable_clipboard_patcher: bool,
     #[serde(skip)]
     pub clipboard_patcher_paused_until: Option<Instant>,
+    /// Config window geometry from when it was last closed.
+    #[serde(default)]
+    pub window: WindowGeometry,
+    #[serde(default)]
+    pub config_tab: ConfigTab,
+    /// Rules created by user, e.g. from possible trackers.
+    #[serde(default)]
+    pub custom_rules: Vec<CustomRule>,
+    /// Enables mixer instance picker, instance url is typed by hand if not set.
+    #[serde(default)]
+    pub mixer_directory: Option<MixerDirectoryConfig>,
+    /// Hotkey washing link under mouse cursor, e.g. `ctrl+alt+KeyL`. Disabled if not set.
+    #[serde(default)]
+    pub pointed_link_hotkey: Option<String>,
+}
+
+impl AppConfig {
+    /// Built-in rules with custom ones on top.
+    pub fn rules(&self) -> Vec<DirtyUrlRule> {
+        let custom = self.custom_rules.iter().map(CustomRule::to_rule).collect();
+        rule_layers::compose(&[RuleLayer::builtin(), RuleLayer::new("user", custom)]).rules
+    }
+}
+
+/// Removes params of urls on domain.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct CustomRule {
+    pub domain: String,
+    pub params: Vec<String>,
+}
+
+impl CustomRule {
+    fn to_rule(&self) -> DirtyUrlRule {
+        let mut rule = DirtyUrlRule::default();
+        rule.name = format!("custom {}", self.domain);
+        rule.domains = vec![self.domain.clone()];
+        rule.washing_programs = vec![WashingProgram::RemoveSomeParams(self.params.clone())];
+        rule
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    /// Outer position of window, chosen by OS if not set.
+    pub position: Option<[f32; 2]>,
+    pub size: [f32; 2],
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self {
+            position: None,
+            size: [620.0, 340.0],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+pub enum ConfigTab {
+    #[default]
+    Desktop,
+    Redirects,
+    History,
 }
 
 impl Default for AppConfig {
@@ -20,13 +88,21 @@ impl Default for AppConfig {
             url_washer: UrlWasherConfig::default(),
             enable_clipboard_patcher: true,
             clipboard_patcher_paused_until: None,
+            window: WindowGeometry::default(),
+            config_tab: ConfigTab::default(),
+            custom_rules: Vec::new(),
+            mixer_directory: None,
+            pointed_link_hotkey: None,
         }
     }
 }
 
 pub async fn from_file() -> anyhow::Result<AppConfig> {
     let bytes = fs::read(CONFIG_FILE).await.context("read file")?;
-    let config = serde_json::from_slice(&bytes).context("deserialize config")?;
+    let mut config: AppConfig = serde_json::from_slice(&bytes).context("deserialize config")?;
+    // policies of older configs are keyed by domain
+    let rules = config.rules();
+    config.url_washer.migrate_redirect_policy(&rules);
     Ok(config)
 }
 
@@ -1,6 +1,11 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 use eframe::egui;
+use global_hotkey::hotkey::HotKey;
 use notify_rust::Notification;
 use tracing::{debug, error};
 use tray_icon::{
@@ -9,15 +14,34 @@ use tray_icon::{
 };
 use url::Url;
 use urlwasher::{
-    rule_set, RedirectWashPolicy, RuleName, UrlWasherConfig, WashingProgram, PUBLIC_MIXER_INSTANCE,
+    frontends::FrontendService, rule_set, RedirectWashPolicy, RuleName, WashingProgram,
+    PUBLIC_MIXER_INSTANCE,
 };
 
-use crate::{AppConfig, AppStateFlow, APP_NAME};
+use crate::{
+    config::{ConfigTab, CustomRule, WindowGeometry},
+    mixer_directory::{self, ListedInstance, MixerDirectoryConfig},
+    AppConfig, AppStateFlow, APP_NAME,
+};
 
 pub struct ConfigWindow {
     hide: bool,
     ui_config_state: UiConfigState,
     app_state_flow: AppStateFlow,
+    /// Geometry seen in last frame, persisted when window is closed,
+    /// saving it on every frame of resize would restart background jobs.
+    window_geometry: WindowGeometry,
+    /// Written by background fetch started from instance picker.
+    directory_listing: Arc<Mutex<DirectoryListing>>,
+}
+
+#[derive(Default)]
+enum DirectoryListing {
+    #[default]
+    NotFetched,
+    Fetching,
+    Fetched(Vec<ListedInstance>),
+    Failed(String),
 }
 
 #[derive(PartialEq, Eq, Clone)]
@@ -26,16 +50,83 @@ struct UiConfigState {
     redirect_policy: HashMap<RuleName, RedirectWashPolicy>,
     enable_clipboard_patcher: bool,
     auto_start: bool,
+    detect_unknown_trackers: bool,
+    strip_universal_trackers: bool,
+    /// One domain per line.
+    ignored_domains: String,
+    /// One domain per line, every domain is washed if empty.
+    only_domains: String,
+    /// In order of [`FrontendService::ALL`].
+    privacy_frontends: Vec<(FrontendService, FrontendState)>,
+    /// Applied to config only when empty or valid, so typing it does not register partial hotkeys.
+    pointed_link_hotkey: String,
+    tab: ConfigTab,
+}
+
+/// Instance url is kept while frontend is disabled, so toggling it does not lose typed url.
+#[derive(PartialEq, Eq, Clone)]
+struct FrontendState {
+    enabled: bool,
+    instance: String,
+}
+
+/// Mixer explanation, shown in collapsible section so it is reachable by keyboard and screen readers.
+const MIXER_HELP: &str = "To remove tracking capabilities of short links like https://vm.tiktok.com/PerUserId \
+we need request target server (in this case - tiktok) to unroll it.\n\
+\
+You can do this from your local network, but there is a risk that they will catch you by correlating your IP address.\n\
+\n\
+This option allows you to resolve these links via service hosted on other network.\n\
+⚠ It sends url to third party person if you don't host mixer yourself ⚠ (Not so scary for TikTok videos tho) \
+";
+
+/// Larger controls and spacing than egui defaults, since config window is also the main ui
+/// for people without tray.
+pub fn configure_style(ctx: &egui::Context) {
+    let mut style = (*ctx.style()).clone();
+    style.spacing.interact_size.y = 28.0;
+    style.spacing.button_padding = egui::vec2(10.0, 5.0);
+    style.spacing.item_spacing = egui::vec2(10.0, 8.0);
+    style.spacing.icon_width = 20.0;
+    style.spacing.icon_width_inner = 12.0;
+    ctx.set_style(style);
 }
 
 fn apply_ui_config(app_config: &mut AppConfig, ui_config: &UiConfigState) {
-    app_config.url_washer = UrlWasherConfig {
-        mixer_instance: Url::parse(&ui_config.mixer_instance)
-            .map(Some)
-            .unwrap_or(None),
-        redirect_policy: ui_config.redirect_policy.clone(),
-    };
+    app_config.url_washer.mixer_instance = Url::parse(&ui_config.mixer_instance)
+        .map(Some)
+        .unwrap_or(None);
+    app_config.url_washer.redirect_policy = ui_config.redirect_policy.clone();
     app_config.enable_clipboard_patcher = ui_config.enable_clipboard_patcher;
+    app_config.url_washer.detect_unknown_trackers = ui_config.detect_unknown_trackers;
+    app_config.url_washer.strip_universal_trackers = ui_config.strip_universal_trackers;
+    app_config.url_washer.ignored_domains = domain_lines(&ui_config.ignored_domains);
+    app_config.url_washer.only_domains = domain_lines(&ui_config.only_domains);
+    app_config.url_washer.privacy_frontends = ui_config
+        .privacy_frontends
+        .iter()
+        .filter(|(_, frontend)| frontend.enabled)
+        .filter_map(|(service, frontend)| {
+            Url::parse(&frontend.instance)
+                .ok()
+                .map(|instance| (*service, instance))
+        })
+        .collect();
+    let hotkey = ui_config.pointed_link_hotkey.trim();
+    if hotkey.is_empty() {
+        app_config.pointed_link_hotkey = None;
+    } else if HotKey::from_str(hotkey).is_ok() {
+        app_config.pointed_link_hotkey = Some(hotkey.to_string());
+    }
+    app_config.config_tab = ui_config.tab;
+}
+
+fn domain_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|domain| !domain.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect()
 }
 
 impl ConfigWindow {
@@ -52,17 +143,38 @@ impl ConfigWindow {
             .auto_launch
             .is_enabled()
             .expect("Could not check if autostart is enabled");
+        let privacy_frontends = FrontendService::ALL
+            .into_iter()
+            .map(|service| {
+                let instance = config.url_washer.privacy_frontends.get(&service);
+                let frontend = FrontendState {
+                    enabled: instance.is_some(),
+                    instance: instance.map(|url| url.to_string()).unwrap_or_default(),
+                };
+                (service, frontend)
+            })
+            .collect();
         let ui_config_state = UiConfigState {
             mixer_instance,
             redirect_policy: config.url_washer.redirect_policy.clone(),
             enable_clipboard_patcher: config.enable_clipboard_patcher,
             auto_start,
+            detect_unknown_trackers: config.url_washer.detect_unknown_trackers,
+            strip_universal_trackers: config.url_washer.strip_universal_trackers,
+            ignored_domains: config.url_washer.ignored_domains.join("\n"),
+            only_domains: config.url_washer.only_domains.join("\n"),
+            privacy_frontends,
+            pointed_link_hotkey: config.pointed_link_hotkey.clone().unwrap_or_default(),
+            tab: config.config_tab,
         };
+        let window_geometry = config.window;
         drop(app_state);
         Self {
             hide: !open_config_window,
             ui_config_state,
             app_state_flow,
+            window_geometry,
+            directory_listing: Default::default(),
         }
     }
 }
@@ -73,77 +185,391 @@ impl eframe::App for ConfigWindow {
             self.hide = false;
             frame.set_visible(false);
         }
+        let window_info = &frame.info().window_info;
+        self.window_geometry = WindowGeometry {
+            position: window_info
+                .position
+                .map(|position| [position.x, position.y]),
+            size: [window_info.size.x, window_info.size.y],
+        };
+        // Escape leaves focused text field first, so it minimizes only when nothing is focused.
+        if ctx.input(|input| input.key_pressed(egui::Key::Escape))
+            && ctx.memory(|memory| memory.focus().is_none())
+        {
+            self.minimize_to_tray();
+        }
 
         let previous_config = self.ui_config_state.clone();
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Desktop settings");
-            ui.checkbox(&mut self.ui_config_state.enable_clipboard_patcher, "Automatically debloat URLs in your clipboard");
-            if ui.checkbox(&mut self.ui_config_state.auto_start, "Start debloater with system startup").clicked() {
-                let auto_launch = &self.app_state_flow.current().auto_launch;
-                if self.ui_config_state.auto_start {
-                    auto_launch.enable().expect("Could not enable auto start");
-                } else {
-                    auto_launch.disable().expect("Could not disable auto start");
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.ui_config_state.tab, ConfigTab::Desktop, "Desktop");
+                ui.selectable_value(
+                    &mut self.ui_config_state.tab,
+                    ConfigTab::Redirects,
+                    "Short links",
+                );
+                ui.selectable_value(&mut self.ui_config_state.tab, ConfigTab::History, "History");
+            });
+            ui.separator();
+            match self.ui_config_state.tab {
+                ConfigTab::Desktop => self.desktop_tab(ui),
+                ConfigTab::Redirects => self.redirects_tab(ui),
+                ConfigTab::History => self.history_tab(ui),
+            }
+        });
+
+        if previous_config != self.ui_config_state {
+            debug!("Config changed.");
+            self.app_state_flow.modify_config(|config| {
+                apply_ui_config(config, &self.ui_config_state);
+            });
+        }
+    }
+
+    fn on_close_event(&mut self) -> bool {
+        self.minimize_to_tray();
+        false
+    }
+}
+
+impl ConfigWindow {
+    fn desktop_tab(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Desktop settings");
+        ui.checkbox(
+            &mut self.ui_config_state.enable_clipboard_patcher,
+            "Automatically debloat URLs in your clipboard",
+        );
+        if ui
+            .checkbox(
+                &mut self.ui_config_state.auto_start,
+                "Start debloater with system startup",
+            )
+            .clicked()
+        {
+            let auto_launch = &self.app_state_flow.current().auto_launch;
+            if self.ui_config_state.auto_start {
+                auto_launch.enable().expect("Could not enable auto start");
+            } else {
+                auto_launch.disable().expect("Could not disable auto start");
+            }
+        }
+        ui.checkbox(
+            &mut self.ui_config_state.strip_universal_trackers,
+            "Remove common trackers (utm_*, fbclid, gclid...) on sites without rules",
+        );
+        ui.checkbox(
+            &mut self.ui_config_state.detect_unknown_trackers,
+            "Detect possible trackers on sites without rules",
+        );
+        if self.ui_config_state.detect_unknown_trackers {
+            self.possible_trackers(ui);
+        }
+        self.pointed_link_hotkey(ui);
+        self.custom_rules(ui);
+        self.skipped_domains(ui);
+        self.privacy_frontends(ui);
+    }
+
+    fn pointed_link_hotkey(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Hotkey debloating link under cursor:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.ui_config_state.pointed_link_hotkey)
+                    .hint_text("e.g. ctrl+alt+KeyL"),
+            );
+        });
+        let hotkey = self.ui_config_state.pointed_link_hotkey.trim();
+        if !hotkey.is_empty() && HotKey::from_str(hotkey).is_err() {
+            ui.colored_label(ui.visuals().error_fg_color, "Invalid hotkey");
+        }
+    }
+
+    fn skipped_domains(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Domains");
+        ui.label("Never debloat URLs on these domains and their subdomains (one per line):");
+        ui.text_edit_multiline(&mut self.ui_config_state.ignored_domains);
+        ui.label("Debloat only URLs on these domains (one per line, all domains if empty):");
+        ui.text_edit_multiline(&mut self.ui_config_state.only_domains);
+    }
+
+    fn privacy_frontends(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Privacy frontends");
+        ui.label("Open washed links in privacy friendly frontend instances of your choice.");
+        for (service, frontend) in &mut self.ui_config_state.privacy_frontends {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut frontend.enabled, service.to_string());
+                ui.add(
+                    egui::TextEdit::singleline(&mut frontend.instance)
+                        .hint_text(format!("{} instance url", service.frontend_name())),
+                );
+            });
+            if frontend.enabled {
+                if let Err(err) = Url::parse(&frontend.instance) {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("Invalid url: {err}"));
                 }
             }
+        }
+    }
 
-            ui.separator();
-            {
-                ui.heading("Per user generated links")
-                    .on_hover_text("Section for links that cannot be anonymised without requesting service server.");
+    fn possible_trackers(&mut self, ui: &mut egui::Ui) {
+        let possible_trackers = self
+            .app_state_flow
+            .current()
+            .possible_trackers
+            .read()
+            .unwrap()
+            .clone();
+        if possible_trackers.is_empty() {
+            return;
+        }
+        ui.heading("Possible trackers");
+        for url_trackers in possible_trackers {
+            let Some(domain) = Url::parse(&url_trackers.url)
+                .ok()
+                .and_then(|url| url.domain().map(str::to_string))
+            else {
+                continue;
+            };
+            ui.label(&url_trackers.url);
+            ui.horizontal_wrapped(|ui| {
+                for tracker in &url_trackers.trackers {
+                    ui.label(format!("{} ({})", tracker.param, tracker.hint));
+                }
+                let create_rule = ui
+                    .button("Create rule")
+                    .on_hover_text(format!("Always remove these params from {domain} links"));
+                let dismiss = ui.button("Dismiss");
+                if create_rule.clicked() {
+                    let params = url_trackers
+                        .trackers
+                        .iter()
+                        .map(|tracker| tracker.param.clone())
+                        .collect::<Vec<_>>();
+                    self.app_state_flow.modify_config(|config| {
+                        match config
+                            .custom_rules
+                            .iter_mut()
+                            .find(|rule| rule.domain == domain)
+                        {
+                            Some(rule) => rule.params.extend(
+                                params
+                                    .into_iter()
+                                    .filter(|param| !rule.params.contains(param)),
+                            ),
+                            None => config.custom_rules.push(CustomRule { domain, params }),
+                        }
+                    });
+                }
+                if create_rule.clicked() || dismiss.clicked() {
+                    self.app_state_flow
+                        .current()
+                        .possible_trackers
+                        .write()
+                        .unwrap()
+                        .retain(|existing| existing.url != url_trackers.url);
+                }
+            });
+        }
+    }
 
-                ui.horizontal(|ui| {
-                    let name_label = ui
-                        .label("Mixer instance url: ")
-                        .on_hover_text("To remove tracking capabilities of short links like https://vm.tiktok.com/PerUserId \
-                        we need request target server (in this case - tiktok) to unroll it.\n\
-                        \
-                        You can do this from your local network, but there is a risk that they will catch you by correlating your IP address.\n\
-                        \n\
-                        This option allows you to resolve these links via service hosted on other network.\n\
-                        ⚠ It sends url to third party person if you don't host mixer yourself ⚠ (Not so scary for TikTok videos tho) \
-                        ");
-                    ui.text_edit_singleline(&mut self.ui_config_state.mixer_instance)
-                        .labelled_by(name_label.id);
-                    if ui.button("use public instance").clicked() {
-                        self.ui_config_state.mixer_instance = PUBLIC_MIXER_INSTANCE.to_string();
+    fn custom_rules(&mut self, ui: &mut egui::Ui) {
+        let custom_rules = self.app_state_flow.current().config.custom_rules.clone();
+        if custom_rules.is_empty() {
+            return;
+        }
+        ui.heading("Custom rules");
+        for rule in custom_rules {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(format!(
+                    "{}: remove {}",
+                    rule.domain,
+                    rule.params.join(", ")
+                ));
+                if ui.button("Remove").clicked() {
+                    self.app_state_flow.modify_config(|config| {
+                        config.custom_rules.retain(|existing| *existing != rule)
+                    });
+                }
+            });
+        }
+    }
+
+    fn history_tab(&mut self, ui: &mut egui::Ui) {
+        let wash_history = self
+            .app_state_flow
+            .current()
+            .wash_history
+            .read()
+            .unwrap()
+            .clone();
+        ui.heading("Washed links");
+        if wash_history.is_empty() {
+            ui.label("Links washed since app start will show up here.");
+            return;
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for washed_url in &wash_history {
+                ui.label(&washed_url.original);
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(format!("→ {}", washed_url.washed));
+                    if ui.button("Copy").clicked() {
+                        ui.output_mut(|output| output.copied_text = washed_url.washed.clone());
                     }
                 });
-                if !self.ui_config_state.mixer_instance.is_empty() {
-                    if let Err(err) = Url::parse(&self.ui_config_state.mixer_instance) {
-                        ui.colored_label(ui.visuals().error_fg_color, format!("Invalid url: {err}"));
-                    }
-                }
+                ui.separator();
+            }
+        });
+    }
 
-                for rule in rule_set().iter().filter(|rule| rule.washing_programs.contains(&WashingProgram::ResolveRedirection)) {
-                    let policy = match self.ui_config_state.redirect_policy.get_mut(&rule.name) {
-                        Some(policy) => policy,
-                        None => {
-                            self.ui_config_state.redirect_policy.entry(rule.name.clone()).or_insert(RedirectWashPolicy::Ignore)
-                        },
-                    };
-
-                    egui::ComboBox::from_label(rule.domains.join(", "))
-                        .selected_text(policy.to_string())
-                        .show_ui(ui, |ui| {
-                            ui.selectable_value(policy, RedirectWashPolicy::Ignore, "ignore");
-                            ui.selectable_value(policy, RedirectWashPolicy::Locally, "locally");
-                            ui.selectable_value(policy, RedirectWashPolicy::ViaMixer, "via mixer");
-                        });
+    fn instance_picker(&mut self, ui: &mut egui::Ui, mixer_directory: MixerDirectoryConfig) {
+        let mut fetch = false;
+        match &*self.directory_listing.lock().unwrap() {
+            DirectoryListing::NotFetched => fetch = ui.button("Fetch instances").clicked(),
+            DirectoryListing::Fetching => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Fetching instances...");
+                });
+            }
+            DirectoryListing::Failed(err) => {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("Could not fetch instances: {err}"),
+                );
+                fetch = ui.button("Retry").clicked();
+            }
+            DirectoryListing::Fetched(instances) => {
+                for instance in instances {
+                    ui.horizontal_wrapped(|ui| {
+                        if ui.button("Use").clicked() {
+                            self.ui_config_state.mixer_instance = instance.entry.url.clone();
+                        }
+                        ui.label(&instance.entry.url);
+                        let latency = instance
+                            .latency
+                            .map_or("unreachable".to_string(), |latency| {
+                                format!("{} ms", latency.as_millis())
+                            });
+                        ui.label(latency);
+                        if let Some(region) = &instance.entry.region {
+                            ui.label(region);
+                        }
+                        if let Some(operator) = &instance.entry.operator {
+                            ui.label(format!("by {operator}"));
+                        }
+                    });
+                    ui.label(mixer_directory::describe_policy(&instance.entry.policy));
+                    ui.separator();
                 }
+                fetch = ui.button("Refresh").clicked();
             }
+        }
+        if fetch {
+            self.fetch_directory(ui.ctx().clone(), mixer_directory);
+        }
+    }
+
+    fn fetch_directory(&self, ctx: egui::Context, mixer_directory: MixerDirectoryConfig) {
+        *self.directory_listing.lock().unwrap() = DirectoryListing::Fetching;
+        let directory_listing = self.directory_listing.clone();
+        let url_washer = self.app_state_flow.current().text_washer.url_washer.clone();
+        tokio::spawn(async move {
+            let listing = match mixer_directory::fetch(&url_washer, &mixer_directory).await {
+                Ok(instances) => DirectoryListing::Fetched(instances),
+                Err(err) => {
+                    error!("Could not fetch mixer directory: {err:?}");
+                    DirectoryListing::Failed(format!("{err:#}"))
+                }
+            };
+            *directory_listing.lock().unwrap() = listing;
+            ctx.request_repaint();
         });
+    }
 
-        if previous_config != self.ui_config_state {
-            debug!("Config changed.");
-            self.app_state_flow.modify_config(|config| {
-                apply_ui_config(config, &self.ui_config_state);
+    fn redirects_tab(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Per user generated links");
+        ui.label("Links that cannot be anonymised without requesting service server.");
+        ui.collapsing("What is mixer?", |ui| ui.label(MIXER_HELP));
+
+        ui.horizontal(|ui| {
+            let name_label = ui.label("Mixer instance url: ").on_hover_text(MIXER_HELP);
+            ui.text_edit_singleline(&mut self.ui_config_state.mixer_instance)
+                .labelled_by(name_label.id);
+            if ui.button("use public instance").clicked() {
+                self.ui_config_state.mixer_instance = PUBLIC_MIXER_INSTANCE.to_string();
+            }
+        });
+        if !self.ui_config_state.mixer_instance.is_empty() {
+            if let Err(err) = Url::parse(&self.ui_config_state.mixer_instance) {
+                ui.colored_label(ui.visuals().error_fg_color, format!("Invalid url: {err}"));
+            }
+        }
+        let mixer_directory = self.app_state_flow.current().config.mixer_directory.clone();
+        if let Some(mixer_directory) = mixer_directory {
+            ui.collapsing("Pick instance from directory", |ui| {
+                self.instance_picker(ui, mixer_directory)
             });
         }
+        if let Some(motd) = self
+            .app_state_flow
+            .current()
+            .mixer_motd
+            .read()
+            .unwrap()
+            .as_ref()
+        {
+            if let Some(maintenance) = &motd.maintenance {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("Mixer instance is under maintenance: {maintenance}"),
+                );
+            }
+            if let Some(message) = &motd.motd {
+                ui.label(format!("Message from mixer instance: {message}"));
+            }
+        }
+
+        for rule in rule_set().iter().filter(|rule| {
+            rule.washing_programs
+                .contains(&WashingProgram::ResolveRedirection)
+        }) {
+            let policy = match self.ui_config_state.redirect_policy.get_mut(&rule.name) {
+                Some(policy) => policy,
+                None => self
+                    .ui_config_state
+                    .redirect_policy
+                    .entry(rule.name.clone())
+                    .or_insert(RedirectWashPolicy::Ignore),
+            };
+
+            egui::ComboBox::from_label(format!("Unroll {} links", rule.domains.join(", ")))
+                .selected_text(policy.to_string())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(policy, RedirectWashPolicy::Ignore, "ignore");
+                    ui.selectable_value(policy, RedirectWashPolicy::Locally, "locally");
+                    ui.selectable_value(policy, RedirectWashPolicy::ViaMixer, "via mixer");
+                    ui.selectable_value(policy, RedirectWashPolicy::ViaTor, "via tor");
+                    ui.selectable_value(
+                        policy,
+                        RedirectWashPolicy::LocallyThenMixer,
+                        "locally, then via mixer",
+                    );
+                    ui.selectable_value(
+                        policy,
+                        RedirectWashPolicy::MixerThenLocally,
+                        "via mixer, then locally",
+                    );
+                });
+        }
     }
 
-    fn on_close_event(&mut self) -> bool {
+    fn minimize_to_tray(&mut self) {
         self.hide = true;
+        if self.app_state_flow.current().config.window != self.window_geometry {
+            let window_geometry = self.window_geometry;
+            self.app_state_flow
+                .modify_config(|config| config.window = window_geometry);
+        }
         if let Err(err) = Notification::new()
             .appname(APP_NAME)
             .summary(APP_NAME)
@@ -152,14 +578,18 @@ impl eframe::App for ConfigWindow {
         {
             error!("Could not show error notification: {err}");
         }
-        false
     }
 }
 
 pub struct TrayMenu {
-    _tray_icon: TrayIcon,
+    tray_icon: TrayIcon,
+    /// Last tooltip set on tray icon.
+    tooltip: String,
     pub wash_clipboard: MenuItem,
     pub pause_clipboard_washer: CheckMenuItem,
+    pub resume_clipboard_washer: MenuItem,
+    /// Checked while armed, until next clipboard change.
+    pub skip_next_copy: CheckMenuItem,
     pub open_config: MenuItem,
 }
 
@@ -169,11 +599,15 @@ impl TrayMenu {
         let wash_clipboard = MenuItem::new("Debloat current clipboard", true, None);
         let pause_clipboard_washer =
             CheckMenuItem::new("Pause clipboard debloater temporary", true, false, None);
+        let resume_clipboard_washer = MenuItem::new("Resume now", false, None);
+        let skip_next_copy = CheckMenuItem::new("Don't debloat next copy", true, false, None);
         let open_config = MenuItem::new("Open configuration", true, None);
         tray_menu
             .append_items(&[
                 &wash_clipboard,
                 &pause_clipboard_washer,
+                &resume_clipboard_washer,
+                &skip_next_copy,
                 &PredefinedMenuItem::separator(),
                 &open_config,
                 &PredefinedMenuItem::separator(),
@@ -196,12 +630,26 @@ impl TrayMenu {
             .build()
             .expect("Could not create tray icon");
         Self {
-            _tray_icon: tray_icon,
+            tray_icon,
+            tooltip: APP_NAME.to_string(),
             wash_clipboard,
             pause_clipboard_washer,
+            resume_clipboard_washer,
+            skip_next_copy,
             open_config,
         }
     }
+
+    /// Updates tooltip only if it changed, as every update redraws tray icon.
+    pub fn set_tooltip(&mut self, tooltip: String) {
+        if self.tooltip == tooltip {
+            return;
+        }
+        if let Err(err) = self.tray_icon.set_tooltip(Some(&tooltip)) {
+            error!("Could not set tray tooltip: {err}");
+        }
+        self.tooltip = tooltip;
+    }
 }
 
 fn load_tray_icon() -> tray_icon::Icon {
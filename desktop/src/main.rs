@@ -10,12 +10,18 @@ use anyhow::Context;
 use auto_launch::AutoLaunch;
 use config::AppConfig;
 use eframe::{egui, DetachedResult};
-use futures::{stream::FuturesUnordered, StreamExt};
+use futures::{future::BoxFuture, stream::FuturesUnordered, StreamExt};
+use global_hotkey::GlobalHotKeyEvent;
 use notify_rust::Notification;
 use std::env;
+use std::path::Path;
 use std::{
+    collections::VecDeque,
     io::{self, ErrorKind},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
     time::Duration,
 };
 use tokio::{
@@ -26,30 +32,87 @@ use tokio::{
 use tracing::{debug, error, info};
 use tracing_subscriber::EnvFilter;
 use tray_icon::menu::MenuEvent;
-use urlwasher::{text_washer::TextWasher, UrlWasher};
+use urlwasher::{
+    blocklist::{BlockedAction, BlockedDestination},
+    mixer_api::MixerMotd,
+    text_washer::{TextWasher, UrlTrackers, WashedText, WashedUrl},
+    UrlWasher,
+};
 use winit::event_loop::ControlFlow;
 
 mod clipboard_poller;
 mod config;
 mod gui;
+mod mixer_directory;
+mod pointed_link;
+mod scheme_handler;
 
 const APP_NAME: &str = "UrlDebloater";
 const CLIPBOARD_PAUSE_DURATION: Duration = Duration::from_secs(30);
+const MIXER_MOTD_REFRESH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// How many urls with possible trackers are kept for config window.
+const MAX_POSSIBLE_TRACKERS: usize = 10;
+/// How many washed urls are kept in history panel.
+const MAX_WASH_HISTORY: usize = 50;
 
 pub struct AppState {
     text_washer: TextWasher,
     config: AppConfig,
     auto_launch: AutoLaunch,
+    /// Last announcement fetched from configured mixer instance.
+    mixer_motd: Arc<RwLock<Option<MixerMotd>>>,
+    /// Recently copied urls without rule, which have params looking like trackers, newest first.
+    possible_trackers: Arc<RwLock<Vec<UrlTrackers>>>,
+    /// Urls washed in this session, newest first.
+    wash_history: Arc<RwLock<VecDeque<WashedUrl>>>,
+    /// Set from tray, clipboard patcher leaves next copied text as it is and clears it.
+    /// Not part of config, so arming it does not restart clipboard patcher.
+    skip_next_copy: Arc<AtomicBool>,
 }
 
 impl AppState {
-    pub fn new(config: AppConfig, auto_launch: AutoLaunch) -> Self {
+    pub fn new(
+        config: AppConfig,
+        auto_launch: AutoLaunch,
+        mixer_motd: Arc<RwLock<Option<MixerMotd>>>,
+        possible_trackers: Arc<RwLock<Vec<UrlTrackers>>>,
+        wash_history: Arc<RwLock<VecDeque<WashedUrl>>>,
+        skip_next_copy: Arc<AtomicBool>,
+    ) -> Self {
+        let url_washer =
+            Arc::new(UrlWasher::new(config.url_washer.clone()).with_rules(config.rules()));
+        Self::with_url_washer(
+            config,
+            auto_launch,
+            mixer_motd,
+            possible_trackers,
+            wash_history,
+            skip_next_copy,
+            url_washer,
+        )
+    }
+
+    /// `url_washer` must be created from `config.url_washer` and `config.rules()`.
+    fn with_url_washer(
+        config: AppConfig,
+        auto_launch: AutoLaunch,
+        mixer_motd: Arc<RwLock<Option<MixerMotd>>>,
+        possible_trackers: Arc<RwLock<Vec<UrlTrackers>>>,
+        wash_history: Arc<RwLock<VecDeque<WashedUrl>>>,
+        skip_next_copy: Arc<AtomicBool>,
+        url_washer: Arc<UrlWasher>,
+    ) -> Self {
         Self {
             text_washer: TextWasher {
-                url_washer: UrlWasher::new(config.url_washer.clone()),
+                url_washer,
+                skip_code: false,
             },
             config,
             auto_launch,
+            mixer_motd,
+            possible_trackers,
+            wash_history,
+            skip_next_copy,
         }
     }
 }
@@ -74,15 +137,52 @@ impl AppStateFlow {
     }
 
     pub fn modify_config(&self, apply_changes: impl FnOnce(&mut AppConfig)) {
-        let (auto_launch, config) = {
+        let (
+            auto_launch,
+            mixer_motd,
+            possible_trackers,
+            wash_history,
+            skip_next_copy,
+            config,
+            url_washer,
+        ) = {
             let current = self.current();
-            (current.auto_launch.clone(), current.config.clone())
+            (
+                current.auto_launch.clone(),
+                current.mixer_motd.clone(),
+                current.possible_trackers.clone(),
+                current.wash_history.clone(),
+                current.skip_next_copy.clone(),
+                current.config.clone(),
+                current.text_washer.url_washer.clone(),
+            )
         };
         let mut new_config = config.clone();
         apply_changes(&mut new_config);
-        let _ = self
-            .tx
-            .send(Arc::new(AppState::new(new_config, auto_launch)));
+        // Keep washer (and its cache) when only desktop settings changed.
+        let new_state = if new_config.url_washer == config.url_washer
+            && new_config.custom_rules == config.custom_rules
+        {
+            AppState::with_url_washer(
+                new_config,
+                auto_launch,
+                mixer_motd,
+                possible_trackers,
+                wash_history,
+                skip_next_copy,
+                url_washer,
+            )
+        } else {
+            AppState::new(
+                new_config,
+                auto_launch,
+                mixer_motd,
+                possible_trackers,
+                wash_history,
+                skip_next_copy,
+            )
+        };
+        let _ = self.tx.send(Arc::new(new_state));
     }
 }
 
@@ -98,7 +198,22 @@ async fn main() -> anyhow::Result<()> {
         .init();
     debug!("Hello, world!");
 
-    let started_from_autolaunch = env::args().skip(1).next() == Some(String::from(AUTOSTART_ARG));
+    let first_arg = env::args().nth(1);
+    if let Some(link) = first_arg
+        .as_deref()
+        .filter(|arg| scheme_handler::is_scheme_link(arg))
+    {
+        // Links are opened by browsers with their own working directory,
+        // while config lives next to the executable.
+        if let Some(app_dir) = env::current_exe().ok().as_deref().and_then(Path::parent) {
+            if let Err(err) = env::set_current_dir(app_dir) {
+                error!("Could not change working directory: {err:?}");
+            }
+        }
+        let config = config::from_file().await.unwrap_or_default();
+        return scheme_handler::run(link, config).await;
+    }
+    let started_from_autolaunch = first_arg.as_deref() == Some(AUTOSTART_ARG);
     let (first_launch, config) = config::from_file()
         .await
         .map(|config| (false, config))
@@ -121,7 +236,14 @@ async fn main() -> anyhow::Result<()> {
             .enable()
             .expect("Could not enable auto launch on initial debloater startup");
     }
-    let app_state = AppState::new(config, auto_launch);
+    let app_state = AppState::new(
+        config,
+        auto_launch,
+        Default::default(),
+        Default::default(),
+        Default::default(),
+        Default::default(),
+    );
     let app_state_flow = AppStateFlow::new(app_state);
     tokio::spawn(persist_config(app_state_flow.rx.clone()));
     tokio::spawn(run_background_jobs_supervisor(app_state_flow.rx.clone()));
@@ -162,23 +284,50 @@ async fn run_background_jobs_supervisor(mut state_rx: watch::Receiver<Arc<AppSta
 }
 
 async fn run_background_jobs(app_state: &AppState) {
-    let mut tasks = FuturesUnordered::new();
+    let mut tasks: FuturesUnordered<BoxFuture<'_, ()>> = FuturesUnordered::new();
 
     let config = &app_state.config;
     if config.enable_clipboard_patcher {
         let paused_until = app_state.config.clipboard_patcher_paused_until;
-        tasks.push(async move {
+        tasks.push(Box::pin(async move {
             if let Some(paused_until) = paused_until {
                 sleep_until(paused_until).await;
             }
             loop {
                 info!("Starting clipboard patcher");
-                if let Err(err) = run_clipboard_patcher(&app_state.text_washer).await {
+                if let Err(err) = run_clipboard_patcher(app_state).await {
                     error!("Could not run clipboard patcher: {err:?}.");
                 }
                 sleep(Duration::from_secs(5)).await;
             }
-        });
+        }));
+    }
+
+    if config.url_washer.mixer_instance.is_some() {
+        tasks.push(Box::pin(async move {
+            loop {
+                match app_state.text_washer.url_washer.mixer_motd().await {
+                    Ok(motd) => *app_state.mixer_motd.write().unwrap() = motd,
+                    Err(err) => error!("Could not fetch mixer motd: {err:?}"),
+                }
+                sleep(MIXER_MOTD_REFRESH_INTERVAL).await;
+            }
+        }));
+    } else {
+        *app_state.mixer_motd.write().unwrap() = None;
+    }
+
+    let blocklist = &config.url_washer.blocklist;
+    if !blocklist.feeds.is_empty() {
+        tasks.push(Box::pin(async move {
+            loop {
+                match app_state.text_washer.url_washer.refresh_blocklist().await {
+                    Ok(listed) => info!("Refreshed blocklist, {listed} domains are listed."),
+                    Err(err) => error!("Could not refresh blocklist: {err:?}"),
+                }
+                sleep(Duration::from_secs(blocklist.refresh_interval_secs)).await;
+            }
+        }));
     }
 
     if tasks.is_empty() {
@@ -188,7 +337,7 @@ async fn run_background_jobs(app_state: &AppState) {
     }
 }
 
-async fn run_clipboard_patcher(text_washer: &TextWasher) -> anyhow::Result<()> {
+async fn run_clipboard_patcher(app_state: &AppState) -> anyhow::Result<()> {
     let mut arboard = arboard::Clipboard::new().context("Could not create clipboard accessor")?;
     let mut clipboard_poller = ClipboardPoller::new();
     loop {
@@ -197,7 +346,13 @@ async fn run_clipboard_patcher(text_washer: &TextWasher) -> anyhow::Result<()> {
             .await
             .context("Could not poll clipboard")?;
         debug!("Detected clipboard change: {dirty_text}");
-        let clean_text = text_washer.wash(dirty_text).await;
+        if app_state.skip_next_copy.swap(false, Ordering::Relaxed) {
+            info!("Leaving copied text as it is, as requested from tray.");
+            continue;
+        }
+        let washed = app_state.text_washer.wash_detailed(dirty_text).await;
+        record_washed_text(app_state, &washed);
+        let clean_text = washed.text;
         if clean_text != dirty_text
             && arboard
                 .get_text()
@@ -231,23 +386,53 @@ fn run_gui(app_state_flow: AppStateFlow, open_config_window: bool) -> ! {
     let mut tray_handler = TrayHandler::new(app_state_flow.clone(), tray_event_tx);
 
     let event_loop = eframe::EventLoopBuilder::<eframe::UserEvent>::with_user_event().build();
+    let window_geometry = app_state_flow.current().config.window;
     let mut detached_app = eframe::run_detached_native(
         APP_NAME,
         &event_loop,
         eframe::NativeOptions {
-            initial_window_size: Some(egui::vec2(620.0, 340.0)),
+            initial_window_size: Some(window_geometry.size.into()),
+            initial_window_pos: window_geometry.position.map(Into::into),
             ..Default::default()
         },
         Box::new({
             let app_state_flow = app_state_flow.clone();
-            move |_cc| Box::new(ConfigWindow::new(app_state_flow, open_config_window))
+            move |cc| {
+                gui::configure_style(&cc.egui_ctx);
+                Box::new(ConfigWindow::new(app_state_flow, open_config_window))
+            }
         }),
     );
 
+    // hotkey stays registered as long as it is alive, so it's moved into event loop
+    let mut pointed_link_hotkey =
+        pointed_link::PointedLinkHotkey::new(&app_state_flow.current().config);
+
     event_loop.run(move |event, event_loop, control_flow| {
         #[cfg(not(target_os = "linux"))]
         tray_handler.update();
 
+        pointed_link_hotkey.update(&app_state_flow.current().config);
+        while let Ok(hotkey_event) = GlobalHotKeyEvent::receiver().try_recv() {
+            if !pointed_link_hotkey.is_pressed(&hotkey_event) {
+                continue;
+            }
+            info!("Debloating link under cursor...");
+            let app_state = app_state_flow.rx.borrow().to_owned();
+            tokio::spawn(async move {
+                if let Err(err) = pointed_link::wash_pointed_link(&app_state).await {
+                    error!("Could not wash link under cursor: {err:?}");
+                    if let Err(err) = Notification::new()
+                        .summary(APP_NAME)
+                        .body(&err.to_string())
+                        .show()
+                    {
+                        error!("Could not show error notification: {err}");
+                    }
+                }
+            });
+        }
+
         while let Ok(tray_event) = tray_event_rx.try_recv() {
             match tray_event {
                 TrayEvent::OpenConfig => {
@@ -281,6 +466,17 @@ fn run_gui(app_state_flow: AppStateFlow, open_config_window: bool) -> ! {
                         }
                     });
                 }
+                TrayEvent::SkipNextCopy => {
+                    app_state_flow
+                        .current()
+                        .skip_next_copy
+                        .fetch_xor(true, Ordering::Relaxed);
+                }
+                TrayEvent::ResumeClipboardWasher => {
+                    app_state_flow.modify_config(|config| {
+                        config.clipboard_patcher_paused_until = None;
+                    });
+                }
             }
         }
 
@@ -354,6 +550,10 @@ impl TrayHandler {
                 TrayEvent::WashClipboard
             } else if event_id == self.tray_menu.pause_clipboard_washer.id() {
                 TrayEvent::PauseClipboardWasher
+            } else if event_id == self.tray_menu.resume_clipboard_washer.id() {
+                TrayEvent::ResumeClipboardWasher
+            } else if event_id == self.tray_menu.skip_next_copy.id() {
+                TrayEvent::SkipNextCopy
             } else {
                 continue;
             };
@@ -362,7 +562,7 @@ impl TrayHandler {
             }
         }
 
-        update_tray_state(&self.tray_menu, &self.app_state_flow.current());
+        update_tray_state(&mut self.tray_menu, &self.app_state_flow.current());
     }
 }
 
@@ -370,21 +570,39 @@ enum TrayEvent {
     OpenConfig,
     WashClipboard,
     PauseClipboardWasher,
+    ResumeClipboardWasher,
+    SkipNextCopy,
 }
 
-fn update_tray_state(tray_menu: &TrayMenu, app_state: &AppState) {
+/// Called every few hundred milliseconds, texts change once per second while paused.
+fn update_tray_state(tray_menu: &mut TrayMenu, app_state: &AppState) {
     tray_menu
         .pause_clipboard_washer
         .set_enabled(app_state.config.enable_clipboard_patcher);
+    // rounded up, so countdown never shows 0 while still paused
+    let paused_for_secs = app_state
+        .config
+        .clipboard_patcher_paused_until
+        .map(|paused_until| paused_until.saturating_duration_since(Instant::now()))
+        .filter(|remaining| !remaining.is_zero())
+        .map(|remaining| (remaining.as_millis() as u64).div_ceil(1000))
+        .filter(|_| app_state.config.enable_clipboard_patcher);
+    tray_menu
+        .resume_clipboard_washer
+        .set_enabled(paused_for_secs.is_some());
+    let skip_next_copy = app_state.skip_next_copy.load(Ordering::Relaxed);
+    tray_menu
+        .skip_next_copy
+        .set_enabled(app_state.config.enable_clipboard_patcher && paused_for_secs.is_none());
+    tray_menu.skip_next_copy.set_checked(skip_next_copy);
+    tray_menu.set_tooltip(match paused_for_secs {
+        Some(secs) => format!("{APP_NAME} - paused for {secs} sec."),
+        None if skip_next_copy => format!("{APP_NAME} - next copy is kept as it is"),
+        None => APP_NAME.to_string(),
+    });
     let (active, new_text) = if app_state.config.enable_clipboard_patcher {
-        match app_state.config.clipboard_patcher_paused_until {
-            Some(paused_until) if paused_until > Instant::now() => (
-                true,
-                format!(
-                    "Clipboard debloater paused for {} sec.",
-                    paused_until.duration_since(Instant::now()).as_secs()
-                ),
-            ),
+        match paused_for_secs {
+            Some(secs) => (true, format!("Clipboard debloater paused for {secs} sec.")),
             _ => (
                 false,
                 format!(
@@ -411,8 +629,77 @@ async fn tray_wash_clipboard(app_state: &AppState) -> anyhow::Result<()> {
     let clipboard_text = clipboard
         .get_text()
         .context("Could not get text from clipboard")?;
+    let washed = app_state.text_washer.wash_detailed(&clipboard_text).await;
+    record_washed_text(app_state, &washed);
     clipboard
-        .set_text(app_state.text_washer.wash(&clipboard_text).await)
+        .set_text(washed.text)
         .context("Could not copy clean text to clipboard")?;
     Ok(())
 }
+
+/// Remembers washed urls and possible trackers for config window,
+/// notifies about blocked urls and about results of washing several urls at once.
+fn record_washed_text(app_state: &AppState, washed: &WashedText) {
+    notify_blocked(&washed.blocked);
+    if !washed.possible_trackers.is_empty() {
+        let mut possible_trackers = app_state.possible_trackers.write().unwrap();
+        for url_trackers in &washed.possible_trackers {
+            possible_trackers.retain(|existing| existing.url != url_trackers.url);
+            possible_trackers.insert(0, url_trackers.clone());
+        }
+        possible_trackers.truncate(MAX_POSSIBLE_TRACKERS);
+    }
+    if washed.washed_urls.is_empty() {
+        return;
+    }
+    {
+        let mut wash_history = app_state.wash_history.write().unwrap();
+        for washed_url in &washed.washed_urls {
+            wash_history.push_front(washed_url.clone());
+        }
+        wash_history.truncate(MAX_WASH_HISTORY);
+    }
+    // single url is replaced silently, summary replaces per url noise for batches
+    if washed.url_count > 1 {
+        let body = format!(
+            "Cleaned {} of {} links, see history for details.",
+            washed.washed_urls.len(),
+            washed.url_count
+        );
+        if let Err(err) = Notification::new()
+            .appname(APP_NAME)
+            .summary(APP_NAME)
+            .body(&body)
+            .show()
+        {
+            error!("Could not show wash summary notification: {err}");
+        }
+    }
+}
+
+fn notify_blocked(blocked: &[BlockedDestination]) {
+    if blocked.is_empty() {
+        return;
+    }
+    let domains = blocked
+        .iter()
+        .map(|destination| destination.domain.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let body = if blocked
+        .iter()
+        .any(|destination| destination.action == BlockedAction::Refuse)
+    {
+        format!("Copied link leads to blocklisted {domains}, it was not unrolled.")
+    } else {
+        format!("Copied link leads to blocklisted {domains}, be careful!")
+    };
+    if let Err(err) = Notification::new()
+        .appname(APP_NAME)
+        .summary(APP_NAME)
+        .body(&body)
+        .show()
+    {
+        error!("Could not show blocked destination notification: {err}");
+    }
+}
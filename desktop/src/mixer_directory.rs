@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use url::Url;
+use urlwasher::{
+    mixer_api::{DirectoryEntry, InstancePolicy},
+    UrlWasher,
+};
+
+/// Signed list of public mixer instances, offered by instance picker.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct MixerDirectoryConfig {
+    pub url: Url,
+    /// Base64 encoded ed25519 key directory has to be signed with.
+    pub public_key: String,
+}
+
+pub struct ListedInstance {
+    pub entry: DirectoryEntry,
+    /// Round trip of `/version` request, [`None`] if instance did not respond.
+    pub latency: Option<Duration>,
+}
+
+/// Fetches and verifies directory, then pings listed instances, fastest ones first.
+pub async fn fetch(
+    url_washer: &UrlWasher,
+    config: &MixerDirectoryConfig,
+) -> anyhow::Result<Vec<ListedInstance>> {
+    let directory = url_washer
+        .mixer_directory(&config.url)
+        .await?
+        .verify(&config.public_key)
+        .context("verify mixer directory")?;
+    let pings = directory.instances.into_iter().map(|entry| async move {
+        let latency = match Url::parse(&entry.url) {
+            Ok(url) => match url_washer.ping_mixer(&url).await {
+                Ok((_, latency)) => Some(latency),
+                Err(err) => {
+                    warn!("Could not ping mixer instance {}: {err:?}", entry.url);
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+        ListedInstance { entry, latency }
+    });
+    let mut instances = futures::future::join_all(pings).await;
+    instances.sort_by_key(|instance| instance.latency.unwrap_or(Duration::MAX));
+    Ok(instances)
+}
+
+pub fn describe_policy(policy: &InstancePolicy) -> String {
+    let mut notes = Vec::new();
+    if policy.requires_api_key {
+        notes.push("requires api key");
+    }
+    if policy.privacy_logging {
+        notes.push("logs censored urls only");
+    }
+    if policy.persistent_cache {
+        notes.push("stores unrolled links on disk");
+    }
+    if notes.is_empty() {
+        return "no policy details".to_string();
+    }
+    notes.join(", ")
+}
@@ -0,0 +1,232 @@
+//! Washing link under mouse cursor, for apps where raw link is hard to copy first.
+//!
+//! Link (or selected text containing it) is read through platform accessibility api:
+//! UI Automation on windows and AXUIElement on macOS. Linux deliberately reads primary selection
+//! (text selected anywhere) instead of AT-SPI, since Wayland does not expose global cursor
+//! position, so element under cursor could not be found there anyway.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use global_hotkey::{hotkey::HotKey, GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use notify_rust::Notification;
+use tracing::{error, info};
+
+use crate::{record_washed_text, AppConfig, AppState, APP_NAME};
+
+/// Registration of [`AppConfig::pointed_link_hotkey`], hotkey works as long as it lives.
+/// Has to be created and updated on thread running event loop.
+pub struct PointedLinkHotkey {
+    /// Config value of last registration, so invalid hotkey is reported only once.
+    configured: Option<String>,
+    manager: Option<GlobalHotKeyManager>,
+    hotkey: Option<HotKey>,
+}
+
+impl PointedLinkHotkey {
+    pub fn new(config: &AppConfig) -> Self {
+        let mut registration = Self {
+            configured: None,
+            manager: None,
+            hotkey: None,
+        };
+        registration.update(config);
+        registration
+    }
+
+    /// Re-registers hotkey if it was changed in config.
+    pub fn update(&mut self, config: &AppConfig) {
+        if self.configured == config.pointed_link_hotkey {
+            return;
+        }
+        self.configured = config.pointed_link_hotkey.clone();
+        if let (Some(manager), Some(hotkey)) = (&self.manager, self.hotkey.take()) {
+            if let Err(err) = manager.unregister(hotkey) {
+                error!("Could not unregister pointed link hotkey: {err:?}");
+            }
+        }
+        let Some(hotkey) = self.configured.as_deref() else {
+            return;
+        };
+        match register(&mut self.manager, hotkey) {
+            Ok(hotkey) => {
+                info!("Registered pointed link hotkey {hotkey:?}");
+                self.hotkey = Some(hotkey);
+            }
+            Err(err) => error!("Could not register pointed link hotkey: {err:?}"),
+        }
+    }
+
+    pub fn is_pressed(&self, event: &GlobalHotKeyEvent) -> bool {
+        self.hotkey
+            .is_some_and(|hotkey| event.id == hotkey.id() && event.state == HotKeyState::Pressed)
+    }
+}
+
+/// Manager is created with first registration and reused by following ones.
+fn register(manager: &mut Option<GlobalHotKeyManager>, hotkey: &str) -> anyhow::Result<HotKey> {
+    let hotkey =
+        HotKey::from_str(hotkey).map_err(|err| anyhow!("invalid hotkey '{hotkey}': {err}"))?;
+    let manager = match manager {
+        Some(manager) => manager,
+        None => manager.insert(GlobalHotKeyManager::new().context("create hotkey manager")?),
+    };
+    manager.register(hotkey).context("register hotkey")?;
+    Ok(hotkey)
+}
+
+/// Washes link under cursor and copies clean version to clipboard.
+pub async fn wash_pointed_link(app_state: &AppState) -> anyhow::Result<()> {
+    let text = tokio::task::spawn_blocking(platform::pointed_text)
+        .await
+        .context("join accessibility task")??
+        .ok_or_else(|| anyhow!("No link under cursor"))?;
+    let washed = app_state.text_washer.wash_detailed(text.trim()).await;
+    record_washed_text(app_state, &washed);
+    let clean_url = match washed.washed_urls.first() {
+        Some(washed_url) => washed_url.washed.clone(),
+        None => washed
+            .text
+            .split_whitespace()
+            .find(|part| part.starts_with("http://") || part.starts_with("https://"))
+            .ok_or_else(|| anyhow!("No link under cursor"))?
+            .to_string(),
+    };
+    info!("Copying washed pointed link: {clean_url}");
+    arboard::Clipboard::new()
+        .context("Could not create clipboard accessor")?
+        .set_text(&clean_url)
+        .context("Could not copy clean link to clipboard")?;
+    if let Err(err) = Notification::new()
+        .appname(APP_NAME)
+        .summary(APP_NAME)
+        .body(&format!("Copied {clean_url}"))
+        .show()
+    {
+        error!("Could not show copied link notification: {err}");
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use anyhow::Context;
+    use uiautomation::{
+        patterns::{UITextPattern, UIValuePattern},
+        types::Point,
+        UIAutomation,
+    };
+    use windows::Win32::{Foundation::POINT, UI::WindowsAndMessaging::GetCursorPos};
+
+    /// Browsers expose href of hovered link as its value, other apps only selected text.
+    pub fn pointed_text() -> anyhow::Result<Option<String>> {
+        let mut cursor = POINT::default();
+        unsafe { GetCursorPos(&mut cursor) }.context("get cursor position")?;
+        let automation = UIAutomation::new().context("create ui automation")?;
+        let element = automation
+            .element_from_point(Point::new(cursor.x, cursor.y))
+            .context("get element under cursor")?;
+        if let Some(value) = element
+            .get_pattern::<UIValuePattern>()
+            .and_then(|pattern| pattern.get_value())
+            .ok()
+            .filter(|value| !value.is_empty())
+        {
+            return Ok(Some(value));
+        }
+        let selection = element
+            .get_pattern::<UITextPattern>()
+            .and_then(|pattern| pattern.get_selection())
+            .unwrap_or_default();
+        let selected = selection
+            .iter()
+            .filter_map(|range| range.get_text(-1).ok())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok((!selected.is_empty()).then_some(selected))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use accessibility_sys::{
+        kAXErrorSuccess, kAXSelectedTextAttribute, kAXURLAttribute, AXUIElementCopyAttributeValue,
+        AXUIElementCopyElementAtPosition, AXUIElementCreateSystemWide, AXUIElementRef,
+    };
+    use anyhow::anyhow;
+    use core_foundation::{
+        base::{CFRelease, CFType, CFTypeRef, TCFType},
+        string::CFString,
+        url::CFURL,
+    };
+    use core_graphics::{
+        event::CGEvent,
+        event_source::{CGEventSource, CGEventSourceStateID},
+    };
+
+    /// Links expose their target as `AXURL`, other elements only selected text.
+    /// Requires accessibility permission granted in system settings.
+    pub fn pointed_text() -> anyhow::Result<Option<String>> {
+        let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState)
+            .map_err(|_| anyhow!("could not create event source"))?;
+        let cursor = CGEvent::new(source)
+            .map_err(|_| anyhow!("could not read cursor position"))?
+            .location();
+        unsafe {
+            let system = AXUIElementCreateSystemWide();
+            let mut element: AXUIElementRef = std::ptr::null_mut();
+            let result = AXUIElementCopyElementAtPosition(
+                system,
+                cursor.x as f32,
+                cursor.y as f32,
+                &mut element,
+            );
+            CFRelease(system as CFTypeRef);
+            if result != kAXErrorSuccess {
+                return Err(anyhow!("could not get element under cursor: {result}"));
+            }
+            let text = attribute(element, kAXURLAttribute)
+                .or_else(|| attribute(element, kAXSelectedTextAttribute));
+            CFRelease(element as CFTypeRef);
+            Ok(text)
+        }
+    }
+
+    unsafe fn attribute(element: AXUIElementRef, name: &str) -> Option<String> {
+        let name = CFString::new(name);
+        let mut value: CFTypeRef = std::ptr::null();
+        if AXUIElementCopyAttributeValue(element, name.as_concrete_TypeRef(), &mut value)
+            != kAXErrorSuccess
+        {
+            return None;
+        }
+        let value = CFType::wrap_under_create_rule(value);
+        if let Some(url) = value.downcast::<CFURL>() {
+            return Some(url.get_string().to_string());
+        }
+        value
+            .downcast::<CFString>()
+            .map(|text| text.to_string())
+            .filter(|text| !text.is_empty())
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use anyhow::Context;
+    use arboard::{GetExtLinux, LinuxClipboardKind};
+
+    /// Text has to be selected before pressing hotkey, hovering link is not enough.
+    pub fn pointed_text() -> anyhow::Result<Option<String>> {
+        let mut clipboard = arboard::Clipboard::new().context("create clipboard accessor")?;
+        match clipboard
+            .get()
+            .clipboard(LinuxClipboardKind::Primary)
+            .text()
+        {
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(err) => Err(err).context("read primary selection"),
+        }
+    }
+}
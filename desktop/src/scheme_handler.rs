@@ -0,0 +1,218 @@
+//! Handler of `urldebloater://wash?url={DIRTY_URL}` links, so browsers, QR scanners and other tools
+//! can hand url to debloater. Received url is shown first and washed only after user confirms it,
+//! then copied to clipboard (or opened with `&action=open`).
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use eframe::egui;
+use tokio::{
+    runtime::Handle,
+    sync::oneshot::{self, error::TryRecvError},
+};
+use tracing::error;
+use url::Url;
+use urlwasher::UrlWasher;
+
+use crate::{config::AppConfig, APP_NAME};
+
+pub const SCHEME: &str = "urldebloater";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SchemeAction {
+    CopyToClipboard,
+    Open,
+}
+
+pub struct SchemeRequest {
+    pub url: Url,
+    pub action: SchemeAction,
+}
+
+pub fn is_scheme_link(arg: &str) -> bool {
+    arg.strip_prefix(SCHEME)
+        .is_some_and(|rest| rest.starts_with(':'))
+}
+
+pub fn parse_request(link: &str) -> anyhow::Result<SchemeRequest> {
+    let link = Url::parse(link).context("invalid link")?;
+    // Some launchers normalize `urldebloater://wash` to `urldebloater:wash`.
+    if link.host_str() != Some("wash") && link.path() != "wash" {
+        return Err(anyhow!("unsupported command, expected {SCHEME}://wash"));
+    }
+    let mut url = None;
+    let mut action = SchemeAction::CopyToClipboard;
+    for (key, value) in link.query_pairs() {
+        match key.as_ref() {
+            "url" => url = Some(Url::parse(&value).context("invalid url param")?),
+            "action" if value == "open" => action = SchemeAction::Open,
+            "action" if value == "copy" => action = SchemeAction::CopyToClipboard,
+            "action" => return Err(anyhow!("unsupported action: {value}")),
+            _ => {}
+        }
+    }
+    Ok(SchemeRequest {
+        url: url.context("missing url param")?,
+        action,
+    })
+}
+
+/// Shows url from scheme link and washes it once user confirms what to do with result.
+/// Runs standalone, without tray and background jobs of regular app.
+pub async fn run(link: &str, config: AppConfig) -> anyhow::Result<()> {
+    let (request, stage) = match parse_request(link) {
+        Ok(request) => (Some(request), Stage::Confirm),
+        Err(err) => (None, Stage::Failed(format!("{err:#}"))),
+    };
+    let url_washer = Arc::new(UrlWasher::new(config.url_washer.clone()).with_rules(config.rules()));
+    // window blocks this thread, washing is spawned on runtime from confirmation window
+    let runtime = Handle::current();
+    eframe::run_native(
+        APP_NAME,
+        eframe::NativeOptions {
+            initial_window_size: Some(egui::vec2(520.0, 200.0)),
+            ..Default::default()
+        },
+        Box::new(move |cc| {
+            crate::gui::configure_style(&cc.egui_ctx);
+            Box::new(ConfirmWindow {
+                request,
+                url_washer,
+                runtime,
+                stage,
+            })
+        }),
+    )
+    .map_err(|err| anyhow!("Could not show confirmation window: {err}"))
+}
+
+struct ConfirmWindow {
+    request: Option<SchemeRequest>,
+    url_washer: Arc<UrlWasher>,
+    runtime: Handle,
+    stage: Stage,
+}
+
+enum Stage {
+    /// Received url is shown, nothing was washed yet.
+    Confirm,
+    Washing {
+        action: SchemeAction,
+        washed_rx: oneshot::Receiver<Result<Url, String>>,
+    },
+    Failed(String),
+}
+
+impl eframe::App for ConfirmWindow {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if ctx.input(|input| input.key_pressed(egui::Key::Escape)) {
+            frame.close();
+        }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Debloat link");
+            if let Some(request) = &self.request {
+                ui.label(format!("Received: {}", request.url));
+            }
+            match &mut self.stage {
+                Stage::Confirm => {
+                    let request = self.request.as_ref().expect("confirmation without request");
+                    ui.horizontal(|ui| {
+                        let copy = ui.button("Debloat and copy");
+                        let open = ui.button("Debloat and open");
+                        let default_button = match request.action {
+                            SchemeAction::CopyToClipboard => &copy,
+                            SchemeAction::Open => &open,
+                        };
+                        if !ctx.memory(|memory| memory.focus().is_some()) {
+                            default_button.request_focus();
+                        }
+                        let action = if copy.clicked() {
+                            SchemeAction::CopyToClipboard
+                        } else if open.clicked() {
+                            SchemeAction::Open
+                        } else {
+                            if ui.button("Cancel").clicked() {
+                                frame.close();
+                            }
+                            return;
+                        };
+                        self.stage = Stage::Washing {
+                            action,
+                            washed_rx: spawn_wash(
+                                &self.runtime,
+                                ctx,
+                                self.url_washer.clone(),
+                                request.url.clone(),
+                            ),
+                        };
+                    });
+                }
+                Stage::Washing { action, washed_rx } => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Debloating...");
+                    });
+                    let washed = match washed_rx.try_recv() {
+                        Ok(Ok(washed)) => washed,
+                        Err(TryRecvError::Empty) => return,
+                        Ok(Err(err)) => {
+                            self.stage = Stage::Failed(err);
+                            return;
+                        }
+                        Err(TryRecvError::Closed) => {
+                            self.stage = Stage::Failed("washing was interrupted".to_string());
+                            return;
+                        }
+                    };
+                    let result = match action {
+                        SchemeAction::CopyToClipboard => copy_to_clipboard(&washed),
+                        SchemeAction::Open => {
+                            open::that_detached(washed.as_str()).context("open url")
+                        }
+                    };
+                    if let Err(err) = result {
+                        error!("Could not handle debloated link: {err:?}");
+                    }
+                    frame.close();
+                }
+                Stage::Failed(err) => {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!("Could not debloat: {err}"),
+                    );
+                    if ui.button("Close").clicked() {
+                        frame.close();
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Washes url in background, repainting window once result is ready.
+fn spawn_wash(
+    runtime: &Handle,
+    ctx: &egui::Context,
+    url_washer: Arc<UrlWasher>,
+    url: Url,
+) -> oneshot::Receiver<Result<Url, String>> {
+    let (washed_tx, washed_rx) = oneshot::channel();
+    let ctx = ctx.clone();
+    runtime.spawn(async move {
+        let washed = url_washer
+            .wash(&url)
+            .await
+            .map(|washed| washed.unwrap_or(url))
+            .map_err(|err| format!("{err:#}"));
+        let _ = washed_tx.send(washed);
+        ctx.request_repaint();
+    });
+    washed_rx
+}
+
+fn copy_to_clipboard(url: &Url) -> anyhow::Result<()> {
+    let mut clipboard = arboard::Clipboard::new().context("Could not create clipboard accessor")?;
+    clipboard
+        .set_text(url.to_string())
+        .context("Could not copy url to clipboard")
+}
@@ -0,0 +1,87 @@
+use std::{collections::HashMap, env, fs, io::ErrorKind};
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use tracing::info;
+use url::Url;
+use urlwasher::{HttpProfile, RedirectWashPolicy, UrlWasherConfig};
+
+const CONFIG_PATH_ENV: &str = "DISCORD_BOT_CONFIG";
+const DEFAULT_CONFIG_PATH: &str = "discord.json";
+const TOKEN_ENV: &str = "DISCORD_TOKEN";
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct BotConfig {
+    /// Bot token, `DISCORD_TOKEN` env takes precedence.
+    pub token: Option<String>,
+    /// Ids of watched channels (threads have their own ids), all visible channels are watched if empty.
+    pub channels: Vec<u64>,
+    pub mode: ReplyMode,
+    /// How short links are unrolled, applies to every rule that resolves redirects.
+    pub redirect_policy: Option<RedirectWashPolicy>,
+    pub mixer_instance: Option<Url>,
+    /// Api key of private mixer instance.
+    pub mixer_api_key: Option<String>,
+    /// SOCKS5 proxy of tor daemon used by `ViaTor` policy.
+    pub tor_proxy: Option<Url>,
+    /// User agent and headers used when resolving redirects, keyed by target domain.
+    pub http_profiles: HashMap<String, HttpProfile>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplyMode {
+    /// Reply in channel with washed URLs.
+    #[default]
+    Reply,
+    /// Send author a direct message with washed message text, so they can edit their message.
+    SuggestEdit,
+}
+
+impl BotConfig {
+    pub fn token(&self) -> anyhow::Result<String> {
+        env::var(TOKEN_ENV)
+            .ok()
+            .or_else(|| self.token.clone())
+            .ok_or_else(|| anyhow!("missing bot token, set {TOKEN_ENV} env or token in config"))
+    }
+
+    pub fn watches_channel(&self, channel_id: u64) -> bool {
+        self.channels.is_empty() || self.channels.contains(&channel_id)
+    }
+
+    pub fn washer_config(&self) -> UrlWasherConfig {
+        let mut washer_config = UrlWasherConfig {
+            mixer_instance: self.mixer_instance.clone(),
+            mixer_api_key: self.mixer_api_key.clone(),
+            http_profiles: self.http_profiles.clone(),
+            ..Default::default()
+        };
+        if let Some(tor_proxy) = &self.tor_proxy {
+            washer_config.tor_proxy = tor_proxy.clone();
+        }
+        if let Some(policy) = self.redirect_policy {
+            washer_config
+                .redirect_policy
+                .iter_mut()
+                .for_each(|(_, redirect_policy)| *redirect_policy = policy);
+        }
+        washer_config
+    }
+}
+
+/// Reads config from path in `DISCORD_BOT_CONFIG` env (or `discord.json`).
+/// Missing file is not an error, defaults are used instead.
+pub fn from_file() -> anyhow::Result<BotConfig> {
+    let path = env::var(CONFIG_PATH_ENV).unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            info!("Config file {path} not found, using default config.");
+            return Ok(BotConfig::default());
+        }
+        Err(err) => return Err(err).context("read config file"),
+    };
+    serde_json::from_slice(&bytes).context("deserialize config")
+}
@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use config::{BotConfig, ReplyMode};
+use serenity::{
+    all::{CreateAllowedMentions, CreateMessage, GatewayIntents, Message, Ready},
+    async_trait,
+    client::{Client, Context, EventHandler},
+};
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::EnvFilter;
+use urlwasher::{
+    text_washer::{TextWasher, WashedText},
+    UrlWasher,
+};
+
+mod config;
+
+/// Discord rejects messages longer than this.
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+struct Handler {
+    config: BotConfig,
+    text_washer: TextWasher,
+}
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn ready(&self, _: Context, ready: Ready) {
+        info!("Connected as {}.", ready.user.name);
+    }
+
+    async fn message(&self, ctx: Context, msg: Message) {
+        if msg.author.bot || !self.config.watches_channel(msg.channel_id.get()) {
+            return;
+        }
+        let washed = self.text_washer.wash_detailed(&msg.content).await;
+        let Some(content) = reply_content(self.config.mode, &washed) else {
+            return;
+        };
+        if content.chars().count() > MAX_MESSAGE_LENGTH {
+            warn!("Washed reply to message {} is too long, skipping.", msg.id);
+            return;
+        }
+        debug!(
+            "Washed {} urls in message {}.",
+            washed.washed_urls.len(),
+            msg.id
+        );
+        if let Err(err) = self.send(&ctx, &msg, content).await {
+            error!("Could not respond to message {}: {err:?}", msg.id);
+        }
+    }
+}
+
+impl Handler {
+    async fn send(&self, ctx: &Context, msg: &Message, content: String) -> anyhow::Result<()> {
+        match self.config.mode {
+            ReplyMode::Reply => {
+                let reply = CreateMessage::new()
+                    .content(content)
+                    .reference_message(msg)
+                    .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+                msg.channel_id
+                    .send_message(ctx, reply)
+                    .await
+                    .context("send reply")?;
+            }
+            ReplyMode::SuggestEdit => {
+                msg.author
+                    .direct_message(ctx, CreateMessage::new().content(content))
+                    .await
+                    .context("send direct message")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns [`None`] if message has nothing to wash.
+fn reply_content(mode: ReplyMode, washed: &WashedText) -> Option<String> {
+    if washed.washed_urls.is_empty() {
+        return None;
+    }
+    Some(match mode {
+        ReplyMode::Reply => washed
+            .washed_urls
+            .iter()
+            .map(|washed_url| washed_url.washed.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ReplyMode::SuggestEdit => format!(
+            "Your message contains tracking links, consider editing it to:\n{}",
+            washed.text
+        ),
+    })
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_line_number(false)
+        .with_file(false)
+        .init();
+
+    let config = config::from_file().expect("Could not load config");
+    let token = config.token().expect("Could not get bot token");
+    let handler = Handler {
+        text_washer: TextWasher {
+            url_washer: Arc::new(UrlWasher::new(config.washer_config())),
+            skip_code: false,
+        },
+        config,
+    };
+    let intents = GatewayIntents::GUILD_MESSAGES
+        | GatewayIntents::DIRECT_MESSAGES
+        | GatewayIntents::MESSAGE_CONTENT;
+    let mut client = Client::builder(&token, intents)
+        .event_handler(handler)
+        .await
+        .expect("Could not create discord client");
+    info!("Starting discord bot...");
+    client.start().await.expect("Discord client error");
+}
+
+#[cfg(test)]
+mod tests {
+    use urlwasher::text_washer::WashedUrl;
+
+    use super::*;
+
+    fn washed_text() -> WashedText {
+        WashedText {
+            text: "look https://x.com/a/status/1 and https://youtu.be/abc".to_string(),
+            url_count: 2,
+            washed_urls: vec![
+                WashedUrl {
+                    original: "https://x.com/a/status/1?s=46".to_string(),
+                    washed: "https://x.com/a/status/1".to_string(),
+                    report: Default::default(),
+                },
+                WashedUrl {
+                    original: "https://youtu.be/abc?si=track".to_string(),
+                    washed: "https://youtu.be/abc".to_string(),
+                    report: Default::default(),
+                },
+            ],
+            blocked: vec![],
+            possible_trackers: vec![],
+        }
+    }
+
+    #[test]
+    fn replies_with_washed_urls() {
+        assert_eq!(
+            reply_content(ReplyMode::Reply, &washed_text()).unwrap(),
+            "https://x.com/a/status/1\nhttps://youtu.be/abc"
+        );
+        assert!(reply_content(ReplyMode::SuggestEdit, &washed_text())
+            .unwrap()
+            .ends_with("\nlook https://x.com/a/status/1 and https://youtu.be/abc"));
+    }
+
+    #[test]
+    fn ignores_clean_message() {
+        let clean = WashedText {
+            text: "https://example.com/".to_string(),
+            url_count: 1,
+            washed_urls: vec![],
+            blocked: vec![],
+            possible_trackers: vec![],
+        };
+        assert_eq!(reply_content(ReplyMode::Reply, &clean), None);
+    }
+}
@@ -0,0 +1,70 @@
+use std::{collections::HashMap, env, fs};
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use url::Url;
+use urlwasher::{HttpProfile, RedirectWashPolicy, UrlWasherConfig};
+
+const CONFIG_PATH_ENV: &str = "MATRIX_BOT_CONFIG";
+const DEFAULT_CONFIG_PATH: &str = "matrix.json";
+const PASSWORD_ENV: &str = "MATRIX_PASSWORD";
+
+#[derive(Deserialize, Debug)]
+pub struct BotConfig {
+    pub homeserver: Url,
+    /// Localpart or full user id of bot account.
+    pub username: String,
+    /// Account password, `MATRIX_PASSWORD` env takes precedence.
+    pub password: Option<String>,
+    /// Reply with washed URLs to every message containing tracking links, not only to `!wash`.
+    #[serde(default)]
+    pub auto_wash: bool,
+    /// How short links are unrolled, applies to every rule that resolves redirects.
+    pub redirect_policy: Option<RedirectWashPolicy>,
+    pub mixer_instance: Option<Url>,
+    /// Api key of private mixer instance.
+    pub mixer_api_key: Option<String>,
+    /// SOCKS5 proxy of tor daemon used by `ViaTor` policy.
+    pub tor_proxy: Option<Url>,
+    /// User agent and headers used when resolving redirects, keyed by target domain.
+    #[serde(default)]
+    pub http_profiles: HashMap<String, HttpProfile>,
+}
+
+impl BotConfig {
+    pub fn password(&self) -> anyhow::Result<String> {
+        env::var(PASSWORD_ENV)
+            .ok()
+            .or_else(|| self.password.clone())
+            .ok_or_else(|| {
+                anyhow!("missing password, set {PASSWORD_ENV} env or password in config")
+            })
+    }
+
+    pub fn washer_config(&self) -> UrlWasherConfig {
+        let mut washer_config = UrlWasherConfig {
+            mixer_instance: self.mixer_instance.clone(),
+            mixer_api_key: self.mixer_api_key.clone(),
+            http_profiles: self.http_profiles.clone(),
+            ..Default::default()
+        };
+        if let Some(tor_proxy) = &self.tor_proxy {
+            washer_config.tor_proxy = tor_proxy.clone();
+        }
+        if let Some(policy) = self.redirect_policy {
+            washer_config
+                .redirect_policy
+                .iter_mut()
+                .for_each(|(_, redirect_policy)| *redirect_policy = policy);
+        }
+        washer_config
+    }
+}
+
+/// Reads config from path in `MATRIX_BOT_CONFIG` env (or `matrix.json`).
+/// Unlike other configs it is required, bot cannot guess its homeserver.
+pub fn from_file() -> anyhow::Result<BotConfig> {
+    let path = env::var(CONFIG_PATH_ENV).unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+    let bytes = fs::read(&path).with_context(|| format!("read config file {path}"))?;
+    serde_json::from_slice(&bytes).context("deserialize config")
+}
@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use matrix_sdk::{
+    config::SyncSettings,
+    event_handler::Ctx,
+    ruma::events::room::{
+        member::StrippedRoomMemberEvent,
+        message::{
+            AddMentions, ForwardThread, MessageType, OriginalSyncRoomMessageEvent,
+            RoomMessageEventContent,
+        },
+    },
+    Client, Room, RoomState,
+};
+use tracing::{debug, error, info};
+use tracing_subscriber::EnvFilter;
+use urlwasher::{text_washer::TextWasher, UrlWasher};
+
+mod config;
+
+const WASH_COMMAND: &str = "!wash";
+
+struct Bot {
+    auto_wash: bool,
+    text_washer: TextWasher,
+}
+
+impl Bot {
+    /// Returns [`None`] if there is nothing to respond with.
+    async fn respond(&self, body: &str) -> Option<String> {
+        let (text, is_command) = match parse_command(body) {
+            Some(text) => (text, true),
+            None if self.auto_wash => (body, false),
+            None => return None,
+        };
+        let washed = self.text_washer.wash_detailed(text).await;
+        if washed.washed_urls.is_empty() {
+            return is_command.then(|| "Nothing to wash.".to_string());
+        }
+        Some(
+            washed
+                .washed_urls
+                .iter()
+                .map(|washed_url| washed_url.washed.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+}
+
+/// Returns text following `!wash` command, [`None`] if message is not a command.
+fn parse_command(body: &str) -> Option<&str> {
+    let args = body.strip_prefix(WASH_COMMAND)?;
+    if !args.is_empty() && !args.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(args.trim())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_line_number(false)
+        .with_file(false)
+        .init();
+
+    let config = config::from_file().context("load config")?;
+    let client = Client::builder()
+        .homeserver_url(&config.homeserver)
+        .build()
+        .await
+        .context("create matrix client")?;
+    client
+        .matrix_auth()
+        .login_username(&config.username, &config.password()?)
+        .initial_device_display_name("urldebloater")
+        .await
+        .context("login")?;
+    info!("Logged in as {}.", config.username);
+
+    client.add_event_handler_context(Arc::new(Bot {
+        auto_wash: config.auto_wash,
+        text_washer: TextWasher {
+            url_washer: Arc::new(UrlWasher::new(config.washer_config())),
+            skip_code: false,
+        },
+    }));
+    client.add_event_handler(on_invite);
+    // Initial sync without message handler, so messages sent while bot was offline are not answered.
+    let response = client
+        .sync_once(SyncSettings::default())
+        .await
+        .context("initial sync")?;
+    client.add_event_handler(on_room_message);
+    info!("Starting matrix bot...");
+    client
+        .sync(SyncSettings::default().token(response.next_batch))
+        .await
+        .context("sync")
+}
+
+async fn on_invite(event: StrippedRoomMemberEvent, client: Client, room: Room) {
+    if Some(event.state_key.as_ref()) != client.user_id() {
+        return;
+    }
+    tokio::spawn(async move {
+        info!("Joining room {} on invite.", room.room_id());
+        if let Err(err) = room.join().await {
+            error!("Could not join room {}: {err:?}", room.room_id());
+        }
+    });
+}
+
+async fn on_room_message(
+    event: OriginalSyncRoomMessageEvent,
+    room: Room,
+    client: Client,
+    Ctx(bot): Ctx<Arc<Bot>>,
+) {
+    if room.state() != RoomState::Joined || Some(event.sender.as_ref()) == client.user_id() {
+        return;
+    }
+    let MessageType::Text(text) = &event.content.msgtype else {
+        return;
+    };
+    let Some(response) = bot.respond(&text.body).await else {
+        return;
+    };
+    debug!(
+        "Responding to {} in room {}.",
+        event.event_id,
+        room.room_id()
+    );
+    let original = event.into_full_event(room.room_id().to_owned());
+    let reply = RoomMessageEventContent::notice_plain(response).make_reply_to(
+        &original,
+        ForwardThread::Yes,
+        AddMentions::No,
+    );
+    if let Err(err) = room.send(reply).await {
+        error!("Could not reply in room {}: {err:?}", room.room_id());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_wash_command() {
+        assert_eq!(
+            parse_command("!wash https://youtu.be/abc?si=track"),
+            Some("https://youtu.be/abc?si=track")
+        );
+        assert_eq!(parse_command("!wash"), Some(""));
+        assert_eq!(parse_command("!washing machine"), None);
+        assert_eq!(parse_command("look https://youtu.be/abc"), None);
+    }
+
+    #[tokio::test]
+    async fn responds_only_to_command_without_auto_wash() {
+        let bot = Bot {
+            auto_wash: false,
+            text_washer: TextWasher::default(),
+        };
+        assert_eq!(
+            bot.respond("look https://youtu.be/abc?si=track").await,
+            None
+        );
+        assert_eq!(
+            bot.respond("!wash https://youtu.be/abc?si=track")
+                .await
+                .as_deref(),
+            Some("https://youtu.be/abc")
+        );
+        assert_eq!(
+            bot.respond("!wash https://example.com/").await.as_deref(),
+            Some("Nothing to wash.")
+        );
+    }
+}
@@ -0,0 +1,16 @@
+use std::process::Command;
+
+fn main() {
+    // Docker build copies sources without .git, so missing hash is not an error.
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(git_hash) = git_hash {
+        println!("cargo:rustc-env=MIXER_GIT_HASH={}", git_hash.trim());
+    }
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+}
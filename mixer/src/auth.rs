@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    num::NonZeroU32,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{Request, State},
+    http::{header::AUTHORIZATION, HeaderMap},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use governor::{
+    clock::{Clock, DefaultClock, QuantaInstant},
+    DefaultDirectRateLimiter, DefaultKeyedRateLimiter, NotUntil, Quota, RateLimiter,
+};
+use serde::Serialize;
+use tower_governor::key_extractor::{KeyExtractor, SmartIpKeyExtractor};
+use tracing::info;
+use urlwasher::mixer_api::ApiKeyUsage;
+
+use crate::{
+    config::{MixerConfig, RateLimitConfig},
+    error::{AppResult, UserError},
+};
+
+const API_KEY_HEADER: &str = "x-api-key";
+const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
+
+pub struct Authenticator {
+    require_api_key: bool,
+    api_keys: HashMap<String, Arc<ApiKey>>,
+    anonymous_limiter: Option<DefaultKeyedRateLimiter<IpAddr>>,
+    /// Rejected clients and when they may send requests again.
+    bans: Mutex<HashMap<BannedClient, (BanReason, Instant)>>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BannedClient {
+    /// Anonymous client.
+    Ip(IpAddr),
+    /// Name of api key.
+    ApiKey(String),
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BanReason {
+    RateLimit,
+    DailyQuota,
+}
+
+impl From<BanReason> for UserError {
+    fn from(reason: BanReason) -> Self {
+        match reason {
+            BanReason::RateLimit => UserError::TooManyRequests,
+            BanReason::DailyQuota => UserError::QuotaExceeded,
+        }
+    }
+}
+
+/// Client rejected by limits, shown on admin dashboard.
+#[derive(Serialize)]
+pub struct ActiveBan {
+    client: BannedClient,
+    reason: BanReason,
+    expires_in_ms: u64,
+}
+
+impl Authenticator {
+    pub fn new(config: &MixerConfig, limit_anonymous: bool) -> Self {
+        Self {
+            require_api_key: config.require_api_key,
+            api_keys: config
+                .api_keys
+                .iter()
+                .map(|key_config| {
+                    let api_key = ApiKey {
+                        name: key_config.name.clone(),
+                        limiter: RateLimiter::direct(quota(key_config.rate_limit)),
+                        daily_quota: key_config.daily_quota,
+                        usage: Mutex::new(DailyUsage::default()),
+                        requests_total: AtomicU64::new(0),
+                    };
+                    (key_config.key.clone(), Arc::new(api_key))
+                })
+                .collect(),
+            anonymous_limiter: limit_anonymous
+                .then(|| RateLimiter::keyed(quota(config.anonymous_rate_limit))),
+            bans: Mutex::default(),
+        }
+    }
+
+    fn ban(&self, client: BannedClient, reason: BanReason, duration: Duration) {
+        let now = Instant::now();
+        let mut bans = self.bans.lock().unwrap();
+        bans.retain(|_, (_, expires_at)| *expires_at > now);
+        bans.insert(client, (reason, now + duration));
+    }
+
+    /// Clients that are still rejected, the longest banned first.
+    pub fn active_bans(&self) -> Vec<ActiveBan> {
+        let now = Instant::now();
+        let mut bans = self
+            .bans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, (_, expires_at))| *expires_at > now)
+            .map(|(client, (reason, expires_at))| ActiveBan {
+                client: client.clone(),
+                reason: *reason,
+                expires_in_ms: (*expires_at - now).as_millis() as u64,
+            })
+            .collect::<Vec<_>>();
+        bans.sort_by_key(|ban| std::cmp::Reverse(ban.expires_in_ms));
+        bans
+    }
+}
+
+/// Time until rate limiter allows next request.
+fn retry_after(not_until: NotUntil<QuantaInstant>) -> Duration {
+    not_until.wait_time_from(DefaultClock::default().now())
+}
+
+fn quota(config: RateLimitConfig) -> Quota {
+    let per_second = NonZeroU32::new(config.per_second).unwrap_or(NonZeroU32::MIN);
+    let burst_size = NonZeroU32::new(config.burst_size).unwrap_or(per_second);
+    Quota::per_second(per_second).allow_burst(burst_size)
+}
+
+pub struct ApiKey {
+    pub name: String,
+    limiter: DefaultDirectRateLimiter,
+    daily_quota: Option<u64>,
+    usage: Mutex<DailyUsage>,
+    requests_total: AtomicU64,
+}
+
+#[derive(Default)]
+struct DailyUsage {
+    day: u64,
+    requests: u64,
+}
+
+impl ApiKey {
+    /// Counts request to limits, rejected one gets reason and time until key may be used again.
+    fn try_acquire(&self) -> Result<(), (BanReason, Duration)> {
+        if let Err(not_until) = self.limiter.check() {
+            return Err((BanReason::RateLimit, retry_after(not_until)));
+        }
+        let mut usage = self.usage.lock().unwrap();
+        let today = current_day();
+        if usage.day != today {
+            *usage = DailyUsage {
+                day: today,
+                requests: 0,
+            };
+        }
+        if self
+            .daily_quota
+            .is_some_and(|daily_quota| usage.requests >= daily_quota)
+        {
+            let until_tomorrow = SECONDS_PER_DAY - current_secs() % SECONDS_PER_DAY;
+            return Err((BanReason::DailyQuota, Duration::from_secs(until_tomorrow)));
+        }
+        usage.requests += 1;
+        self.requests_total.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn usage(&self) -> ApiKeyUsage {
+        let usage = self.usage.lock().unwrap();
+        ApiKeyUsage {
+            name: self.name.clone(),
+            requests_today: if usage.day == current_day() {
+                usage.requests
+            } else {
+                0
+            },
+            daily_quota: self.daily_quota,
+            requests_total: self.requests_total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn current_day() -> u64 {
+    current_secs() / SECONDS_PER_DAY
+}
+
+fn current_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn api_key_from_headers(headers: &HeaderMap) -> Option<&str> {
+    if let Some(key) = headers.get(API_KEY_HEADER) {
+        return key.to_str().ok();
+    }
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+/// Resolves caller api key (if any) and enforces its limits.
+/// Authenticated requests get [`ApiKey`] inserted into request extensions.
+pub async fn authenticate(
+    State(authenticator): State<Arc<Authenticator>>,
+    mut req: Request,
+    next: Next,
+) -> AppResult<Response> {
+    match api_key_from_headers(req.headers()) {
+        Some(key) => {
+            let api_key = authenticator
+                .api_keys
+                .get(key)
+                .ok_or(UserError::InvalidApiKey)?
+                .clone();
+            if let Err((reason, retry_after)) = api_key.try_acquire() {
+                info!("Rejected request of api key '{}': {reason:?}", api_key.name);
+                let client = BannedClient::ApiKey(api_key.name.clone());
+                authenticator.ban(client, reason, retry_after);
+                return Err(UserError::from(reason).into());
+            }
+            req.extensions_mut().insert(api_key);
+        }
+        None if authenticator.require_api_key => return Err(UserError::MissingApiKey.into()),
+        None => {
+            if let Some(limiter) = &authenticator.anonymous_limiter {
+                let ip = SmartIpKeyExtractor
+                    .extract(&req)
+                    .map_err(|_| anyhow::anyhow!("could not extract client ip"))?;
+                if let Err(not_until) = limiter.check_key(&ip) {
+                    let reason = BanReason::RateLimit;
+                    authenticator.ban(BannedClient::Ip(ip), reason, retry_after(not_until));
+                    return Err(UserError::from(reason).into());
+                }
+            }
+        }
+    }
+    Ok(next.run(req).await)
+}
+
+pub async fn bans(State(authenticator): State<Arc<Authenticator>>) -> Json<Vec<ActiveBan>> {
+    Json(authenticator.active_bans())
+}
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use url::Url;
+use urlwasher::UrlWasher;
+
+use crate::error::{AppResult, UserError};
+
+#[derive(Serialize)]
+pub struct CacheEntry {
+    url: Url,
+    washed: Url,
+}
+
+/// Cached wash results, most recently used first in each cache shard.
+pub async fn entries(State(washer): State<Arc<UrlWasher>>) -> Json<Vec<CacheEntry>> {
+    Json(
+        washer
+            .cache_entries()
+            .into_iter()
+            .map(|(url, washed)| CacheEntry { url, washed })
+            .collect(),
+    )
+}
+
+#[derive(Deserialize)]
+pub struct PurgeQuery {
+    /// Purge only this url instead of whole cache.
+    url: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PurgeResponse {
+    removed: usize,
+}
+
+pub async fn purge(
+    State(washer): State<Arc<UrlWasher>>,
+    Query(query): Query<PurgeQuery>,
+) -> AppResult<Json<PurgeResponse>> {
+    let removed = match query.url {
+        Some(url) => {
+            let url = Url::parse(&url).map_err(|_| UserError::InvalidUrl)?;
+            usize::from(washer.cache_remove(&url).is_some())
+        }
+        None => {
+            let removed = washer.cache_entries().len();
+            washer.cache_clear();
+            removed
+        }
+    };
+    info!("Purged {removed} cached urls.");
+    Ok(Json(PurgeResponse { removed }))
+}
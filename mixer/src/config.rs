@@ -0,0 +1,177 @@
+use std::{
+    collections::HashMap, env, fs, io::ErrorKind, net::SocketAddr, num::NonZeroUsize,
+    time::Duration,
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+use tracing::info;
+use url::Url;
+use urlwasher::{
+    blocklist::BlocklistConfig, rule_source::RuleSource, shorteners::ShortenersConfig, HttpProfile,
+    RedirectRequestMethod,
+};
+
+use crate::{dns::DnsConfig, privacy::PrivacyLoggingConfig, store::PersistentCacheConfig};
+
+const CONFIG_PATH_ENV: &str = "MIXER_CONFIG";
+const DEFAULT_CONFIG_PATH: &str = "mixer.json";
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct MixerConfig {
+    /// Addresses to listen on, e.g. `[::]:7777` and `0.0.0.0:7777`.
+    /// Only `0.0.0.0:7777` if empty.
+    pub listen: Vec<SocketAddr>,
+    /// Reject requests that do not carry a known api key.
+    pub require_api_key: bool,
+    /// Limits applied per client IP to requests without api key.
+    pub anonymous_rate_limit: RateLimitConfig,
+    pub api_keys: Vec<ApiKeyConfig>,
+    /// User agent and headers used when resolving redirects, keyed by target domain.
+    pub http_profiles: HashMap<String, HttpProfile>,
+    /// Resolver used for outbound requests.
+    pub dns: DnsConfig,
+    /// Bearer token for `/admin` endpoints, admin api is disabled if not set.
+    pub admin_token: Option<String>,
+    /// Message of the day displayed by clients.
+    pub motd: Option<String>,
+    /// Maintenance mode state on startup.
+    pub maintenance: MaintenanceConfig,
+    pub timeouts: TimeoutConfig,
+    /// How long wash results are cached, until evicted by newer ones if not set.
+    pub cache_ttl_secs: Option<u64>,
+    /// Wash results kept in memory, 1024 if not set.
+    pub cache_capacity: Option<NonZeroUsize>,
+    /// How often expired cache entries are evicted.
+    pub cache_sweep_interval_secs: Option<u64>,
+    /// Redirects followed per washed link, e.g. short link leading to another one, 3 if not set.
+    pub max_redirect_hops: Option<u8>,
+    /// Look for redirect scripts in pages of short links, see [`urlwasher::html_redirect::script_redirect`].
+    pub detect_script_redirects: bool,
+    /// `get` (default) or `head`, see [`urlwasher::RedirectRequestMethod`].
+    pub redirect_request_method: RedirectRequestMethod,
+    /// Time limit of each request unrolling short link in milliseconds, 10000 if not set.
+    pub redirect_timeout_ms: Option<u64>,
+    /// Retries of short links that timed out or refused connection.
+    pub redirect_retries: u8,
+    /// Short links of the same domain unrolled at once, 4 if not set.
+    pub redirect_concurrency_per_domain: Option<NonZeroUsize>,
+    /// Refuse short links redirecting off their site, see [`urlwasher::UrlWasherConfig::same_site_redirects`].
+    pub same_site_redirects: bool,
+    /// Wash urls nested in params, see [`urlwasher::UrlWasherConfig::wash_nested_urls`].
+    pub wash_nested_urls: bool,
+    /// Unroll links of url shorteners without rules, see [`urlwasher::shorteners`].
+    pub shorteners: ShortenersConfig,
+    /// Keeps resolved short links in sqlite database, in-memory only if not set.
+    pub persistent_cache: Option<PersistentCacheConfig>,
+    /// Feeds of malicious domains, short links leading to them are flagged or refused.
+    pub blocklist: BlocklistConfig,
+    /// Built-in rules by default, rule file or remote rule set composed after them.
+    pub rules: RuleSource,
+    /// How often remote rule set is checked for changes, 6 hours if not set.
+    pub rules_refresh_interval_secs: Option<u64>,
+    /// Keeps full urls out of logs.
+    pub privacy_logging: PrivacyLoggingConfig,
+    /// Opts in to public mixer directory, instance is not listed if not set.
+    pub directory_listing: Option<DirectoryListingConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DirectoryListingConfig {
+    /// Url clients reach instance at.
+    pub public_url: Url,
+    pub operator: Option<String>,
+    pub region: Option<String>,
+}
+
+/// Timeouts of routes that need more than [`TimeoutConfig::default_ms`], unless configured.
+/// Washing may unroll short link, admin refreshes download whole feeds and rule sets.
+const DEFAULT_ROUTE_TIMEOUTS_MS: [(&str, u64); 3] = [
+    ("/wash", 10_000),
+    ("/admin/blocklist", 60_000),
+    ("/admin/rules", 60_000),
+];
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    /// Timeout of routes without their own, in milliseconds.
+    pub default_ms: u64,
+    /// Timeouts in milliseconds keyed by route path, e.g. `/wash`.
+    /// Override built-in ones of routes that wash or refresh.
+    pub routes: HashMap<String, u64>,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            default_ms: 2_000,
+            routes: HashMap::new(),
+        }
+    }
+}
+
+impl TimeoutConfig {
+    pub fn route(&self, path: &str) -> Duration {
+        let default_route_ms = DEFAULT_ROUTE_TIMEOUTS_MS
+            .iter()
+            .find(|(route, _)| *route == path)
+            .map(|(_, timeout_ms)| timeout_ms);
+        let timeout_ms = self
+            .routes
+            .get(path)
+            .or(default_route_ms)
+            .unwrap_or(&self.default_ms);
+        Duration::from_millis(*timeout_ms)
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    pub enabled: bool,
+    pub message: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub per_second: u32,
+    pub burst_size: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_second: 5,
+            burst_size: 10,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ApiKeyConfig {
+    /// Name used in logs and usage reports, so the secret key never has to be printed.
+    pub name: String,
+    pub key: String,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// Max requests per UTC day, unlimited if not set.
+    pub daily_quota: Option<u64>,
+}
+
+/// Reads config from path in `MIXER_CONFIG` env (or `mixer.json`).
+/// Missing file is not an error, defaults are used instead.
+pub fn from_file() -> anyhow::Result<MixerConfig> {
+    let path = env::var(CONFIG_PATH_ENV).unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            info!("Config file {path} not found, using default config.");
+            return Ok(MixerConfig::default());
+        }
+        Err(err) => return Err(err).context("read config file"),
+    };
+    serde_json::from_slice(&bytes).context("deserialize config")
+}
@@ -0,0 +1,55 @@
+use hickory_resolver::{
+    config::{ResolverConfig, ResolverOpts},
+    system_conf,
+};
+use serde::Deserialize;
+use urlwasher::doh::CachingResolver;
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct DnsConfig {
+    pub resolver: DnsResolver,
+    /// Max cached records, ignored by [`DnsResolver::System`].
+    pub cache_size: usize,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            resolver: DnsResolver::System,
+            cache_size: 1024,
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DnsResolver {
+    /// Operating system resolver (getaddrinfo) without caching in mixer.
+    #[default]
+    System,
+    /// Nameservers from system configuration with caching in mixer.
+    SystemCached,
+    Cloudflare,
+    CloudflareHttps,
+    Google,
+    GoogleHttps,
+    Quad9Https,
+}
+
+/// Caching resolver for outbound requests, `None` if system resolver should be used.
+pub fn resolver(config: &DnsConfig) -> anyhow::Result<Option<CachingResolver>> {
+    let (resolver_config, mut opts) = match config.resolver {
+        DnsResolver::System => return Ok(None),
+        DnsResolver::SystemCached => system_conf::read_system_conf()?,
+        DnsResolver::Cloudflare => (ResolverConfig::cloudflare(), ResolverOpts::default()),
+        DnsResolver::CloudflareHttps => {
+            (ResolverConfig::cloudflare_https(), ResolverOpts::default())
+        }
+        DnsResolver::Google => (ResolverConfig::google(), ResolverOpts::default()),
+        DnsResolver::GoogleHttps => (ResolverConfig::google_https(), ResolverOpts::default()),
+        DnsResolver::Quad9Https => (ResolverConfig::quad9_https(), ResolverOpts::default()),
+    };
+    opts.cache_size = config.cache_size;
+    Ok(Some(CachingResolver::new(resolver_config, opts)))
+}
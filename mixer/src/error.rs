@@ -4,11 +4,14 @@ use axum::{
 };
 use tracing::error;
 
+use crate::privacy;
+
 pub type AppResult<T> = core::result::Result<T, AppError>;
 
 #[derive(Debug)]
 pub enum AppError {
     User(UserError),
+    Maintenance(String),
     Internal(anyhow::Error),
 }
 
@@ -16,6 +19,15 @@ pub enum AppError {
 pub enum UserError {
     InvalidUrl,
     TooLongUrl,
+    MissingApiKey,
+    InvalidApiKey,
+    TooManyRequests,
+    QuotaExceeded,
+    InvalidAdminToken,
+    BlockedDestination,
+    RedirectLoop,
+    OffSiteRedirect,
+    NotListed,
 }
 
 impl IntoResponse for AppError {
@@ -23,8 +35,42 @@ impl IntoResponse for AppError {
         match self {
             AppError::User(UserError::InvalidUrl) => (StatusCode::BAD_REQUEST, "invalid url"),
             AppError::User(UserError::TooLongUrl) => (StatusCode::BAD_REQUEST, "too long url"),
+            AppError::User(UserError::MissingApiKey) => {
+                (StatusCode::UNAUTHORIZED, "missing api key")
+            }
+            AppError::User(UserError::InvalidApiKey) => {
+                (StatusCode::UNAUTHORIZED, "invalid api key")
+            }
+            AppError::User(UserError::TooManyRequests) => {
+                (StatusCode::TOO_MANY_REQUESTS, "too many requests")
+            }
+            AppError::User(UserError::QuotaExceeded) => {
+                (StatusCode::TOO_MANY_REQUESTS, "daily quota exceeded")
+            }
+            AppError::User(UserError::InvalidAdminToken) => {
+                (StatusCode::UNAUTHORIZED, "invalid admin token")
+            }
+            AppError::User(UserError::BlockedDestination) => {
+                (StatusCode::FORBIDDEN, "destination is blocklisted")
+            }
+            AppError::User(UserError::RedirectLoop) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "short link redirects in a loop",
+            ),
+            AppError::User(UserError::OffSiteRedirect) => {
+                (StatusCode::FORBIDDEN, "short link redirects off its site")
+            }
+            AppError::User(UserError::NotListed) => {
+                (StatusCode::NOT_FOUND, "instance is not listed in directory")
+            }
+            AppError::Maintenance(message) => {
+                return (StatusCode::SERVICE_UNAVAILABLE, message).into_response()
+            }
             AppError::Internal(err) => {
-                error!("Internal server error: {err:?}");
+                error!(
+                    "Internal server error: {}",
+                    privacy::text(&format!("{err:?}"))
+                );
                 (StatusCode::INTERNAL_SERVER_ERROR, "internal server error")
             }
         }
@@ -0,0 +1,158 @@
+use std::sync::{Arc, RwLock};
+
+use axum::{
+    extract::{Request, State},
+    http::{header::AUTHORIZATION, HeaderValue},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use serde::Deserialize;
+use tracing::info;
+use urldebloater_proto::directory::{DirectoryEntry, InstancePolicy};
+use urlwasher::mixer_api::{MixerMotd, MixerVersion};
+
+use crate::{
+    config::MixerConfig,
+    error::{AppError, AppResult, UserError},
+};
+
+const DEFAULT_MAINTENANCE_MESSAGE: &str = "Instance is under maintenance, try again later.";
+
+/// Operator controlled state of instance.
+pub struct InstanceStatus {
+    admin_token: Option<String>,
+    motd: RwLock<MixerMotd>,
+    /// Present if instance opted in to directory.
+    directory_entry: Option<DirectoryEntry>,
+}
+
+impl InstanceStatus {
+    pub fn new(config: &MixerConfig) -> Self {
+        Self {
+            admin_token: config.admin_token.clone(),
+            motd: RwLock::new(MixerMotd {
+                motd: config.motd.clone(),
+                maintenance: config.maintenance.enabled.then(|| {
+                    config
+                        .maintenance
+                        .message
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_MAINTENANCE_MESSAGE.to_string())
+                }),
+            }),
+            directory_entry: config
+                .directory_listing
+                .as_ref()
+                .map(|listing| DirectoryEntry {
+                    url: listing.public_url.to_string(),
+                    operator: listing.operator.clone(),
+                    region: listing.region.clone(),
+                    policy: InstancePolicy {
+                        requires_api_key: config.require_api_key,
+                        privacy_logging: config.privacy_logging.enabled,
+                        persistent_cache: config.persistent_cache.is_some(),
+                    },
+                }),
+        }
+    }
+}
+
+/// Rejects requests while instance is in maintenance mode.
+pub async fn maintenance_guard(
+    State(status): State<Arc<InstanceStatus>>,
+    req: Request,
+    next: Next,
+) -> AppResult<Response> {
+    let maintenance = status.motd.read().unwrap().maintenance.clone();
+    match maintenance {
+        Some(message) => Err(AppError::Maintenance(message)),
+        None => Ok(next.run(req).await),
+    }
+}
+
+pub async fn require_admin(
+    State(status): State<Arc<InstanceStatus>>,
+    req: Request,
+    next: Next,
+) -> AppResult<Response> {
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "));
+    match (&status.admin_token, token) {
+        (Some(admin_token), Some(token)) if admin_token == token => Ok(next.run(req).await),
+        _ => Err(UserError::InvalidAdminToken.into()),
+    }
+}
+
+pub const VERSION_HEADER: &str = "x-urldebloater-version";
+
+pub fn mixer_version() -> MixerVersion {
+    MixerVersion {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: option_env!("MIXER_GIT_HASH").map(str::to_string),
+        rule_set_version: urlwasher::RULE_SET_VERSION,
+        protocol_version: urldebloater_proto::PROTOCOL_VERSION,
+    }
+}
+
+pub async fn version() -> Json<MixerVersion> {
+    Json(mixer_version())
+}
+
+/// Value of [`VERSION_HEADER`] added to every response: `{version}; rules={rule_set_version}; protocol={protocol_version}`.
+pub fn version_header_value() -> HeaderValue {
+    let version = mixer_version();
+    HeaderValue::from_str(&format!(
+        "{}; rules={}; protocol={}",
+        version.version, version.rule_set_version, version.protocol_version
+    ))
+    .expect("Invalid version header value")
+}
+
+/// Entry for public mixer directory, describing instance policy.
+pub async fn directory_entry(
+    State(status): State<Arc<InstanceStatus>>,
+) -> AppResult<Json<DirectoryEntry>> {
+    let entry = status.directory_entry.clone().ok_or(UserError::NotListed)?;
+    Ok(Json(entry))
+}
+
+pub async fn motd(State(status): State<Arc<InstanceStatus>>) -> Json<MixerMotd> {
+    Json(status.motd.read().unwrap().clone())
+}
+
+#[derive(Deserialize)]
+pub struct MaintenanceRequest {
+    enabled: bool,
+    message: Option<String>,
+}
+
+pub async fn set_maintenance(
+    State(status): State<Arc<InstanceStatus>>,
+    Json(req): Json<MaintenanceRequest>,
+) -> Json<MixerMotd> {
+    let mut motd = status.motd.write().unwrap();
+    motd.maintenance = req.enabled.then(|| {
+        req.message
+            .unwrap_or_else(|| DEFAULT_MAINTENANCE_MESSAGE.to_string())
+    });
+    info!("Maintenance mode changed: {:?}", motd.maintenance);
+    Json(motd.clone())
+}
+
+#[derive(Deserialize)]
+pub struct MotdRequest {
+    motd: Option<String>,
+}
+
+pub async fn set_motd(
+    State(status): State<Arc<InstanceStatus>>,
+    Json(req): Json<MotdRequest>,
+) -> Json<MixerMotd> {
+    let mut motd = status.motd.write().unwrap();
+    motd.motd = req.motd;
+    Json(motd.clone())
+}
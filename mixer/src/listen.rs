@@ -0,0 +1,42 @@
+use std::{io, net::SocketAddr};
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::TcpListener;
+
+/// Used when config does not list any addresses.
+pub const DEFAULT_LISTEN_ADDRESS: &str = "0.0.0.0:7777";
+const BACKLOG: i32 = 1024;
+
+/// Binds tcp listener like [`TcpListener::bind`], but IPv6 sockets accept only IPv6 connections,
+/// so `[::]:7777` and `0.0.0.0:7777` can be bound side by side on dual-stack hosts.
+pub fn bind(address: SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(
+        Domain::for_address(address),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+    if address.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&address.into())?;
+    socket.listen(BACKLOG)?;
+    TcpListener::from_std(socket.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+    use super::bind;
+
+    #[tokio::test]
+    async fn binds_same_port_on_v4_and_v6() {
+        let v6 = bind(SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0))).unwrap();
+        let port = v6.local_addr().unwrap().port();
+        let v4 = bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port))).unwrap();
+        assert_eq!(v4.local_addr().unwrap().port(), port);
+    }
+}
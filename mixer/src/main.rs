@@ -1,109 +1,462 @@
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use std::time::Duration;
 
 use anyhow::Context;
+use auth::{ApiKey, Authenticator};
 use axum::extract::{Query, State};
 use axum::{
-    error_handling::HandleErrorLayer, http::StatusCode, response::IntoResponse, routing::get,
-    BoxError, Router,
+    error_handling::HandleErrorLayer,
+    http::{HeaderName, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post, MethodRouter},
+    BoxError, Extension, Json, Router,
 };
-use axum_macros::debug_handler;
+use axum_macros::{debug_handler, FromRef};
+use config::{MixerConfig, TimeoutConfig};
 use error::{AppResult, UserError};
-use serde::Deserialize;
+use instance::InstanceStatus;
+use serde::Serialize;
+use stats::Stats;
+use store::SqliteRedirectStore;
+use tokio::task::JoinSet;
 use tower::ServiceBuilder;
-use tower_governor::key_extractor::SmartIpKeyExtractor;
-use tower_governor::{governor::GovernorConfigBuilder, GovernorError, GovernorLayer};
-use tower_http::trace::TraceLayer;
+use tower_http::{set_header::SetResponseHeaderLayer, trace::TraceLayer};
 use tracing::{error, info};
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::{
+    fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer,
+};
 use url::Url;
-use urlwasher::{RedirectWashPolicy, UrlWasher, UrlWasherConfig};
+use urlwasher::{
+    blocklist::{BlockedAction, BlockedDestination},
+    mixer_api::{ApiKeyUsage, WashQuery},
+    rule_source::{RemoteRules, RuleSource},
+    DirtyUrlRule, OffSiteRedirect, RedirectLoop, RedirectWashPolicy, UrlWasher, UrlWasherConfig,
+};
 
+mod auth;
+mod cache;
+mod config;
+mod dns;
 mod error;
+mod instance;
+mod listen;
+mod privacy;
+mod stats;
+mod store;
+mod telemetry;
+
+const DEFAULT_CACHE_SWEEP_INTERVAL_SECS: u64 = 60;
+const DEFAULT_RULES_REFRESH_INTERVAL_SECS: u64 = 6 * 60 * 60;
+/// Set on wash responses whose destination is on blocklist, contains the listed domain.
+const BLOCKED_HEADER: &str = "x-urldebloater-blocked";
+
+#[derive(Clone, FromRef)]
+struct AppState {
+    url_washer: Arc<UrlWasher>,
+    instance_status: Arc<InstanceStatus>,
+    stats: Arc<Stats>,
+    authenticator: Arc<Authenticator>,
+    rule_reloader: Arc<RuleReloader>,
+}
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .pretty()
-        .with_line_number(false)
-        .with_file(false)
+    tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env())
+        .with(
+            tracing_subscriber::fmt::layer()
+                .pretty()
+                .with_line_number(false)
+                .with_file(false)
+                // closed spans log their duration, e.g. of washer's redirect resolution
+                .with_span_events(FmtSpan::CLOSE)
+                .with_filter(privacy::UrlEventsFilter),
+        )
         .init();
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:7777")
-        .await
-        .expect("Could not bind tcp listener");
-    info!("Starting listening...");
-    axum::serve(
-        listener,
-        app(true).into_make_service_with_connect_info::<SocketAddr>(),
-    )
-    .await
-    .unwrap();
-}
-
-fn app(rate_limit: bool) -> Router {
-    let url_washer = UrlWasher::new({
-        let mut config = UrlWasherConfig::default();
-        config
+    let config = config::from_file().expect("Could not load config");
+    telemetry::init();
+    privacy::init(&config.privacy_logging);
+    let addresses = if config.listen.is_empty() {
+        vec![listen::DEFAULT_LISTEN_ADDRESS.parse().unwrap()]
+    } else {
+        config.listen.clone()
+    };
+    let listeners = addresses
+        .iter()
+        .map(|address| {
+            listen::bind(*address)
+                .unwrap_or_else(|err| panic!("Could not bind tcp listener on {address}: {err}"))
+        })
+        .collect::<Vec<_>>();
+    let service = app(&config, true).into_make_service_with_connect_info::<SocketAddr>();
+    let mut servers = JoinSet::new();
+    for (address, listener) in addresses.into_iter().zip(listeners) {
+        info!("Starting listening on {address}...");
+        let service = service.clone();
+        servers.spawn(async move { axum::serve(listener, service).await });
+    }
+    // servers run until error, so first one to finish stops whole mixer
+    if let Some(result) = servers.join_next().await {
+        result.expect("Server task panicked").unwrap();
+    }
+}
+
+fn app(config: &MixerConfig, rate_limit: bool) -> Router {
+    let mut http_client = urlwasher::http_client_builder();
+    if let Some(resolver) = dns::resolver(&config.dns).expect("Could not create dns resolver") {
+        http_client = http_client.dns_resolver(Arc::new(resolver));
+    }
+    let http_client = http_client.build().expect("Could not create http client");
+    let washer_rules = config.rules.initial_rules().expect("Could not load rules");
+    let washer_config = {
+        let mut washer_config = UrlWasherConfig::default();
+        washer_config
             .redirect_policy
             .iter_mut()
             .for_each(|(_, redirect_policy)| *redirect_policy = RedirectWashPolicy::Locally);
-        config
+        washer_config.set_redirect_policy(&washer_rules, RedirectWashPolicy::Locally);
+        washer_config.http_profiles = config.http_profiles.clone();
+        washer_config.cache_ttl_secs = config.cache_ttl_secs;
+        if let Some(cache_capacity) = config.cache_capacity {
+            washer_config.cache_capacity = cache_capacity;
+        }
+        // most of washed links are clean ones, scanning rules again for them is wasted work
+        washer_config.unmatched_cache_capacity = Some(washer_config.cache_capacity);
+        washer_config.detect_script_redirects = config.detect_script_redirects;
+        washer_config.redirect_request_method = config.redirect_request_method;
+        if let Some(redirect_timeout_ms) = config.redirect_timeout_ms {
+            washer_config.redirect_timeout_ms = Some(redirect_timeout_ms);
+        }
+        washer_config.redirect_retries = config.redirect_retries;
+        if let Some(concurrency) = config.redirect_concurrency_per_domain {
+            washer_config.redirect_concurrency_per_domain = Some(concurrency);
+        }
+        washer_config.same_site_redirects = config.same_site_redirects;
+        washer_config.wash_nested_urls = config.wash_nested_urls;
+        washer_config.shorteners = config.shorteners.clone();
+        if let Some(max_redirect_hops) = config.max_redirect_hops {
+            washer_config.max_redirect_hops = max_redirect_hops;
+        }
+        washer_config.blocklist = config.blocklist.clone();
+        washer_config
+    };
+    let redirect_store = config.persistent_cache.as_ref().map(|persistent_cache| {
+        let ttl = config.cache_ttl_secs.map(Duration::from_secs);
+        Arc::new(
+            SqliteRedirectStore::open(persistent_cache, ttl)
+                .expect("Could not open persistent cache"),
+        )
     });
+    // washes are counted by observer, it knows their rule and whether cache was hit
+    let stats = Arc::new(Stats::default());
+    let mut url_washer = UrlWasher::with_http_client(washer_config, http_client.clone())
+        .with_rules(washer_rules)
+        .with_observer(stats.clone());
+    if let Some(redirect_store) = &redirect_store {
+        url_washer = url_washer.with_redirect_store(redirect_store.clone());
+    }
+    let url_washer = Arc::new(url_washer);
+    if config.cache_ttl_secs.is_some() || redirect_store.is_some() {
+        let interval = config
+            .cache_sweep_interval_secs
+            .unwrap_or(DEFAULT_CACHE_SWEEP_INTERVAL_SECS);
+        tokio::spawn(sweep_cache(
+            Arc::downgrade(&url_washer),
+            redirect_store,
+            Duration::from_secs(interval),
+        ));
+    }
+    if !config.blocklist.feeds.is_empty() {
+        tokio::spawn(refresh_blocklist(
+            Arc::downgrade(&url_washer),
+            Duration::from_secs(config.blocklist.refresh_interval_secs),
+        ));
+    }
+    let rule_reloader = Arc::new(RuleReloader::new(config.rules.clone(), http_client));
+    if rule_reloader.remote.is_some() {
+        tokio::spawn(refresh_rules(
+            Arc::downgrade(&url_washer),
+            rule_reloader.clone(),
+            Duration::from_secs(
+                config
+                    .rules_refresh_interval_secs
+                    .unwrap_or(DEFAULT_RULES_REFRESH_INTERVAL_SECS),
+            ),
+        ));
+    }
+    let authenticator = Arc::new(Authenticator::new(config, rate_limit));
+    let state = AppState {
+        url_washer,
+        instance_status: Arc::new(InstanceStatus::new(config)),
+        stats,
+        authenticator: authenticator.clone(),
+        rule_reloader,
+    };
+    let timeouts = &config.timeouts;
+    let api = Router::new()
+        .timed_route("/wash", get(wash), timeouts)
+        .timed_route("/usage", get(usage), timeouts)
+        .route_layer(middleware::from_fn_with_state(
+            authenticator,
+            auth::authenticate,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.instance_status.clone(),
+            instance::maintenance_guard,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            state.stats.clone(),
+            stats::record,
+        ));
+    let admin = Router::new()
+        .timed_route(
+            "/admin/maintenance",
+            post(instance::set_maintenance),
+            timeouts,
+        )
+        .timed_route("/admin/motd", post(instance::set_motd), timeouts)
+        .timed_route(
+            "/admin/cache",
+            get(cache::entries).delete(cache::purge),
+            timeouts,
+        )
+        .timed_route("/admin/stats", get(stats::snapshot), timeouts)
+        .timed_route("/admin/blocklist", post(reload_blocklist), timeouts)
+        .timed_route("/admin/rules", post(reload_rules), timeouts)
+        .timed_route("/admin/bans", get(auth::bans), timeouts)
+        .timed_route("/admin/metrics", get(telemetry::render), timeouts)
+        .route_layer(middleware::from_fn_with_state(
+            state.instance_status.clone(),
+            instance::require_admin,
+        ));
     Router::new()
-        .route("/wash", get(wash))
+        .merge(api)
+        .merge(admin)
+        .timed_route("/admin/dashboard", get(stats::dashboard), timeouts)
+        .timed_route("/version", get(instance::version), timeouts)
+        .timed_route("/motd", get(instance::motd), timeouts)
+        .timed_route("/rules", get(rules), timeouts)
+        .timed_route("/directory-entry", get(instance::directory_entry), timeouts)
         .layer(
             ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
-                .layer(HandleErrorLayer::new(handle_service_err))
-                .timeout(Duration::from_secs(10))
-                .option_layer(if rate_limit {
-                    Some(GovernorLayer {
-                        config: Box::leak(Box::new(
-                            GovernorConfigBuilder::default()
-                                .per_second(5)
-                                .burst_size(10)
-                                .key_extractor(SmartIpKeyExtractor)
-                                .finish()
-                                .unwrap(),
-                        )),
-                    })
-                } else {
-                    None
-                }),
+                .layer(TraceLayer::new_for_http().make_span_with(privacy::RequestSpan))
+                .layer(SetResponseHeaderLayer::overriding(
+                    HeaderName::from_static(instance::VERSION_HEADER),
+                    instance::version_header_value(),
+                )),
         )
-        .with_state(Arc::new(url_washer))
+        .with_state(state)
 }
 
-#[derive(Deserialize)]
-struct WashQuery {
-    url: String,
+/// Evicts expired cache entries until app (and its washer) is dropped.
+async fn sweep_cache(
+    url_washer: Weak<UrlWasher>,
+    redirect_store: Option<Arc<SqliteRedirectStore>>,
+    interval: Duration,
+) {
+    let mut interval = tokio::time::interval(interval);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let Some(url_washer) = url_washer.upgrade() else {
+            return;
+        };
+        let removed = url_washer.cache_sweep();
+        if removed > 0 {
+            info!("Evicted {removed} expired cache entries.");
+        }
+        match redirect_store.as_ref().map(|store| store.sweep()) {
+            Some(Ok(removed)) if removed > 0 => info!("Evicted {removed} stored redirects."),
+            Some(Err(err)) => error!("Could not sweep persistent cache: {err:?}"),
+            _ => {}
+        }
+    }
 }
 
-#[debug_handler]
+/// Refreshes blocklist feeds until app (and its washer) is dropped.
+async fn refresh_blocklist(url_washer: Weak<UrlWasher>, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        let Some(url_washer) = url_washer.upgrade() else {
+            return;
+        };
+        match url_washer.refresh_blocklist().await {
+            Ok(listed) => info!("Refreshed blocklist, {listed} domains are listed."),
+            Err(err) => error!("Could not refresh blocklist: {err:?}"),
+        }
+    }
+}
+
+/// Loads rules of configured [`RuleSource`] into washer, periodically or on admin request.
+struct RuleReloader {
+    source: RuleSource,
+    /// Remembers last fetched remote rule set, so unchanged one is not applied again.
+    remote: Option<RemoteRules>,
+    http_client: reqwest::Client,
+}
+
+impl RuleReloader {
+    fn new(source: RuleSource, http_client: reqwest::Client) -> Self {
+        let remote = match &source {
+            RuleSource::Remote(url) => Some(RemoteRules::new(url.clone())),
+            RuleSource::BuiltIn | RuleSource::File(_) => None,
+        };
+        Self {
+            source,
+            remote,
+            http_client,
+        }
+    }
+
+    /// Returns how many rules washer has now, [`None`] if remote rule set did not change.
+    /// Rule file is read again, so edits are picked up without restart.
+    async fn reload(&self, url_washer: Arc<UrlWasher>) -> anyhow::Result<Option<usize>> {
+        if let Some(remote) = &self.remote {
+            return remote.refresh(&self.http_client, &[url_washer]).await;
+        }
+        let rules = self.source.initial_rules()?;
+        let len = rules.len();
+        url_washer.replace_rules(rules);
+        Ok(Some(len))
+    }
+}
+
+/// Fetches remote rule set until app (and its washer) is dropped, first time right after start.
+async fn refresh_rules(
+    url_washer: Weak<UrlWasher>,
+    rule_reloader: Arc<RuleReloader>,
+    interval: Duration,
+) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        let Some(url_washer) = url_washer.upgrade() else {
+            return;
+        };
+        match rule_reloader.reload(url_washer).await {
+            Ok(Some(rules)) => info!("Updated remote rules, {rules} rules loaded."),
+            Ok(None) => {}
+            Err(err) => error!("Could not refresh rules: {err:?}"),
+        }
+    }
+}
+
+/// Adds route with time of handling limited by its [`TimeoutConfig`] entry, so path is written once.
+trait TimedRoute<S> {
+    fn timed_route(self, path: &str, route: MethodRouter<S>, timeouts: &TimeoutConfig) -> Self;
+}
+
+impl<S> TimedRoute<S> for Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    fn timed_route(self, path: &str, route: MethodRouter<S>, timeouts: &TimeoutConfig) -> Self {
+        self.route(
+            path,
+            route.layer(
+                ServiceBuilder::new()
+                    .layer(HandleErrorLayer::new(handle_service_err))
+                    .timeout(timeouts.route(path)),
+            ),
+        )
+    }
+}
+
+#[debug_handler(state = AppState)]
 async fn wash(
     State(washer): State<Arc<UrlWasher>>,
     Query(query): Query<WashQuery>,
-) -> AppResult<String> {
+) -> AppResult<Response> {
     const MAX_URL_LENGTH: usize = 1024;
     if query.url.len() > MAX_URL_LENGTH {
         return Err(UserError::TooLongUrl.into());
     }
 
     let url = Url::parse(&query.url).map_err(|_| UserError::InvalidUrl)?;
-    let washed = washer.wash(&url).await.context("wash url")?;
-    Ok(washed.unwrap_or(url).to_string())
+    let washed = match washer.wash(&url).await {
+        Ok(washed) => washed,
+        Err(err) => match err.downcast::<BlockedDestination>() {
+            Ok(blocked) if blocked.action == BlockedAction::Flag => {
+                let header = HeaderName::from_static(BLOCKED_HEADER);
+                return Ok(([(header, blocked.domain)], blocked.url.to_string()).into_response());
+            }
+            Ok(_) => return Err(UserError::BlockedDestination.into()),
+            Err(err) if err.is::<RedirectLoop>() => return Err(UserError::RedirectLoop.into()),
+            Err(err) if err.is::<OffSiteRedirect>() => {
+                return Err(UserError::OffSiteRedirect.into())
+            }
+            Err(err) => return Err(err.context("wash url").into()),
+        },
+    };
+    Ok(washed.unwrap_or(url).to_string().into_response())
+}
+
+async fn usage(api_key: Option<Extension<Arc<ApiKey>>>) -> AppResult<Json<ApiKeyUsage>> {
+    let Extension(api_key) = api_key.ok_or(UserError::MissingApiKey)?;
+    Ok(Json(api_key.usage()))
+}
+
+/// Rule file (see [`urlwasher::rule_file`]) with rules of this instance.
+#[derive(Serialize)]
+struct RulesResponse {
+    rules: Vec<DirtyUrlRule>,
+}
+
+async fn rules(State(washer): State<Arc<UrlWasher>>) -> Json<RulesResponse> {
+    Json(RulesResponse {
+        rules: washer.rules().to_vec(),
+    })
+}
+
+#[derive(Serialize)]
+struct BlocklistResponse {
+    listed: usize,
+}
+
+/// Refetches blocklist feeds without waiting for next scheduled refresh.
+async fn reload_blocklist(
+    State(washer): State<Arc<UrlWasher>>,
+) -> AppResult<Json<BlocklistResponse>> {
+    let listed = washer
+        .refresh_blocklist()
+        .await
+        .context("refresh blocklist")?;
+    info!("Refreshed blocklist on admin request, {listed} domains are listed.");
+    Ok(Json(BlocklistResponse { listed }))
+}
+
+#[derive(Serialize)]
+struct RulesReloadResponse {
+    /// Not set if remote rule set did not change.
+    rules: Option<usize>,
+}
+
+/// Reloads rules without waiting for next scheduled refresh.
+async fn reload_rules(
+    State(washer): State<Arc<UrlWasher>>,
+    State(rule_reloader): State<Arc<RuleReloader>>,
+) -> AppResult<Json<RulesReloadResponse>> {
+    let rules = rule_reloader.reload(washer).await.context("reload rules")?;
+    match rules {
+        Some(rules) => info!("Reloaded rules on admin request, {rules} rules loaded."),
+        None => info!("Reloaded rules on admin request, remote rules did not change."),
+    }
+    Ok(Json(RulesReloadResponse { rules }))
 }
 
 async fn handle_service_err(err: BoxError) -> impl IntoResponse {
-    if let Some(GovernorError::TooManyRequests { .. }) = err.downcast_ref::<GovernorError>() {
-        (StatusCode::TOO_MANY_REQUESTS).into_response()
-    } else {
-        error!("Internal server error: {err:?}");
-        (StatusCode::INTERNAL_SERVER_ERROR).into_response()
+    if err.is::<tower::timeout::error::Elapsed>() {
+        return StatusCode::GATEWAY_TIMEOUT.into_response();
     }
+    error!(
+        "Internal server error: {}",
+        privacy::text(&format!("{err:?}"))
+    );
+    (StatusCode::INTERNAL_SERVER_ERROR).into_response()
 }
 
 #[cfg(test)]
@@ -116,7 +469,7 @@ mod tests {
 
     #[tokio::test]
     async fn cleans_url() {
-        let app = app(false);
+        let app = app(&MixerConfig::default(), false);
 
         let response = app
             .oneshot(
@@ -134,4 +487,423 @@ mod tests {
         let body = String::from_utf8_lossy(&body);
         assert_eq!(body, "https://youtube.com/watch?v=d2348942389234&t=123");
     }
+
+    #[tokio::test]
+    async fn enforces_api_key_quota() {
+        let config: MixerConfig = serde_json::from_str(
+            r#"{
+                "require_api_key": true,
+                "api_keys": [{ "name": "friend", "key": "secret", "daily_quota": 1 }]
+            }"#,
+        )
+        .unwrap();
+        let app = app(&config, true);
+        let request = |api_key: Option<&str>| {
+            let mut builder = Request::builder().uri("/wash?url=https://example.com/");
+            if let Some(api_key) = api_key {
+                builder = builder.header("Authorization", format!("Bearer {api_key}"));
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+
+        let response = app.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.clone().oneshot(request(Some("bad"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.clone().oneshot(request(Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.oneshot(request(Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn rate_limits_api_key() {
+        let config: MixerConfig = serde_json::from_str(
+            r#"{
+                "api_keys": [{
+                    "name": "friend",
+                    "key": "secret",
+                    "rate_limit": { "per_second": 1, "burst_size": 1 }
+                }]
+            }"#,
+        )
+        .unwrap();
+        let app = app(&config, true);
+        let request = || {
+            Request::builder()
+                .uri("/wash?url=https://example.com/")
+                .header("X-Api-Key", "secret")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "too many requests");
+    }
+
+    #[tokio::test]
+    async fn rate_limits_anonymous_clients_per_ip() {
+        let config: MixerConfig = serde_json::from_str(
+            r#"{ "anonymous_rate_limit": { "per_second": 1, "burst_size": 1 } }"#,
+        )
+        .unwrap();
+        let app = app(&config, true);
+        let request = |ip: &str| {
+            Request::builder()
+                .uri("/wash?url=https://example.com/")
+                .header("X-Forwarded-For", ip)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("192.0.2.1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.clone().oneshot(request("192.0.2.1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = app.oneshot(request("192.0.2.2")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn reports_api_key_usage() {
+        let config: MixerConfig = serde_json::from_str(
+            r#"{ "api_keys": [{ "name": "friend", "key": "secret", "daily_quota": 10 }] }"#,
+        )
+        .unwrap();
+        let app = app(&config, false);
+        let request = |uri: &str, api_key: Option<&str>| {
+            let mut builder = Request::builder().uri(uri);
+            if let Some(api_key) = api_key {
+                builder = builder.header("X-Api-Key", api_key);
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+
+        let response = app.clone().oneshot(request("/usage", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "missing api key");
+        let response = app
+            .clone()
+            .oneshot(request("/usage", Some("bad")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "invalid api key");
+
+        let response = app
+            .clone()
+            .oneshot(request("/wash?url=https://example.com/", Some("secret")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app
+            .oneshot(request("/usage", Some("secret")))
+            .await
+            .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let usage: ApiKeyUsage = serde_json::from_slice(&body).unwrap();
+        assert_eq!(usage.name, "friend");
+        // usage request itself is counted too
+        assert_eq!(usage.requests_today, 2);
+        assert_eq!(usage.requests_total, 2);
+        assert_eq!(usage.daily_quota, Some(10));
+    }
+
+    #[tokio::test]
+    async fn maintenance_mode_rejects_wash() {
+        let config: MixerConfig = serde_json::from_str(r#"{ "admin_token": "admin" }"#).unwrap();
+        let app = app(&config, false);
+        let set_maintenance = |token: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/admin/maintenance")
+                .header("Authorization", format!("Bearer {token}"))
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{ "enabled": true, "message": "brb" }"#))
+                .unwrap()
+        };
+        let wash_request = || {
+            Request::builder()
+                .uri("/wash?url=https://example.com/")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(set_maintenance("bad")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.clone().oneshot(wash_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.clone().oneshot(set_maintenance("admin")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.clone().oneshot(wash_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "brb");
+
+        let response = app
+            .oneshot(Request::builder().uri("/motd").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, r#"{"motd":null,"maintenance":"brb"}"#);
+    }
+
+    #[tokio::test]
+    async fn admin_purges_cache() {
+        let config: MixerConfig = serde_json::from_str(r#"{ "admin_token": "admin" }"#).unwrap();
+        let app = app(&config, false);
+        let admin_request = |method: &str| {
+            Request::builder()
+                .method(method)
+                .uri("/admin/cache")
+                .header("Authorization", "Bearer admin")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let wash_request = Request::builder()
+            .uri("/wash?url=https://youtu.be/lSwnPoo9ZK0?si%3DTRACKING")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.clone().oneshot(wash_request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.clone().oneshot(admin_request("GET")).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            body,
+            r#"[{"url":"https://youtu.be/lSwnPoo9ZK0?si=TRACKING","washed":"https://youtu.be/lSwnPoo9ZK0"}]"#
+        );
+
+        let response = app.clone().oneshot(admin_request("DELETE")).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, r#"{"removed":1}"#);
+        let response = app.oneshot(admin_request("GET")).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "[]");
+    }
+
+    #[tokio::test]
+    async fn exposes_washer_metrics() {
+        telemetry::init();
+        let config: MixerConfig = serde_json::from_str(r#"{ "admin_token": "admin" }"#).unwrap();
+        let app = app(&config, false);
+        let wash_request = Request::builder()
+            .uri("/wash?url=https://youtu.be/lSwnPoo9ZK0?si%3DTRACKING")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(wash_request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/admin/metrics")
+                    .header("Authorization", "Bearer admin")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains(r#"urlwasher_washes_total{outcome="washed"}"#));
+    }
+
+    #[tokio::test]
+    async fn dashboard_reports_stats() {
+        let config: MixerConfig = serde_json::from_str(r#"{ "admin_token": "admin" }"#).unwrap();
+        let app = app(&config, false);
+        let wash_request = || {
+            Request::builder()
+                .uri("/wash?url=https://youtu.be/lSwnPoo9ZK0?si%3DTRACKING")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let stats_request = |token: &str| {
+            Request::builder()
+                .uri("/admin/stats")
+                .header("Authorization", format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(wash_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.clone().oneshot(wash_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/wash").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app.clone().oneshot(stats_request("bad")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.oneshot(stats_request("admin")).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats["requests_total"], 3);
+        assert_eq!(stats["requests_last_minute"], 3);
+        assert_eq!(stats["client_errors"], 1);
+        assert_eq!(stats["cache_hit_ratio"], 0.5);
+        assert_eq!(stats["rules"], serde_json::json!({ "youtu.be": 2 }));
+    }
+
+    #[tokio::test]
+    async fn admin_lists_active_bans() {
+        let config: MixerConfig = serde_json::from_str(
+            r#"{
+                "admin_token": "admin",
+                "anonymous_rate_limit": { "per_second": 1, "burst_size": 1 },
+                "api_keys": [{ "name": "friend", "key": "secret", "daily_quota": 1 }]
+            }"#,
+        )
+        .unwrap();
+        let app = app(&config, true);
+        let wash_request = |header: (&str, &str)| {
+            Request::builder()
+                .uri("/wash?url=https://example.com/")
+                .header(header.0, header.1)
+                .body(Body::empty())
+                .unwrap()
+        };
+        for request in [
+            wash_request(("X-Forwarded-For", "192.0.2.1")),
+            wash_request(("X-Forwarded-For", "192.0.2.1")),
+            wash_request(("X-Api-Key", "secret")),
+            wash_request(("X-Api-Key", "secret")),
+        ] {
+            app.clone().oneshot(request).await.unwrap();
+        }
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/admin/bans")
+                    .header("Authorization", "Bearer admin")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let bans: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            bans[0]["client"],
+            serde_json::json!({ "api_key": "friend" })
+        );
+        assert_eq!(bans[0]["reason"], "daily_quota");
+        assert_eq!(bans[1]["client"], serde_json::json!({ "ip": "192.0.2.1" }));
+        assert_eq!(bans[1]["reason"], "rate_limit");
+        assert!(bans[1]["expires_in_ms"].as_u64().unwrap() <= 1000);
+        assert_eq!(bans.as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn admin_reloads_rules() {
+        let config: MixerConfig = serde_json::from_str(r#"{ "admin_token": "admin" }"#).unwrap();
+        let app = app(&config, false);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/admin/rules")
+                    .header("Authorization", "Bearer admin")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            body,
+            format!(r#"{{"rules":{}}}"#, urlwasher::rule_set().len())
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_version() {
+        let app = app(&MixerConfig::default(), false);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/version")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[instance::VERSION_HEADER],
+            instance::version_header_value()
+        );
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let version: urlwasher::mixer_api::MixerVersion = serde_json::from_slice(&body).unwrap();
+        assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(version.rule_set_version, urlwasher::RULE_SET_VERSION);
+    }
+
+    #[tokio::test]
+    async fn serves_rules_as_rule_file() {
+        let app = app(&MixerConfig::default(), false);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/rules")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let layer = urlwasher::rule_file::import(&body).unwrap();
+        assert_eq!(layer.rules.len(), urlwasher::rule_set().len());
+    }
+
+    #[test]
+    fn configured_route_timeouts_override_defaults() {
+        let timeouts = TimeoutConfig::default();
+        assert_eq!(timeouts.route("/version"), Duration::from_secs(2));
+        assert_eq!(timeouts.route("/wash"), Duration::from_secs(10));
+        assert_eq!(timeouts.route("/admin/blocklist"), Duration::from_secs(60));
+
+        let timeouts: TimeoutConfig =
+            serde_json::from_str(r#"{ "default_ms": 1000, "routes": { "/wash": 5000 } }"#).unwrap();
+        assert_eq!(timeouts.route("/version"), Duration::from_secs(1));
+        assert_eq!(timeouts.route("/wash"), Duration::from_secs(5));
+        assert_eq!(timeouts.route("/admin/blocklist"), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn persistent_cache_evicts_over_limit() {
+        use urlwasher::RedirectStore;
+
+        let config: store::PersistentCacheConfig =
+            serde_json::from_str(r#"{ "path": ":memory:", "max_entries": 1 }"#).unwrap();
+        let store = SqliteRedirectStore::open(&config, None).unwrap();
+        let short = |id: &str| Url::parse(&format!("https://vm.tiktok.com/{id}/")).unwrap();
+        let location = Url::parse("https://www.tiktok.com/@i0ki.clips/video/1").unwrap();
+        store.insert(short("first"), location.clone());
+        assert_eq!(store.location(&short("first")), Some(location.clone()));
+
+        store.insert(short("second"), location.clone());
+        // both were resolved in the same second, so either one may be evicted
+        assert_eq!(store.sweep().unwrap(), 1);
+        store.clear();
+        assert_eq!(store.location(&short("second")), None);
+    }
 }
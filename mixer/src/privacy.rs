@@ -0,0 +1,176 @@
+use std::{borrow::Cow, sync::OnceLock};
+
+use axum::http::Request;
+use ring::hmac;
+use serde::Deserialize;
+use tower_http::trace::{DefaultMakeSpan, MakeSpan};
+use tracing::{debug_span, subscriber::Interest, Level, Metadata, Span};
+use tracing_subscriber::layer::{Context, Filter};
+use url::Url;
+use urlwasher::public_suffix::registrable_domain;
+
+/// Bytes of url hmac kept in logs, enough to tell urls apart.
+const HASH_LEN: usize = 8;
+/// Crates whose debug events contain whole urls of washed links, e.g. washer's cache hits
+/// and http client's redirects. Their spans record domains only.
+const URL_LOGGING_TARGETS: &[&str] = &["urlwasher", "reqwest", "hyper"];
+
+static CENSOR: OnceLock<Censor> = OnceLock::new();
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct PrivacyLoggingConfig {
+    /// Replace urls in traces and errors with their eTLD+1 and salted hash.
+    pub enabled: bool,
+    /// Salt of url hashes, random on every start if not set,
+    /// so hashes of the same url can be correlated only within one run.
+    pub salt: Option<String>,
+}
+
+/// Turns on censoring of urls logged by mixer, process wide since logging is too.
+pub fn init(config: &PrivacyLoggingConfig) {
+    if !config.enabled {
+        return;
+    }
+    let salt = config
+        .salt
+        .clone()
+        .unwrap_or_else(|| format!("{:016x}", rand::random::<u64>()));
+    if CENSOR.set(Censor::new(&salt)).is_err() {
+        panic!("Privacy logging is already initialized");
+    }
+}
+
+/// Url as it should be logged, censored if privacy logging is enabled.
+pub fn url(url: &str) -> Cow<'_, str> {
+    match CENSOR.get() {
+        Some(censor) => Cow::Owned(censor.url(url)),
+        None => Cow::Borrowed(url),
+    }
+}
+
+/// Text (e.g. error message) as it should be logged, with urls censored if privacy logging is enabled.
+pub fn text(text: &str) -> Cow<'_, str> {
+    match CENSOR.get() {
+        Some(censor) => Cow::Owned(censor.text(text)),
+        None => Cow::Borrowed(text),
+    }
+}
+
+/// Request span of [`tower_http::trace::TraceLayer`] that does not record query (and urls in it)
+/// when privacy logging is enabled.
+#[derive(Clone, Default)]
+pub struct RequestSpan;
+
+impl<B> MakeSpan<B> for RequestSpan {
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        if CENSOR.get().is_none() {
+            return DefaultMakeSpan::new().make_span(request);
+        }
+        let washed_url = request.uri().query().and_then(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "url")
+                .map(|(_, value)| url(&value).into_owned())
+        });
+        debug_span!(
+            "request",
+            method = %request.method(),
+            path = request.uri().path(),
+            url = washed_url,
+            version = ?request.version(),
+        )
+    }
+}
+
+/// Drops debug events of [`URL_LOGGING_TARGETS`] when privacy logging is enabled,
+/// their urls cannot be censored since they are logged outside of mixer.
+pub struct UrlEventsFilter;
+
+impl<S> Filter<S> for UrlEventsFilter {
+    fn enabled(&self, metadata: &Metadata<'_>, _: &Context<'_, S>) -> bool {
+        CENSOR.get().is_none()
+            || !metadata.is_event()
+            || *metadata.level() <= Level::INFO
+            || !URL_LOGGING_TARGETS
+                .iter()
+                .any(|target| metadata.target().starts_with(target))
+    }
+
+    fn callsite_enabled(&self, _: &'static Metadata<'static>) -> Interest {
+        // privacy logging is initialized after logging, so interest of callsites cannot be cached
+        Interest::sometimes()
+    }
+}
+
+struct Censor {
+    /// Keyed by salt, so hashes are stable across releases and cannot be reversed without it.
+    key: hmac::Key,
+}
+
+impl Censor {
+    fn new(salt: &str) -> Self {
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, salt.as_bytes()),
+        }
+    }
+
+    /// e.g. `tiktok.com#1f0c8d3a5b7e9246`.
+    fn url(&self, url: &str) -> String {
+        let tag = hmac::sign(&self.key, url.as_bytes());
+        let hash = tag.as_ref()[..HASH_LEN]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        let site = Url::parse(url)
+            .ok()
+            .and_then(|url| {
+                url.domain()
+                    .map(|domain| registrable_domain(domain).unwrap_or(domain).to_string())
+            })
+            .unwrap_or_else(|| "-".to_string());
+        format!("{site}#{hash}")
+    }
+
+    fn text(&self, text: &str) -> String {
+        let mut censored = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = ["http://", "https://"]
+            .iter()
+            .filter_map(|scheme| rest.find(scheme))
+            .min()
+        {
+            censored.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find(|c: char| c.is_whitespace() || matches!(c, ')' | '"' | '\'' | '>' | ','))
+                .map_or(rest.len(), |end| start + end);
+            censored.push_str(&self.url(&rest[start..end]));
+            rest = &rest[end..];
+        }
+        censored.push_str(rest);
+        censored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Censor;
+
+    #[test]
+    fn censors_urls_in_text() {
+        let censor = Censor::new("salt");
+        let url = "https://vm.tiktok.com/ZMabc/";
+        let censored = censor.url(url);
+        assert!(censored.starts_with("tiktok.com#"));
+        assert_eq!(censored.len(), "tiktok.com#".len() + 16);
+        assert!(censor
+            .url("https://a.b.bbc.co.uk/news")
+            .starts_with("bbc.co.uk#"));
+        assert_ne!(censored, Censor::new("other salt").url(url));
+        assert_eq!(censored, censor.url(url));
+        assert_ne!(censored, censor.url("https://vm.tiktok.com/ZMother/"));
+        assert_eq!(
+            censor.text(&format!("error sending request for url ({url}): timed out")),
+            format!("error sending request for url ({censored}): timed out")
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{Html, Response},
+    Json,
+};
+use serde::Serialize;
+use tower_governor::key_extractor::{KeyExtractor, SmartIpKeyExtractor};
+use urlwasher::{WashEvent, WashObserver};
+
+/// Window of request rate and rate limited clients.
+const WINDOW_SECS: u64 = 60;
+
+/// Live counters of api traffic shown on admin dashboard, reset on restart.
+pub struct Stats {
+    started_at: Instant,
+    requests: AtomicU64,
+    client_errors: AtomicU64,
+    server_errors: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    /// Requests per second of uptime, `(second, count)` slots reused every [`WINDOW_SECS`].
+    recent_requests: Mutex<[(u64, u64); WINDOW_SECS as usize]>,
+    /// Washes that changed url keyed by name of their rule.
+    rules: Mutex<HashMap<String, u64>>,
+    /// Last time client was rejected with status 429.
+    rate_limited: Mutex<HashMap<IpAddr, Instant>>,
+}
+
+#[derive(Serialize)]
+pub struct StatsSnapshot {
+    uptime_secs: u64,
+    requests_total: u64,
+    requests_last_minute: u64,
+    client_errors: u64,
+    server_errors: u64,
+    cache_hits: u64,
+    cache_misses: u64,
+    /// Not set before first wash.
+    cache_hit_ratio: Option<f64>,
+    rules: HashMap<String, u64>,
+    /// Clients rejected by rate limits in last minute.
+    rate_limited_clients: usize,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            started_at: Instant::now(),
+            requests: AtomicU64::default(),
+            client_errors: AtomicU64::default(),
+            server_errors: AtomicU64::default(),
+            cache_hits: AtomicU64::default(),
+            cache_misses: AtomicU64::default(),
+            recent_requests: Mutex::new([(0, 0); WINDOW_SECS as usize]),
+            rules: Mutex::default(),
+            rate_limited: Mutex::default(),
+        }
+    }
+}
+
+impl WashObserver for Stats {
+    /// Records wash that changed url, by its rule.
+    fn on_washed(&self, event: &WashEvent) {
+        let Some(rule) = event.rule else {
+            return;
+        };
+        *self
+            .rules
+            .lock()
+            .unwrap()
+            .entry(rule.to_string())
+            .or_default() += 1;
+        let counter = if event.cached {
+            &self.cache_hits
+        } else {
+            &self.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Stats {
+    fn record_response(&self, status: StatusCode, client_ip: Option<IpAddr>) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let second = self.started_at.elapsed().as_secs();
+        {
+            let mut recent_requests = self.recent_requests.lock().unwrap();
+            let slot = &mut recent_requests[(second % WINDOW_SECS) as usize];
+            if slot.0 != second {
+                *slot = (second, 0);
+            }
+            slot.1 += 1;
+        }
+        if status.is_server_error() {
+            self.server_errors.fetch_add(1, Ordering::Relaxed);
+        } else if status.is_client_error() {
+            self.client_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        if let Some(client_ip) = client_ip.filter(|_| status == StatusCode::TOO_MANY_REQUESTS) {
+            let now = Instant::now();
+            let mut rate_limited = self.rate_limited.lock().unwrap();
+            rate_limited.retain(|_, rejected_at| now - *rejected_at < window());
+            rate_limited.insert(client_ip, now);
+        }
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let uptime = self.started_at.elapsed();
+        let second = uptime.as_secs();
+        let cache_hits = self.cache_hits.load(Ordering::Relaxed);
+        let cache_misses = self.cache_misses.load(Ordering::Relaxed);
+        let washes = cache_hits + cache_misses;
+        StatsSnapshot {
+            uptime_secs: second,
+            requests_total: self.requests.load(Ordering::Relaxed),
+            requests_last_minute: self
+                .recent_requests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(slot_second, _)| slot_second + WINDOW_SECS > second)
+                .map(|(_, count)| count)
+                .sum(),
+            client_errors: self.client_errors.load(Ordering::Relaxed),
+            server_errors: self.server_errors.load(Ordering::Relaxed),
+            cache_hits,
+            cache_misses,
+            cache_hit_ratio: (washes > 0).then(|| cache_hits as f64 / washes as f64),
+            rules: self.rules.lock().unwrap().clone(),
+            rate_limited_clients: self
+                .rate_limited
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|rejected_at| rejected_at.elapsed() < window())
+                .count(),
+        }
+    }
+}
+
+fn window() -> Duration {
+    Duration::from_secs(WINDOW_SECS)
+}
+
+/// Counts api responses by status.
+pub async fn record(State(stats): State<Arc<Stats>>, req: Request, next: Next) -> Response {
+    let client_ip = SmartIpKeyExtractor.extract(&req).ok();
+    let response = next.run(req).await;
+    stats.record_response(response.status(), client_ip);
+    response
+}
+
+pub async fn snapshot(State(stats): State<Arc<Stats>>) -> Json<StatsSnapshot> {
+    Json(stats.snapshot())
+}
+
+/// Dashboard page, it asks for admin token and reads everything through admin api.
+pub async fn dashboard() -> Html<&'static str> {
+    Html(include_str!("dashboard.html"))
+}
@@ -0,0 +1,170 @@
+use std::{
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use tracing::error;
+use url::Url;
+use urlwasher::RedirectStore;
+
+use crate::privacy;
+
+#[derive(Deserialize, Debug)]
+pub struct PersistentCacheConfig {
+    /// Sqlite database file, created if missing.
+    pub path: PathBuf,
+    /// Oldest resolved redirects above this limit are evicted on sweep.
+    #[serde(default = "default_max_entries")]
+    pub max_entries: u64,
+}
+
+fn default_max_entries() -> u64 {
+    100_000
+}
+
+/// Resolved short links kept in sqlite, so they survive restarts.
+///
+/// Database can be inspected offline, e.g. `sqlite3 cache.db 'SELECT * FROM redirects'`.
+pub struct SqliteRedirectStore {
+    connection: Mutex<Connection>,
+    ttl: Option<Duration>,
+    max_entries: u64,
+}
+
+impl SqliteRedirectStore {
+    pub fn open(config: &PersistentCacheConfig, ttl: Option<Duration>) -> anyhow::Result<Self> {
+        let connection = Connection::open(&config.path)
+            .with_context(|| format!("open {}", config.path.display()))?;
+        Self::with_connection(connection, ttl, config.max_entries)
+    }
+
+    fn with_connection(
+        connection: Connection,
+        ttl: Option<Duration>,
+        max_entries: u64,
+    ) -> anyhow::Result<Self> {
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS redirects (
+                    short_url TEXT PRIMARY KEY,
+                    location TEXT NOT NULL,
+                    resolved_at INTEGER NOT NULL,
+                    expires_at INTEGER
+                );
+                CREATE INDEX IF NOT EXISTS redirects_resolved_at ON redirects (resolved_at);",
+            )
+            .context("create schema")?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            ttl,
+            max_entries,
+        })
+    }
+
+    /// Evicts expired redirects and oldest ones above size limit, returns how many were removed.
+    pub fn sweep(&self) -> anyhow::Result<usize> {
+        let connection = self.connection.lock().unwrap();
+        let expired = connection
+            .execute(
+                "DELETE FROM redirects WHERE expires_at <= ?1",
+                params![unix_now()],
+            )
+            .context("delete expired")?;
+        let over_limit = connection
+            .execute(
+                "DELETE FROM redirects WHERE short_url NOT IN (
+                    SELECT short_url FROM redirects ORDER BY resolved_at DESC LIMIT ?1
+                )",
+                params![self.max_entries],
+            )
+            .context("delete over limit")?;
+        Ok(expired + over_limit)
+    }
+
+    fn try_location(&self, short_url: &Url) -> anyhow::Result<Option<Url>> {
+        let location: Option<String> = self
+            .connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT location FROM redirects
+                WHERE short_url = ?1 AND (expires_at IS NULL OR expires_at > ?2)",
+                params![short_url.as_str(), unix_now()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        location
+            .map(|location| Url::parse(&location).context("parse stored location"))
+            .transpose()
+    }
+
+    fn try_insert(&self, short_url: &Url, location: &Url) -> anyhow::Result<()> {
+        let now = unix_now();
+        let expires_at = self.ttl.map(|ttl| now + ttl.as_secs() as i64);
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO redirects (short_url, location, resolved_at, expires_at)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![short_url.as_str(), location.as_str(), now, expires_at],
+        )?;
+        Ok(())
+    }
+
+    fn try_remove(&self, short_url: Option<&Url>) -> anyhow::Result<()> {
+        let connection = self.connection.lock().unwrap();
+        match short_url {
+            Some(short_url) => connection.execute(
+                "DELETE FROM redirects WHERE short_url = ?1",
+                params![short_url.as_str()],
+            )?,
+            None => connection.execute("DELETE FROM redirects", [])?,
+        };
+        Ok(())
+    }
+}
+
+/// Sqlite errors are logged and treated like a cache miss, washing does not depend on the store.
+impl RedirectStore for SqliteRedirectStore {
+    fn location(&self, short_url: &Url) -> Option<Url> {
+        self.try_location(short_url).unwrap_or_else(|err| {
+            error!(
+                "Could not read stored redirect of {}: {err:?}",
+                privacy::url(short_url.as_str())
+            );
+            None
+        })
+    }
+
+    fn insert(&self, short_url: Url, location: Url) {
+        if let Err(err) = self.try_insert(&short_url, &location) {
+            error!(
+                "Could not store redirect of {}: {err:?}",
+                privacy::url(short_url.as_str())
+            );
+        }
+    }
+
+    fn remove(&self, short_url: &Url) {
+        if let Err(err) = self.try_remove(Some(short_url)) {
+            error!(
+                "Could not remove stored redirect of {}: {err:?}",
+                privacy::url(short_url.as_str())
+            );
+        }
+    }
+
+    fn clear(&self) {
+        if let Err(err) = self.try_remove(None) {
+            error!("Could not clear stored redirects: {err:?}");
+        }
+    }
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs() as i64)
+}
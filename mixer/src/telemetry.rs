@@ -0,0 +1,33 @@
+//! Prometheus exposition of washer metrics (see `urlwasher` `metrics` feature) at `/admin/metrics`.
+
+use std::sync::OnceLock;
+
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Installs process wide recorder, metrics recorded before are lost.
+pub fn init() {
+    let handle = PrometheusBuilder::new()
+        .install_recorder()
+        .expect("Could not install metrics recorder");
+    if HANDLE.set(handle).is_err() {
+        panic!("Metrics recorder is already installed");
+    }
+}
+
+/// Metrics in prometheus text format, not found if recorder is not installed.
+pub async fn render() -> Response {
+    match HANDLE.get() {
+        Some(handle) => (
+            [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+            handle.render(),
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
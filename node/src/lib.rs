@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use napi::{Error, Result};
+use napi_derive::napi;
+use url::Url;
+use urlwasher::{text_washer::TextWasher, RedirectWashPolicy, UrlWasherConfig};
+
+#[napi(string_enum)]
+pub enum RedirectPolicy {
+    /// Do not unroll short links.
+    Ignore,
+    /// Unroll short links from this machine.
+    Locally,
+    /// Unroll short links using mixer instance.
+    ViaMixer,
+    /// Unroll short links through local tor daemon.
+    ViaTor,
+    /// Unroll short links from this machine, using mixer instance if it fails.
+    LocallyThenMixer,
+    /// Unroll short links using mixer instance, from this machine if mixer fails.
+    MixerThenLocally,
+}
+
+impl From<RedirectPolicy> for RedirectWashPolicy {
+    fn from(policy: RedirectPolicy) -> Self {
+        match policy {
+            RedirectPolicy::Ignore => RedirectWashPolicy::Ignore,
+            RedirectPolicy::Locally => RedirectWashPolicy::Locally,
+            RedirectPolicy::ViaMixer => RedirectWashPolicy::ViaMixer,
+            RedirectPolicy::ViaTor => RedirectWashPolicy::ViaTor,
+            RedirectPolicy::LocallyThenMixer => RedirectWashPolicy::LocallyThenMixer,
+            RedirectPolicy::MixerThenLocally => RedirectWashPolicy::MixerThenLocally,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct WasherOptions {
+    /// Mixer instance used by `ViaMixer` redirect policy.
+    pub mixer_instance: Option<String>,
+    /// Api key of private mixer instance.
+    pub mixer_api_key: Option<String>,
+    /// How to unroll short links, `Locally` by default.
+    pub redirect_policy: Option<RedirectPolicy>,
+    /// SOCKS5 proxy of tor daemon used by `ViaTor` redirect policy, `socks5h://127.0.0.1:9050` by default.
+    pub tor_proxy: Option<String>,
+}
+
+#[napi(object)]
+pub struct WashedUrl {
+    pub original: String,
+    pub washed: String,
+}
+
+#[napi(object)]
+pub struct WashedText {
+    pub text: String,
+    pub washed_urls: Vec<WashedUrl>,
+}
+
+/// Washer exposed to JavaScript as `UrlWasher` class.
+#[napi(js_name = "UrlWasher")]
+pub struct JsUrlWasher {
+    text_washer: TextWasher,
+}
+
+#[napi]
+impl JsUrlWasher {
+    #[napi(constructor)]
+    pub fn new(options: Option<WasherOptions>) -> Result<Self> {
+        let mut config = UrlWasherConfig::default();
+        if let Some(options) = options {
+            config.mixer_instance = options
+                .mixer_instance
+                .map(|mixer_instance| Url::parse(&mixer_instance))
+                .transpose()
+                .map_err(|err| Error::from_reason(format!("invalid mixer instance: {err}")))?;
+            config.mixer_api_key = options.mixer_api_key;
+            if let Some(tor_proxy) = options.tor_proxy {
+                config.tor_proxy = Url::parse(&tor_proxy)
+                    .map_err(|err| Error::from_reason(format!("invalid tor proxy: {err}")))?;
+            }
+            if let Some(redirect_policy) = options.redirect_policy {
+                let redirect_policy = RedirectWashPolicy::from(redirect_policy);
+                config
+                    .redirect_policy
+                    .values_mut()
+                    .for_each(|policy| *policy = redirect_policy);
+            }
+        }
+        Ok(Self {
+            text_washer: TextWasher {
+                url_washer: Arc::new(urlwasher::UrlWasher::new(config)),
+                skip_code: false,
+            },
+        })
+    }
+
+    /// Returns clean url, or the same url if there was nothing to wash.
+    #[napi]
+    pub async fn wash(&self, url: String) -> Result<String> {
+        let url =
+            Url::parse(&url).map_err(|err| Error::from_reason(format!("invalid url: {err}")))?;
+        let washed = self
+            .text_washer
+            .url_washer
+            .wash(&url)
+            .await
+            .map_err(|err| Error::from_reason(format!("{err:#}")))?;
+        Ok(washed.unwrap_or(url).to_string())
+    }
+
+    /// Washes all urls found in text.
+    #[napi]
+    pub async fn wash_text(&self, text: String) -> String {
+        self.text_washer.wash(&text).await
+    }
+
+    /// Washes all urls found in text and reports which of them were changed.
+    #[napi]
+    pub async fn wash_text_detailed(&self, text: String) -> WashedText {
+        let washed = self.text_washer.wash_detailed(&text).await;
+        WashedText {
+            text: washed.text,
+            washed_urls: washed
+                .washed_urls
+                .into_iter()
+                .map(|washed_url| WashedUrl {
+                    original: washed_url.original,
+                    washed: washed_url.washed,
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Directory of public mixer instances, signed by its maintainer so clients can trust the list
+//! no matter where it is hosted.
+//!
+//! [`SignedDirectory::directory`] holds [`MixerDirectory`] serialized to JSON, signed as is with ed25519,
+//! so verification does not depend on how JSON is formatted. Instances opt in by exposing their
+//! [`DirectoryEntry`] at mixer `/directory-entry` endpoint, maintainer collects entries and signs the list.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct SignedDirectory {
+    /// [`MixerDirectory`] as JSON.
+    pub directory: String,
+    /// Base64 encoded ed25519 signature of [`SignedDirectory::directory`] bytes.
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug)]
+pub struct MixerDirectory {
+    /// Unix timestamp of signing, lets clients prefer newer lists.
+    pub issued_at: u64,
+    pub instances: Vec<DirectoryEntry>,
+}
+
+/// Listed instance, also response of mixer `/directory-entry` endpoint.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct DirectoryEntry {
+    pub url: String,
+    /// Who runs the instance.
+    #[serde(default)]
+    pub operator: Option<String>,
+    /// Where instance is hosted, e.g. `eu` or `de-fra`.
+    #[serde(default)]
+    pub region: Option<String>,
+    #[serde(default)]
+    pub policy: InstancePolicy,
+}
+
+/// What instance does with requests, shown to users picking an instance.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct InstancePolicy {
+    pub requires_api_key: bool,
+    /// Urls are censored in instance logs.
+    pub privacy_logging: bool,
+    /// Resolved short links are stored on disk.
+    pub persistent_cache: bool,
+}
+
+#[cfg(feature = "signing")]
+pub use signing::*;
+
+#[cfg(feature = "signing")]
+mod signing {
+    use std::fmt::Display;
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use ring::{
+        rand::SystemRandom,
+        signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519},
+    };
+
+    use super::{MixerDirectory, SignedDirectory};
+
+    #[derive(Debug)]
+    pub enum DirectoryError {
+        InvalidKey,
+        InvalidSignature,
+        InvalidDirectory(serde_json::Error),
+    }
+
+    impl Display for DirectoryError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                DirectoryError::InvalidKey => write!(f, "invalid directory key"),
+                DirectoryError::InvalidSignature => write!(f, "invalid directory signature"),
+                DirectoryError::InvalidDirectory(err) => write!(f, "invalid directory: {err}"),
+            }
+        }
+    }
+
+    impl std::error::Error for DirectoryError {}
+
+    /// New signing key as pkcs8 document, with its base64 encoded public key.
+    pub fn generate_key() -> Result<(Vec<u8>, String), DirectoryError> {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| DirectoryError::InvalidKey)?;
+        let key_pair =
+            Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).map_err(|_| DirectoryError::InvalidKey)?;
+        let public_key = STANDARD.encode(key_pair.public_key().as_ref());
+        Ok((pkcs8.as_ref().to_vec(), public_key))
+    }
+
+    impl SignedDirectory {
+        pub fn sign(directory: &MixerDirectory, pkcs8: &[u8]) -> Result<Self, DirectoryError> {
+            let key_pair =
+                Ed25519KeyPair::from_pkcs8(pkcs8).map_err(|_| DirectoryError::InvalidKey)?;
+            let directory =
+                serde_json::to_string(directory).map_err(DirectoryError::InvalidDirectory)?;
+            let signature = STANDARD.encode(key_pair.sign(directory.as_bytes()).as_ref());
+            Ok(Self {
+                directory,
+                signature,
+            })
+        }
+
+        /// Returns directory only if it was signed by owner of base64 encoded `public_key`.
+        pub fn verify(&self, public_key: &str) -> Result<MixerDirectory, DirectoryError> {
+            let public_key = STANDARD
+                .decode(public_key.trim())
+                .map_err(|_| DirectoryError::InvalidKey)?;
+            let signature = STANDARD
+                .decode(&self.signature)
+                .map_err(|_| DirectoryError::InvalidSignature)?;
+            UnparsedPublicKey::new(&ED25519, public_key)
+                .verify(self.directory.as_bytes(), &signature)
+                .map_err(|_| DirectoryError::InvalidSignature)?;
+            serde_json::from_str(&self.directory).map_err(DirectoryError::InvalidDirectory)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::generate_key;
+        use crate::directory::{DirectoryEntry, MixerDirectory, SignedDirectory};
+
+        #[test]
+        fn verifies_signed_directory() {
+            let (pkcs8, public_key) = generate_key().unwrap();
+            let directory = MixerDirectory {
+                issued_at: 1700000000,
+                instances: vec![DirectoryEntry {
+                    url: "https://mixer.example/".to_string(),
+                    operator: Some("example".to_string()),
+                    region: None,
+                    policy: Default::default(),
+                }],
+            };
+            let mut signed = SignedDirectory::sign(&directory, &pkcs8).unwrap();
+            assert_eq!(signed.verify(&public_key).unwrap(), directory);
+
+            signed.directory = signed.directory.replace("mixer.example", "evil.example");
+            assert!(signed.verify(&public_key).is_err());
+            let (_, other_public_key) = generate_key().unwrap();
+            assert!(SignedDirectory::sign(&directory, &pkcs8)
+                .unwrap()
+                .verify(&other_public_key)
+                .is_err());
+        }
+    }
+}
@@ -0,0 +1,12 @@
+//! Message formats shared by UrlDebloater components, so they stay wire-compatible.
+//!
+//! Every breaking change of types here must bump [`PROTOCOL_VERSION`].
+
+pub mod directory;
+pub mod message;
+pub mod mixer;
+pub mod native_messaging;
+pub mod report;
+
+/// Version of [`message`] protocol spoken by this build.
+pub const PROTOCOL_VERSION: u32 = 1;
@@ -0,0 +1,117 @@
+//! Requests sent by browser extension to desktop app, over native messaging or local http api.
+
+use serde::{Deserialize, Serialize};
+
+use crate::PROTOCOL_VERSION;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Request {
+    /// [`PROTOCOL_VERSION`] of sender.
+    pub version: u32,
+    /// Echoed in [`Response`], so responses can be matched with pipelined requests.
+    pub id: u64,
+    #[serde(flatten)]
+    pub body: RequestBody,
+}
+
+impl Request {
+    pub fn new(id: u64, body: RequestBody) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            id,
+            body,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RequestBody {
+    /// Wash single url.
+    Wash { url: String },
+    /// Wash all urls found in text.
+    WashText { text: String },
+    /// Ask for version of app, valid in every protocol version.
+    Version,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Response {
+    /// [`PROTOCOL_VERSION`] of sender.
+    pub version: u32,
+    /// Id of [`Request`] this responds to.
+    pub id: u64,
+    #[serde(flatten)]
+    pub body: ResponseBody,
+}
+
+impl Response {
+    pub fn new(id: u64, body: ResponseBody) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            id,
+            body,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseBody {
+    /// Clean url, the same as requested if there was nothing to wash.
+    Washed {
+        url: String,
+    },
+    WashedText {
+        text: String,
+        washed_urls: Vec<WashedUrl>,
+    },
+    Version {
+        version: String,
+    },
+    /// Request failed, e.g. invalid url or unsupported protocol version.
+    Error {
+        message: String,
+    },
+}
+
+/// Url found in text that was replaced by its washed version.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct WashedUrl {
+    pub original: String,
+    pub washed: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_wire_format() {
+        let request = Request::new(
+            7,
+            RequestBody::Wash {
+                url: "https://youtu.be/lSwnPoo9ZK0?si=xd".to_string(),
+            },
+        );
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"id":7,"type":"wash","url":"https://youtu.be/lSwnPoo9ZK0?si=xd"}"#
+        );
+        assert_eq!(serde_json::from_str::<Request>(&json).unwrap(), request);
+
+        let response: Response =
+            serde_json::from_str(r#"{"version":1,"id":7,"type":"error","message":"invalid url"}"#)
+                .unwrap();
+        assert_eq!(
+            response,
+            Response::new(
+                7,
+                ResponseBody::Error {
+                    message: "invalid url".to_string()
+                }
+            )
+        );
+    }
+}
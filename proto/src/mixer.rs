@@ -0,0 +1,42 @@
+//! Types exposed by mixer http api.
+
+use serde::{Deserialize, Serialize};
+
+/// Response of mixer `/version` endpoint.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct MixerVersion {
+    pub version: String,
+    /// Commit mixer was built from, unknown if built outside of git checkout.
+    #[serde(default)]
+    pub git_hash: Option<String>,
+    /// Version of built-in rule set, changes whenever rules do.
+    #[serde(default)]
+    pub rule_set_version: u32,
+    /// [`crate::PROTOCOL_VERSION`] of mixer.
+    #[serde(default)]
+    pub protocol_version: u32,
+}
+
+/// Response of mixer `/motd` endpoint, announcements of instance operator.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug)]
+pub struct MixerMotd {
+    /// Message of the day, e.g. policy changes.
+    pub motd: Option<String>,
+    /// Message explaining downtime, present only while instance is in maintenance mode.
+    pub maintenance: Option<String>,
+}
+
+/// Query of mixer `/wash` endpoint, responded with washed url as plain text.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct WashQuery {
+    pub url: String,
+}
+
+/// Response of mixer `/usage` endpoint, usage of api key request was sent with.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ApiKeyUsage {
+    pub name: String,
+    pub requests_today: u64,
+    pub daily_quota: Option<u64>,
+    pub requests_total: u64,
+}
@@ -0,0 +1,53 @@
+//! Framing of browser native messaging: JSON prefixed with its length as native endian u32.
+
+use std::io::{self, ErrorKind, Read, Write};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Browsers reject messages from native host bigger than 1 MB.
+pub const MAX_HOST_MESSAGE_LEN: usize = 1024 * 1024;
+
+pub fn write_message<W: Write>(writer: &mut W, message: &impl Serialize) -> io::Result<()> {
+    let json = serde_json::to_vec(message)?;
+    if json.len() > MAX_HOST_MESSAGE_LEN {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "native message is too long",
+        ));
+    }
+    writer.write_all(&(json.len() as u32).to_ne_bytes())?;
+    writer.write_all(&json)?;
+    writer.flush()
+}
+
+/// Returns `None` when browser closed the pipe.
+pub fn read_message<R: Read, T: DeserializeOwned>(reader: &mut R) -> io::Result<Option<T>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let mut json = vec![0; u32::from_ne_bytes(len) as usize];
+    reader.read_exact(&mut json)?;
+    Ok(Some(serde_json::from_slice(&json)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Request, RequestBody};
+
+    #[test]
+    fn reads_written_messages() {
+        let mut pipe = Vec::new();
+        let request = Request::new(1, RequestBody::Version);
+        write_message(&mut pipe, &request).unwrap();
+        let mut reader = pipe.as_slice();
+        assert_eq!(
+            read_message::<_, Request>(&mut reader).unwrap(),
+            Some(request)
+        );
+        assert_eq!(read_message::<_, Request>(&mut reader).unwrap(), None);
+    }
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// Description of what was changed in washed url, printed by `urlwash --format json`
+/// and returned by `UrlWasher::wash_with_report`.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug)]
+pub struct WashReport {
+    /// File containing original url.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub original: String,
+    pub cleaned: String,
+    /// Name of rule that matched original url.
+    pub rule: Option<String>,
+    /// Query params present in original url and missing in clean one.
+    pub removed_params: Vec<String>,
+    /// Redirections followed to unroll short link.
+    pub resolution_hops: usize,
+    /// Washing programs ran, including ones of rules matching extracted or unrolled destination.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub programs: Vec<String>,
+    /// Clean url was served from cache, so ran programs are unknown.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    /// Params of url without rule that look like trackers, reported by `urlwash --detect-trackers`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub possible_trackers: Vec<String>,
+}
@@ -0,0 +1,34 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use url::Url;
+use urlwasher::remove_query_params;
+
+fn urls(count: usize) -> Vec<Url> {
+    (0..count)
+        .map(|i| {
+            Url::parse(&format!(
+                "https://music.youtube.com/watch?v=IeojlW7S{i}&list=RDAMVM{i}&t=1%3A{i}&si=TRACKING{i}"
+            ))
+            .unwrap()
+        })
+        .collect()
+}
+
+fn bench_remove_query_params(c: &mut Criterion) {
+    let params = ["si".to_string()];
+    let mut group = c.benchmark_group("remove_query_params");
+    for count in [1, 100, 500] {
+        let urls = urls(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &urls, |b, urls| {
+            b.iter(|| {
+                for url in urls {
+                    black_box(remove_query_params(black_box(url), &params));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_remove_query_params);
+criterion_main!(benches);
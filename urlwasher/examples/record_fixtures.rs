@@ -0,0 +1,36 @@
+//! Resolves short links live and writes their locations to `fixtures/redirects.json`, replayed by tests.
+//!
+//! Records every short link already in fixtures again, plus ones given as arguments:
+//! ```sh
+//! cargo run -p urlwasher --features test-fixtures --example record_fixtures -- https://vm.tiktok.com/ZGJoJs8jb/
+//! ```
+
+use std::{collections::BTreeMap, env, fs, path::PathBuf, sync::Arc};
+
+use anyhow::Context;
+use url::Url;
+use urlwasher::{fixtures::RedirectFixtures, UrlWasher, UrlWasherConfig};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/redirects.json");
+    let bytes = fs::read(&path).with_context(|| format!("read {}", path.display()))?;
+    let recorded: BTreeMap<Url, Url> =
+        serde_json::from_slice(&bytes).with_context(|| format!("parse {}", path.display()))?;
+    let mut short_links = recorded.into_keys().collect::<Vec<_>>();
+    for arg in env::args().skip(1) {
+        short_links.push(Url::parse(&arg).with_context(|| format!("parse {arg}"))?);
+    }
+
+    let fixtures = Arc::new(RedirectFixtures::record());
+    let washer =
+        UrlWasher::new(UrlWasherConfig::default()).with_redirect_fixtures(fixtures.clone());
+    for short_link in &short_links {
+        let washed = washer
+            .wash(short_link)
+            .await
+            .with_context(|| format!("wash {short_link}"))?;
+        println!("{short_link} -> {}", washed.as_ref().unwrap_or(short_link));
+    }
+    fixtures.save(&path)
+}
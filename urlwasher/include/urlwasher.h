@@ -0,0 +1,48 @@
+/* C interface of urlwasher, built with `ffi` feature, see urlwasher/README.md. */
+#ifndef URLWASHER_H
+#define URLWASHER_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Washer with its own config and cache, see urlwasher_new. */
+typedef struct UrlWasher UrlWasher;
+
+typedef struct UrlWasherReport {
+    char *cleaned;
+    /* NULL if no rule matched original url. */
+    char *rule;
+    char **removed_params;
+    size_t removed_params_len;
+    size_t resolution_hops;
+    bool cached;
+} UrlWasherReport;
+
+/* Washer configured by UrlWasherConfig serialized as JSON, default config if config_json is NULL.
+ * NULL if config is invalid. Free it with urlwasher_free. */
+UrlWasher *urlwasher_new(const char *config_json);
+
+void urlwasher_free(UrlWasher *washer);
+
+/* Clean url, or the same url if there was nothing to wash.
+ * NULL if url is invalid or washing failed. Blocks until short link is unrolled.
+ * Free result with urlwasher_string_free. */
+char *urlwasher_wash(const UrlWasher *washer, const char *url);
+
+/* Like urlwasher_wash, also describing what was changed.
+ * Free result with urlwasher_report_free. */
+UrlWasherReport *urlwasher_wash_with_report(const UrlWasher *washer, const char *url);
+
+void urlwasher_string_free(char *string);
+
+void urlwasher_report_free(UrlWasherReport *report);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
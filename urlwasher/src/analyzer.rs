@@ -0,0 +1,160 @@
+//! Heuristics flagging query params that look like trackers on domains without rules,
+//! so users can notice them and create a rule.
+
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Param names (or prefixes ending with `_`) used by common ad and analytics platforms.
+const KNOWN_TRACKER_KEYS: &[&str] = &[
+    "utm_",
+    "fbclid",
+    "gclid",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "yclid",
+    "ttclid",
+    "twclid",
+    "igshid",
+    "mc_eid",
+    "mc_cid",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "oly_enc_id",
+    "oly_anon_id",
+    "vero_id",
+    "wickedid",
+    "__s",
+    "_openstat",
+    "si",
+    "spm",
+];
+
+/// Values shorter than this are too short to carry an identifier worth flagging.
+const MIN_SUSPICIOUS_VALUE_LEN: usize = 16;
+/// Shannon entropy in bits per character, random ids are above it, words and slugs usually are not.
+const HIGH_ENTROPY_THRESHOLD: f64 = 3.5;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackerHint {
+    /// Name of param is used by known tracking platform.
+    KnownKey,
+    /// Value looks like random identifier.
+    HighEntropy,
+    /// Value looks like uuid or other one-time token.
+    OneTimeToken,
+}
+
+impl Display for TrackerHint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TrackerHint::KnownKey => "known tracker",
+            TrackerHint::HighEntropy => "random looking value",
+            TrackerHint::OneTimeToken => "one-time token",
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct PossibleTracker {
+    pub param: String,
+    pub hint: TrackerHint,
+}
+
+/// Query params of `url` that look like trackers, in order of appearance.
+pub fn possible_trackers(url: &Url) -> Vec<PossibleTracker> {
+    let mut trackers: Vec<PossibleTracker> = Vec::new();
+    for (key, value) in url.query_pairs() {
+        if trackers.iter().any(|tracker| tracker.param == key) {
+            continue;
+        }
+        if let Some(hint) = tracker_hint(&key, &value) {
+            trackers.push(PossibleTracker {
+                param: key.into_owned(),
+                hint,
+            });
+        }
+    }
+    trackers
+}
+
+fn tracker_hint(key: &str, value: &str) -> Option<TrackerHint> {
+    let key = key.to_ascii_lowercase();
+    let known_key = KNOWN_TRACKER_KEYS
+        .iter()
+        .any(|known| match known.strip_suffix('_') {
+            Some(_) => key.starts_with(known),
+            None => key == *known,
+        });
+    if known_key {
+        Some(TrackerHint::KnownKey)
+    } else if is_one_time_token(value) {
+        Some(TrackerHint::OneTimeToken)
+    } else if value.len() >= MIN_SUSPICIOUS_VALUE_LEN && entropy(value) >= HIGH_ENTROPY_THRESHOLD {
+        Some(TrackerHint::HighEntropy)
+    } else {
+        None
+    }
+}
+
+/// Uuids and long hex strings (hashes, session ids).
+fn is_one_time_token(value: &str) -> bool {
+    let hex_digits = value.chars().filter(|c| c.is_ascii_hexdigit()).count();
+    let is_uuid = value.len() == 36
+        && hex_digits == 32
+        && value
+            .match_indices('-')
+            .map(|(index, _)| index)
+            .eq([8, 13, 18, 23]);
+    let is_hex =
+        value.len() >= 24 && hex_digits == value.len() && value.chars().any(|c| c.is_ascii_digit());
+    is_uuid || is_hex
+}
+
+fn entropy(value: &str) -> f64 {
+    let mut counts = HashMap::new();
+    for c in value.chars() {
+        *counts.entry(c).or_insert(0usize) += 1;
+    }
+    let len = value.chars().count() as f64;
+    counts
+        .values()
+        .map(|count| {
+            let probability = *count as f64 / len;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::{possible_trackers, TrackerHint};
+
+    #[test]
+    fn flags_suspicious_params() {
+        let url = Url::parse(
+            "https://shop.example/item?id=42&utm_source=newsletter&q=red+shoes\
+            &session=550e8400-e29b-41d4-a716-446655440000&r=aZ3kQ9xP2mL7vB4nT8wY",
+        )
+        .unwrap();
+        let flagged = possible_trackers(&url)
+            .into_iter()
+            .map(|tracker| (tracker.param, tracker.hint))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            flagged,
+            vec![
+                ("utm_source".to_string(), TrackerHint::KnownKey),
+                ("session".to_string(), TrackerHint::OneTimeToken),
+                ("r".to_string(), TrackerHint::HighEntropy),
+            ]
+        );
+    }
+}
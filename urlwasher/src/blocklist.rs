@@ -0,0 +1,134 @@
+//! Domains of malicious destinations, checked when short links are unrolled.
+//!
+//! Blocklist is filled from feeds by [`crate::UrlWasher::refresh_blocklist`],
+//! refreshing it periodically is up to the app, as washer does not depend on any async runtime.
+
+use std::{collections::HashSet, fmt::Display, sync::RwLock};
+
+#[cfg(feature = "network")]
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct BlocklistConfig {
+    /// Feeds with one domain per line, hosts file format (`0.0.0.0 example.com`) is accepted too.
+    pub feeds: Vec<Url>,
+    pub refresh_interval_secs: u64,
+    pub action: BlockedAction,
+}
+
+impl Default for BlocklistConfig {
+    fn default() -> Self {
+        Self {
+            feeds: Vec::new(),
+            refresh_interval_secs: 6 * 60 * 60,
+            action: BlockedAction::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockedAction {
+    /// Emit destination url, but mark it as blocked.
+    #[default]
+    Flag,
+    /// Never emit destination url.
+    Refuse,
+}
+
+/// Error of [`crate::UrlWasher::wash`] when short link leads to blocklisted domain.
+/// Blocked results are not cached, so they are rechecked after blocklist refresh.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BlockedDestination {
+    /// Washed destination, should not be shown to user if action is [`BlockedAction::Refuse`].
+    pub url: Url,
+    /// Listed domain that matched destination.
+    pub domain: String,
+    pub action: BlockedAction,
+}
+
+impl Display for BlockedDestination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "destination domain {} is blocklisted", self.domain)
+    }
+}
+
+impl std::error::Error for BlockedDestination {}
+
+#[derive(Default)]
+pub struct Blocklist {
+    domains: RwLock<HashSet<String>>,
+}
+
+impl Blocklist {
+    pub fn new(domains: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            domains: RwLock::new(domains.into_iter().collect()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.domains.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn replace(&self, domains: HashSet<String>) {
+        *self.domains.write().unwrap() = domains;
+    }
+
+    /// Listed domain equal to `domain` or one of its parents.
+    pub fn matching(&self, domain: &str) -> Option<String> {
+        let domains = self.domains.read().unwrap();
+        let mut domain = domain;
+        loop {
+            if domains.contains(domain) {
+                return Some(domain.to_string());
+            }
+            domain = domain.split_once('.')?.1;
+        }
+    }
+
+    /// Replaces listed domains with ones from `feeds`, returns how many are listed.
+    /// Current list is kept if any feed fails, so a flaky feed never unblocks domains.
+    #[cfg(feature = "network")]
+    pub async fn refresh(
+        &self,
+        http_client: &reqwest::Client,
+        feeds: &[Url],
+    ) -> anyhow::Result<usize> {
+        let mut domains = HashSet::new();
+        for feed in feeds {
+            let resp = http_client
+                .get(feed.clone())
+                .send()
+                .await
+                .with_context(|| format!("fetch feed {feed}"))?;
+            if !resp.status().is_success() {
+                return Err(anyhow!("Invalid status of feed {feed}: {}", resp.status()));
+            }
+            let text = resp
+                .text()
+                .await
+                .with_context(|| format!("read feed {feed}"))?;
+            domains.extend(parse_feed(&text));
+        }
+        let len = domains.len();
+        self.replace(domains);
+        Ok(len)
+    }
+}
+
+/// Domains listed in feed, lines starting with `#` are comments.
+pub fn parse_feed(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.lines()
+        .map(|line| line.split_once('#').map_or(line, |(line, _)| line).trim())
+        .filter_map(|line| line.split_whitespace().last())
+        .filter(|domain| !matches!(*domain, "localhost" | "0.0.0.0" | "127.0.0.1"))
+        .map(|domain| domain.trim_end_matches('.').to_ascii_lowercase())
+}
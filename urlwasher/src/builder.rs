@@ -0,0 +1,263 @@
+//! Customization of washer's rules, http client and cache, beyond [`UrlWasherConfig`].
+
+use std::{num::NonZeroUsize, time::Duration};
+
+use crate::{DirtyUrlRule, UrlWasher, UrlWasherConfig};
+
+/// Created by [`UrlWasher::builder`], finished by [`UrlWasherBuilder::build`].
+///
+/// Client options (user agent, timeouts, proxy) apply to client built by washer,
+/// so they cannot be combined with [`UrlWasherBuilder::http_client`].
+pub struct UrlWasherBuilder {
+    config: UrlWasherConfig,
+    rules: Option<Vec<DirtyUrlRule>>,
+    #[cfg(feature = "network")]
+    http_client: Option<reqwest::Client>,
+    #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+    client_options: ClientOptions,
+}
+
+#[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+#[derive(Default)]
+struct ClientOptions {
+    user_agent: Option<String>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    proxy: Option<reqwest::Proxy>,
+    /// See [`UrlWasherConfig::dns_over_https`].
+    dns_over_https: Option<crate::doh::DohProvider>,
+}
+
+#[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+impl ClientOptions {
+    fn is_set(&self) -> bool {
+        self.user_agent.is_some()
+            || self.connect_timeout.is_some()
+            || self.timeout.is_some()
+            || self.proxy.is_some()
+            || self.dns_over_https.is_some()
+    }
+
+    fn build(self) -> anyhow::Result<reqwest::Client> {
+        let mut builder = crate::http_client_builder();
+        if let Some(user_agent) = self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = self.proxy {
+            builder = builder.proxy(proxy);
+        }
+        if let Some(provider) = self.dns_over_https {
+            #[cfg(feature = "doh")]
+            {
+                let resolver = crate::doh::CachingResolver::doh(provider);
+                builder = builder.dns_resolver(std::sync::Arc::new(resolver));
+            }
+            #[cfg(not(feature = "doh"))]
+            anyhow::bail!("dns over https ({provider}) requires doh feature of urlwasher");
+        }
+        Ok(builder.build()?)
+    }
+}
+
+impl UrlWasherBuilder {
+    pub(crate) fn new(config: UrlWasherConfig) -> Self {
+        Self {
+            rules: None,
+            #[cfg(feature = "network")]
+            http_client: None,
+            #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+            client_options: ClientOptions {
+                dns_over_https: config.dns_over_https,
+                ..Default::default()
+            },
+            config,
+        }
+    }
+
+    /// Sends requests with `http_client`, which must not follow redirects,
+    /// see [`crate::http_client_builder`].
+    #[cfg(feature = "network")]
+    pub fn http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    /// Replaces [`crate::DEFAULT_USER_AGENT`], [`crate::HttpProfile`] of domain still takes precedence.
+    #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.client_options.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Time limit of establishing connection, unlimited by default.
+    #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.client_options.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Time limit of whole request, from connecting until response body is read, unlimited by default.
+    #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.client_options.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends all requests through `proxy`, e.g. `reqwest::Proxy::all("socks5h://127.0.0.1:9050")`.
+    #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.client_options.proxy = Some(proxy);
+        self
+    }
+
+    /// Replaces built-in [`crate::rule_set`], e.g. with defaults and extra rules of embedder.
+    /// Redirect policy of extra rules is set by [`UrlWasherConfig::set_redirect_policy`].
+    pub fn rules(mut self, rules: Vec<DirtyUrlRule>) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+
+    /// See [`UrlWasherConfig::cache_capacity`].
+    pub fn cache_capacity(mut self, capacity: NonZeroUsize) -> Self {
+        self.config.cache_capacity = capacity;
+        self
+    }
+
+    /// See [`UrlWasherConfig::cache_ttl_secs`], rounded down to whole seconds.
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.config.cache_ttl_secs = Some(ttl.as_secs());
+        self
+    }
+
+    /// See [`UrlWasherConfig::unmatched_cache_capacity`].
+    pub fn unmatched_cache_capacity(mut self, capacity: NonZeroUsize) -> Self {
+        self.config.unmatched_cache_capacity = Some(capacity);
+        self
+    }
+
+    /// Fails if http client cannot be built, or client options (including [`UrlWasherConfig::dns_over_https`])
+    /// are set together with custom client.
+    pub fn build(self) -> anyhow::Result<UrlWasher> {
+        #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+        let http_client = match self.http_client {
+            Some(_) if self.client_options.is_set() => {
+                anyhow::bail!("client options cannot be combined with custom http client")
+            }
+            Some(http_client) => http_client,
+            None => self.client_options.build()?,
+        };
+        #[cfg(all(feature = "network", target_arch = "wasm32"))]
+        let http_client = match self.http_client {
+            Some(http_client) => http_client,
+            None => crate::http_client_builder().build()?,
+        };
+        #[cfg(not(feature = "network"))]
+        let http_client = ();
+        let url_washer = UrlWasher::with_client(self.config, http_client);
+        Ok(match self.rules {
+            Some(rules) => url_washer.with_rules(rules),
+            None => url_washer,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use url::Url;
+
+    use super::*;
+    use crate::{tests::MemoryRedirectStore, RedirectStore, RedirectWashPolicy, WashingProgram};
+
+    #[tokio::test]
+    async fn builds_customized_washer() {
+        let url_washer = UrlWasher::builder(UrlWasherConfig::default())
+            .cache_capacity(NonZeroUsize::new(1).unwrap())
+            .cache_ttl(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        assert_eq!(url_washer.config.cache_ttl_secs, Some(60));
+        for url in ["https://youtu.be/a?si=x", "https://youtu.be/b?si=x"] {
+            url_washer.wash(&Url::parse(url).unwrap()).await.unwrap();
+        }
+        assert_eq!(url_washer.cache_entries().len(), 1);
+
+        #[cfg(feature = "network")]
+        {
+            let url_washer = UrlWasher::builder(UrlWasherConfig::default())
+                .user_agent("urlwasher-test")
+                .connect_timeout(Duration::from_secs(5))
+                .timeout(Duration::from_secs(10))
+                .build();
+            assert!(url_washer.is_ok());
+            let url_washer = UrlWasher::builder(UrlWasherConfig::default())
+                .http_client(crate::http_client_builder().build().unwrap())
+                .timeout(Duration::from_secs(10))
+                .build();
+            assert!(url_washer.is_err());
+
+            let config = UrlWasherConfig {
+                dns_over_https: Some(crate::doh::DohProvider::Quad9),
+                ..Default::default()
+            };
+            let url_washer = UrlWasher::builder(config.clone()).build();
+            assert_eq!(url_washer.is_ok(), cfg!(feature = "doh"));
+            let url_washer = UrlWasher::builder(config)
+                .http_client(crate::http_client_builder().build().unwrap())
+                .build();
+            assert!(url_washer.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_own_rule_set_per_instance() {
+        let short = Url::parse("https://sho.rt/abc").unwrap();
+        let mut rules = crate::rule_set().clone();
+        rules.extend([
+            DirtyUrlRule {
+                name: "example.com".to_string(),
+                domains: vec!["example.com".to_string()],
+                washing_programs: vec![WashingProgram::RemoveAllParams],
+                ..Default::default()
+            },
+            DirtyUrlRule {
+                name: "sho.rt".to_string(),
+                domains: vec!["sho.rt".to_string()],
+                washing_programs: vec![WashingProgram::ResolveRedirection],
+                ..Default::default()
+            },
+        ]);
+        let mut config = UrlWasherConfig::default();
+        config.set_redirect_policy(&rules, RedirectWashPolicy::Locally);
+        let store = Arc::new(MemoryRedirectStore::default());
+        store.insert(
+            short.clone(),
+            Url::parse("https://example.com/a?ref=x").unwrap(),
+        );
+        let custom = UrlWasher::builder(config)
+            .rules(rules)
+            .build()
+            .unwrap()
+            .with_redirect_store(store);
+        let default = UrlWasher::default();
+
+        let dirty = Url::parse("https://example.com/a?ref=x").unwrap();
+        let washed = custom.wash(&dirty).await.unwrap();
+        assert_eq!(washed.unwrap().as_str(), "https://example.com/a");
+        assert_eq!(default.wash(&dirty).await.unwrap(), None);
+        let washed = custom.wash(&short).await.unwrap();
+        assert_eq!(washed.unwrap().as_str(), "https://example.com/a");
+        let washed = custom
+            .wash(&Url::parse("https://youtu.be/a?si=x").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(washed.unwrap().as_str(), "https://youtu.be/a");
+    }
+}
@@ -0,0 +1,194 @@
+//! Import of [ClearURLs](https://docs.clearurls.xyz/) rules database (`data.min.json`, also published as `data.minify.json`).
+//!
+//! Complete providers (blocking whole domains) are skipped.
+//! Params listed in `referralMarketing` are removed by [`WashingProgram::RemoveReferralMarketing`],
+//! so they can be kept with [`crate::UrlWasherConfig::keep_referral_marketing`], like in the browser extension.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::{rule_layers::RuleLayer, DirtyUrlRule, Pattern, WashingProgram};
+
+pub const LAYER_NAME: &str = "clearurls";
+
+/// Provider matching every url, its rules apply only if no other provider matched.
+const GLOBAL_PROVIDER: &str = "globalRules";
+
+#[derive(Deserialize)]
+struct Database {
+    providers: BTreeMap<String, Provider>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Provider {
+    url_pattern: String,
+    #[serde(default)]
+    complete_provider: bool,
+    #[serde(default)]
+    rules: Vec<String>,
+    #[serde(default)]
+    referral_marketing: Vec<String>,
+    #[serde(default)]
+    raw_rules: Vec<String>,
+    #[serde(default)]
+    exceptions: Vec<String>,
+    #[serde(default)]
+    redirections: Vec<String>,
+}
+
+/// Converts ClearURLs database to rule layer, to be composed after built-in rules.
+/// Providers with patterns unsupported by regex crate (e.g. lookarounds) are skipped.
+pub fn import(json: &[u8]) -> anyhow::Result<RuleLayer> {
+    let database: Database = serde_json::from_slice(json).context("deserialize database")?;
+    let (global, providers): (Vec<_>, Vec<_>) = database
+        .providers
+        .into_iter()
+        .filter(|(_, provider)| !provider.complete_provider)
+        .partition(|(name, _)| name == GLOBAL_PROVIDER);
+    let rules = providers
+        .into_iter()
+        .chain(global)
+        .filter_map(|(name, provider)| match provider_rule(&name, &provider) {
+            Ok(rule) => Some(rule),
+            Err(err) => {
+                warn!("Skipping ClearURLs provider {name}: {err:#}");
+                None
+            }
+        })
+        .collect();
+    Ok(RuleLayer::new(LAYER_NAME, rules))
+}
+
+fn provider_rule(name: &str, provider: &Provider) -> anyhow::Result<DirtyUrlRule> {
+    let mut washing_programs = Vec::new();
+    if !provider.raw_rules.is_empty() {
+        washing_programs.push(WashingProgram::RemoveRawMatches(url_patterns(
+            &provider.raw_rules,
+        )?));
+    }
+    if !provider.rules.is_empty() {
+        washing_programs.push(WashingProgram::RemoveMatchingParams(param_patterns(
+            &provider.rules,
+        )?));
+    }
+    if !provider.referral_marketing.is_empty() {
+        washing_programs.push(WashingProgram::RemoveReferralMarketing(param_patterns(
+            &provider.referral_marketing,
+        )?));
+    }
+    // last, so params of wrapper are removed before it is replaced with destination
+    if !provider.redirections.is_empty() {
+        washing_programs.push(WashingProgram::ExtractRedirection(url_patterns(
+            &provider.redirections,
+        )?));
+    }
+    Ok(DirtyUrlRule {
+        name: name.to_string(),
+        url_pattern: Some(
+            Pattern::new(&format!("(?i){}", provider.url_pattern)).context("url pattern")?,
+        ),
+        url_exceptions: url_patterns(&provider.exceptions).context("exceptions")?,
+        washing_programs,
+        ..Default::default()
+    })
+}
+
+/// ClearURLs matches url patterns case insensitive.
+fn url_patterns(patterns: &[String]) -> anyhow::Result<Vec<Pattern>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Pattern::new(&format!("(?i){pattern}")).with_context(|| format!("pattern {pattern}"))
+        })
+        .collect()
+}
+
+/// ClearURLs rules match whole param name, case insensitive.
+fn param_patterns(rules: &[String]) -> anyhow::Result<Vec<Pattern>> {
+    rules
+        .iter()
+        .map(|rule| {
+            Pattern::new(&format!("(?i)^(?:{rule})$")).with_context(|| format!("param {rule}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::{rule_layers, UrlWasher, UrlWasherConfig};
+
+    const DATABASE: &str = r#"{
+        "providers": {
+            "amazon": {
+                "urlPattern": "^https?:\\/\\/(?:[a-z0-9-]+\\.)*?amazon(?:\\.[a-z]{2,}){1,}",
+                "completeProvider": false,
+                "rules": ["pd_rd_[a-z]*", "ref_?"],
+                "referralMarketing": ["tag"]
+            },
+            "google": {
+                "urlPattern": "^https?:\\/\\/(?:[a-z0-9-]+\\.)*?google(?:\\.[a-z]{2,}){1,}",
+                "rules": ["ved", "usg"],
+                "rawRules": ["\\/ref=[^/?]*"],
+                "exceptions": ["^https?:\\/\\/mail\\.google\\.com\\/"],
+                "redirections": ["^https?:\\/\\/(?:[a-z0-9-]+\\.)*?google(?:\\.[a-z]{2,}){1,}\\/url\\?.*?(?:url|q)=(https?[^&]+)"]
+            },
+            "doubleclick": {
+                "urlPattern": "^https?:\\/\\/(?:[a-z0-9-]+\\.)*?doubleclick\\.net",
+                "completeProvider": true
+            }
+        }
+    }"#;
+
+    async fn wash(keep_referral_marketing: bool) -> Url {
+        let layer = super::import(DATABASE.as_bytes()).unwrap();
+        let rules = rule_layers::compose(&[layer]).rules;
+        let washer = UrlWasher::new(UrlWasherConfig {
+            keep_referral_marketing,
+            ..Default::default()
+        })
+        .with_rules(rules);
+        let dirty = Url::parse("https://www.amazon.com/dp/B0?pd_rd_w=1&tag=abc-20&th=1").unwrap();
+        washer.wash(&dirty).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn applies_raw_rules_exceptions_and_redirections() {
+        let layer = super::import(DATABASE.as_bytes()).unwrap();
+        let washer = UrlWasher::new(UrlWasherConfig::default())
+            .with_rules(rule_layers::compose(&[layer]).rules);
+        let wash = |url: &str| {
+            let url = Url::parse(url).unwrap();
+            let washer = &washer;
+            async move { washer.wash(&url).await.unwrap().map(String::from) }
+        };
+
+        assert_eq!(
+            wash("https://www.google.com/url?sa=t&q=https%3A%2F%2Fexample.com%2Fa%3Fb%3D1&ved=2")
+                .await,
+            Some("https://example.com/a?b=1".to_string())
+        );
+        assert_eq!(
+            wash("https://www.google.com/shop/ref=abc?usg=1&id=2").await,
+            Some("https://www.google.com/shop?id=2".to_string())
+        );
+        assert_eq!(wash("https://mail.google.com/mail?ved=1").await, None);
+    }
+
+    #[tokio::test]
+    async fn referral_marketing_is_removed_unless_kept() {
+        assert_eq!(
+            wash(false).await.as_str(),
+            "https://www.amazon.com/dp/B0?th=1"
+        );
+        assert_eq!(
+            wash(true).await.as_str(),
+            "https://www.amazon.com/dp/B0?tag=abc-20&th=1"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Data-driven wash tests: JSON files with dirty urls, their expected clean versions
+//! and redirect locations of short links, so they run without network.
+//!
+//! ```json
+//! [
+//!   {
+//!     "input": "https://vm.tiktok.com/ZGJoJs8jb/",
+//!     "expected": "https://www.tiktok.com/@i0ki.clips/video/7297742182851611936",
+//!     "redirects": { "https://vm.tiktok.com/ZGJoJs8jb/": "https://www.tiktok.com/@i0ki.clips/video/7297742182851611936?_r=1" }
+//!   }
+//! ]
+//! ```
+//!
+//! Built-in corpus lives in `urlwasher/corpus`, every rule of [`crate::rule_set`] must be covered by it.
+
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+
+use anyhow::Context;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{fixtures::RedirectFixtures, UrlWasher, UrlWasherConfig};
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct CorpusCase {
+    pub input: Url,
+    /// Same as input if url is not expected to be washed.
+    pub expected: Url,
+    /// Short link -> location header it responds with.
+    #[serde(default)]
+    pub redirects: HashMap<Url, Url>,
+}
+
+#[derive(Debug)]
+pub struct CorpusFailure {
+    pub case: CorpusCase,
+    /// Washed url or wash error.
+    pub actual: Result<Url, String>,
+}
+
+/// Loads cases from corpus file, or from all `.json` files in corpus directory.
+pub fn load(path: &Path) -> anyhow::Result<Vec<CorpusCase>> {
+    if !path.is_dir() {
+        let bytes = fs::read(path).with_context(|| format!("read {}", path.display()))?;
+        return serde_json::from_slice(&bytes).with_context(|| format!("parse {}", path.display()));
+    }
+    let mut paths = fs::read_dir(path)
+        .with_context(|| format!("read dir {}", path.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+    let mut cases = Vec::new();
+    for path in paths {
+        cases.extend(load(&path)?);
+    }
+    Ok(cases)
+}
+
+/// Washes every case with default config and its mocked redirects, returns cases that did not match.
+pub async fn run(cases: &[CorpusCase]) -> Vec<CorpusFailure> {
+    let mut failures = Vec::new();
+    for case in cases {
+        let washer = UrlWasher::new(UrlWasherConfig::default())
+            .with_redirect_fixtures(Arc::new(RedirectFixtures::replay(case.redirects.clone())));
+        let actual = match washer.wash(&case.input).await {
+            Ok(washed) => Ok(washed.unwrap_or_else(|| case.input.clone())),
+            Err(err) => Err(format!("{err:#}")),
+        };
+        if actual.as_ref() != Ok(&case.expected) {
+            failures.push(CorpusFailure {
+                case: case.clone(),
+                actual,
+            });
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::rule_set;
+
+    fn builtin_corpus() -> Vec<CorpusCase> {
+        load(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("corpus")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn builtin_corpus_passes() {
+        let failures = run(&builtin_corpus()).await;
+        assert!(failures.is_empty(), "Corpus failures: {failures:#?}");
+    }
+
+    #[test]
+    fn every_rule_is_covered() {
+        let corpus = builtin_corpus();
+        for rule in rule_set() {
+            assert!(
+                corpus.iter().any(|case| rule.matches(&case.input)),
+                "Rule {} has no corpus entry",
+                rule.name
+            );
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Resolving domains of outbound requests with DNS over HTTPS (`doh` feature),
+//! so short links resolved locally do not leak their domains to resolver of ISP,
+//! see [`crate::UrlWasherConfig::dns_over_https`].
+
+use std::fmt::Display;
+#[cfg(all(feature = "doh", not(target_arch = "wasm32")))]
+use std::net::SocketAddr;
+
+#[cfg(all(feature = "doh", not(target_arch = "wasm32")))]
+use hickory_resolver::{
+    config::{ResolverConfig, ResolverOpts},
+    TokioAsyncResolver,
+};
+#[cfg(all(feature = "doh", not(target_arch = "wasm32")))]
+use hyper_reqwest::client::connect::dns::Name;
+#[cfg(all(feature = "doh", not(target_arch = "wasm32")))]
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DohProvider {
+    Cloudflare,
+    Google,
+    Quad9,
+}
+
+impl Display for DohProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DohProvider::Cloudflare => "cloudflare",
+            DohProvider::Google => "google",
+            DohProvider::Quad9 => "quad9",
+        })
+    }
+}
+
+/// Resolver of reqwest client backed by hickory, with its own cache.
+///
+/// Asks [`DohProvider`] for washer, apps with other resolvers (e.g. mixer) create it with their own config.
+#[cfg(all(feature = "doh", not(target_arch = "wasm32")))]
+pub struct CachingResolver(TokioAsyncResolver);
+
+#[cfg(all(feature = "doh", not(target_arch = "wasm32")))]
+impl CachingResolver {
+    pub fn new(config: ResolverConfig, opts: ResolverOpts) -> Self {
+        Self(TokioAsyncResolver::tokio(config, opts))
+    }
+
+    /// Resolver asking `provider` over HTTPS.
+    pub fn doh(provider: DohProvider) -> Self {
+        let config = match provider {
+            DohProvider::Cloudflare => ResolverConfig::cloudflare_https(),
+            DohProvider::Google => ResolverConfig::google_https(),
+            DohProvider::Quad9 => ResolverConfig::quad9_https(),
+        };
+        Self::new(config, ResolverOpts::default())
+    }
+}
+
+#[cfg(all(feature = "doh", not(target_arch = "wasm32")))]
+impl Resolve for CachingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.0.clone();
+        Box::pin(async move {
+            let lookup = resolver.lookup_ip(name.as_str()).await?;
+            let addrs: Addrs = Box::new(
+                lookup
+                    .iter()
+                    .map(|ip| SocketAddr::new(ip, 0))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            );
+            Ok(addrs)
+        })
+    }
+}
@@ -0,0 +1,237 @@
+//! C interface of washer (`ffi` feature), declared in [`include/urlwasher.h`](../include/urlwasher.h).
+//!
+//! Urls are washed by [`UrlWasher`] handle created with `urlwasher_new`, blocking calling thread until done.
+//! Handles, strings and reports returned by the library must be freed with
+//! `urlwasher_free`, `urlwasher_string_free` and `urlwasher_report_free`.
+
+use std::{
+    ffi::{c_char, CStr, CString},
+    future::Future,
+    ptr,
+    sync::OnceLock,
+};
+
+use tokio::runtime::Runtime;
+use tracing::debug;
+use url::Url;
+
+use crate::{UrlWasher, UrlWasherConfig};
+
+/// Drives network requests of washes, shared by all calling threads.
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// [`crate::WashReport`] passed to C, strings are owned by it.
+#[repr(C)]
+pub struct UrlWasherReport {
+    pub cleaned: *mut c_char,
+    /// `NULL` if no rule matched original url.
+    pub rule: *mut c_char,
+    pub removed_params: *mut *mut c_char,
+    pub removed_params_len: usize,
+    pub resolution_hops: usize,
+    pub cached: bool,
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .enable_all()
+                .build()
+                .expect("build ffi runtime")
+        })
+        .block_on(future)
+}
+
+unsafe fn parse_url(url: *const c_char) -> Option<Url> {
+    if url.is_null() {
+        return None;
+    }
+    Url::parse(CStr::from_ptr(url).to_str().ok()?).ok()
+}
+
+/// `NULL` if `string` contains nul byte.
+fn into_raw_string(string: String) -> *mut c_char {
+    CString::new(string).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Creates washer configured by [`UrlWasherConfig`] serialized as JSON, or default config if `config_json` is `NULL`.
+/// Returns `NULL` if config is invalid.
+///
+/// # Safety
+/// `config_json` must be `NULL` or nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn urlwasher_new(config_json: *const c_char) -> *mut UrlWasher {
+    let config = if config_json.is_null() {
+        UrlWasherConfig::default()
+    } else {
+        let parsed = CStr::from_ptr(config_json)
+            .to_str()
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(serde_json::from_str(json)?));
+        match parsed {
+            Ok(config) => config,
+            Err(err) => {
+                debug!("parse config: {err:#}");
+                return ptr::null_mut();
+            }
+        }
+    };
+    match UrlWasher::builder(config).build() {
+        Ok(washer) => Box::into_raw(Box::new(washer)),
+        Err(err) => {
+            debug!("build washer: {err:#}");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees washer returned by [`urlwasher_new`], `NULL` is ignored.
+///
+/// # Safety
+/// `washer` must be returned by [`urlwasher_new`], not freed yet and not used by other threads.
+#[no_mangle]
+pub unsafe extern "C" fn urlwasher_free(washer: *mut UrlWasher) {
+    if !washer.is_null() {
+        drop(Box::from_raw(washer));
+    }
+}
+
+/// Washes `url`, returning clean url, or the same url if there was nothing to wash.
+/// Returns `NULL` if `washer` is `NULL`, `url` is not a valid url or washing failed.
+///
+/// # Safety
+/// `washer` must be `NULL` or returned by [`urlwasher_new`] and not freed yet.
+/// `url` must be `NULL` or nul terminated string. Must not be called from async Rust code.
+#[no_mangle]
+pub unsafe extern "C" fn urlwasher_wash(
+    washer: *const UrlWasher,
+    url: *const c_char,
+) -> *mut c_char {
+    let (Some(washer), Some(url)) = (washer.as_ref(), parse_url(url)) else {
+        return ptr::null_mut();
+    };
+    match block_on(washer.wash(&url)) {
+        Ok(washed) => into_raw_string(washed.unwrap_or(url).into()),
+        Err(err) => {
+            debug!("wash {url}: {err:#}");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Like [`urlwasher_wash`], also describing what was changed.
+/// Returns `NULL` if `washer` is `NULL`, `url` is not a valid url or washing failed.
+///
+/// # Safety
+/// See [`urlwasher_wash`].
+#[no_mangle]
+pub unsafe extern "C" fn urlwasher_wash_with_report(
+    washer: *const UrlWasher,
+    url: *const c_char,
+) -> *mut UrlWasherReport {
+    let (Some(washer), Some(url)) = (washer.as_ref(), parse_url(url)) else {
+        return ptr::null_mut();
+    };
+    let report = match block_on(washer.wash_with_report(&url)) {
+        Ok(report) => report,
+        Err(err) => {
+            debug!("wash {url}: {err:#}");
+            return ptr::null_mut();
+        }
+    };
+    let removed_params = report
+        .removed_params
+        .into_iter()
+        .map(into_raw_string)
+        .collect::<Box<[_]>>();
+    Box::into_raw(Box::new(UrlWasherReport {
+        cleaned: into_raw_string(report.cleaned),
+        rule: report.rule.map_or(ptr::null_mut(), into_raw_string),
+        removed_params_len: removed_params.len(),
+        removed_params: Box::into_raw(removed_params).cast(),
+        resolution_hops: report.resolution_hops,
+        cached: report.cached,
+    }))
+}
+
+/// Frees string returned by [`urlwasher_wash`], `NULL` is ignored.
+///
+/// # Safety
+/// `string` must be returned by this library and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn urlwasher_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Frees report returned by [`urlwasher_wash_with_report`] with all its strings, `NULL` is ignored.
+///
+/// # Safety
+/// `report` must be returned by this library and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn urlwasher_report_free(report: *mut UrlWasherReport) {
+    if report.is_null() {
+        return;
+    }
+    let report = Box::from_raw(report);
+    urlwasher_string_free(report.cleaned);
+    urlwasher_string_free(report.rule);
+    let removed_params = Box::from_raw(ptr::slice_from_raw_parts_mut(
+        report.removed_params,
+        report.removed_params_len,
+    ));
+    for param in removed_params.iter() {
+        urlwasher_string_free(*param);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn washes_through_c_interface() {
+        let url = c"https://open.spotify.com/track/1?si=x&id=2";
+        unsafe {
+            let washer = urlwasher_new(ptr::null());
+            assert!(!washer.is_null());
+
+            let washed = urlwasher_wash(washer, url.as_ptr());
+            assert_eq!(
+                CStr::from_ptr(washed).to_str(),
+                Ok("https://open.spotify.com/track/1?id=2")
+            );
+            urlwasher_string_free(washed);
+
+            let report = urlwasher_wash_with_report(washer, url.as_ptr());
+            assert!(!(*report).rule.is_null());
+            assert_eq!((*report).removed_params_len, 1);
+            assert_eq!(CStr::from_ptr(*(*report).removed_params).to_str(), Ok("si"));
+            urlwasher_report_free(report);
+
+            assert!(urlwasher_wash(washer, c"not a url".as_ptr()).is_null());
+            assert!(urlwasher_wash(washer, ptr::null()).is_null());
+            assert!(urlwasher_wash(ptr::null(), url.as_ptr()).is_null());
+            urlwasher_free(washer);
+        }
+    }
+
+    #[test]
+    fn creates_washer_from_json_config() {
+        unsafe {
+            assert!(urlwasher_new(c"not json".as_ptr()).is_null());
+
+            let config = UrlWasherConfig {
+                keep_referral_marketing: true,
+                ..Default::default()
+            };
+            let json = CString::new(serde_json::to_string(&config).unwrap()).unwrap();
+            let washer = urlwasher_new(json.as_ptr());
+            assert_eq!((*washer).config, config);
+            urlwasher_free(washer);
+        }
+    }
+}
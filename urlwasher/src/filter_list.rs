@@ -0,0 +1,269 @@
+//! Import of `$removeparam` rules from AdGuard and uBlock Origin filter lists.
+//!
+//! Supported filters are `$removeparam` (removes all params), `$removeparam=name` and
+//! `$removeparam=/regex/i`, optionally scoped by url pattern, e.g. `||example.com^$removeparam=ref`
+//! (uBlock Origin's legacy `$queryprune` is accepted too).
+//! Other filters, exception filters (`@@`) and negated values (`~name`) are skipped.
+//!
+//! Washer applies only the first matching rule, so unscoped params are added to every scoped rule
+//! and rule removing them from any url comes last. Built-in rules still take precedence
+//! when layer is composed after them.
+
+use anyhow::{anyhow, Context};
+use tracing::warn;
+#[cfg(feature = "network")]
+use url::Url;
+
+use crate::{rule_layers::RuleLayer, DirtyUrlRule, Pattern, WashingProgram};
+
+pub const LAYER_NAME: &str = "filter list";
+
+/// Name of rule removing unscoped params.
+const GLOBAL_RULE: &str = "*";
+
+#[derive(Default)]
+struct ScopedFilters {
+    remove_all: bool,
+    params: Vec<Pattern>,
+}
+
+/// Fetches filter list subscription and converts it to rule layer.
+#[cfg(feature = "network")]
+pub async fn fetch(http_client: &reqwest::Client, url: &Url) -> anyhow::Result<RuleLayer> {
+    let resp = http_client
+        .get(url.clone())
+        .send()
+        .await
+        .with_context(|| format!("fetch filter list {url}"))?;
+    if !resp.status().is_success() {
+        return Err(anyhow!(
+            "Invalid status of filter list {url}: {}",
+            resp.status()
+        ));
+    }
+    let text = resp
+        .text()
+        .await
+        .with_context(|| format!("read filter list {url}"))?;
+    Ok(import(&text))
+}
+
+/// Converts filter list to rule layer, to be composed after built-in rules.
+/// Filters with invalid patterns are skipped.
+pub fn import(text: &str) -> RuleLayer {
+    let mut scopes: Vec<(String, ScopedFilters)> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        let Some((scope, value)) = parse_filter(line) else {
+            continue;
+        };
+        let param = match value.map(param_pattern).transpose() {
+            Ok(param) => param,
+            Err(err) => {
+                warn!("Skipping filter {line}: {err:#}");
+                continue;
+            }
+        };
+        let index = match scopes.iter().position(|(existing, _)| *existing == scope) {
+            Some(index) => index,
+            None => {
+                scopes.push((scope.to_string(), ScopedFilters::default()));
+                scopes.len() - 1
+            }
+        };
+        let filters = &mut scopes[index].1;
+        match param {
+            Some(param) => filters.params.push(param),
+            None => filters.remove_all = true,
+        }
+    }
+
+    let global = scopes
+        .iter()
+        .position(|(scope, _)| scope.is_empty())
+        .map(|index| scopes.remove(index).1);
+    let global_params = global
+        .as_ref()
+        .map_or(&[][..], |global| global.params.as_slice());
+    let mut rules = scopes
+        .into_iter()
+        .filter_map(|(scope, filters)| {
+            let url_pattern = match url_pattern(&scope) {
+                Ok(url_pattern) => url_pattern,
+                Err(err) => {
+                    warn!("Skipping filters of {scope}: {err:#}");
+                    return None;
+                }
+            };
+            let mut params = filters.params;
+            params.extend_from_slice(global_params);
+            Some(rule(scope, url_pattern, filters.remove_all, params))
+        })
+        .collect::<Vec<_>>();
+    if let Some(global) = global {
+        let any_url = Pattern::new("^https?://").unwrap();
+        rules.push(rule(
+            GLOBAL_RULE.to_string(),
+            any_url,
+            global.remove_all,
+            global.params,
+        ));
+    }
+    RuleLayer::new(LAYER_NAME, rules)
+}
+
+fn rule(
+    name: String,
+    url_pattern: Pattern,
+    remove_all: bool,
+    params: Vec<Pattern>,
+) -> DirtyUrlRule {
+    DirtyUrlRule {
+        name,
+        url_pattern: Some(url_pattern),
+        washing_programs: vec![if remove_all {
+            WashingProgram::RemoveAllParams
+        } else {
+            WashingProgram::RemoveMatchingParams(params)
+        }],
+        ..Default::default()
+    }
+}
+
+/// Url pattern and `$removeparam` value of filter, value is [`None`] if filter removes all params.
+fn parse_filter(line: &str) -> Option<(&str, Option<&str>)> {
+    if line.is_empty() || line.starts_with(['!', '[', '#']) || line.starts_with("@@") {
+        return None;
+    }
+    // `$` of options is the first one, except for ones inside regex scope `/.../`
+    let options_start = match line.strip_prefix('/') {
+        Some(regex_scope) => regex_scope.find("/$")? + 2,
+        None => line.find('$')?,
+    };
+    let (scope, options) = (&line[..options_start], &line[options_start + 1..]);
+    if scope.contains("##") || scope.contains("#@#") {
+        return None;
+    }
+    let option = split_options(options).find(|option| {
+        let name = option.split_once('=').map_or(*option, |(name, _)| name);
+        matches!(name, "removeparam" | "queryprune")
+    })?;
+    match option.split_once('=') {
+        Some((_, value)) if value.starts_with('~') || value.is_empty() => None,
+        Some((_, value)) => Some((scope, Some(value))),
+        None => Some((scope, None)),
+    }
+}
+
+/// Options are separated with commas, except for ones escaped or inside regex value.
+fn split_options(options: &str) -> impl Iterator<Item = &str> {
+    let mut in_regex = false;
+    let mut escaped = false;
+    let mut start = 0;
+    let mut parts = Vec::new();
+    for (index, char) in options.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '/' if options[start..index].ends_with('=') || in_regex => in_regex = !in_regex,
+            ',' if !in_regex => {
+                parts.push(&options[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&options[start..]);
+    parts.into_iter()
+}
+
+/// `/regex/flags` or exact param name.
+fn param_pattern(value: &str) -> anyhow::Result<Pattern> {
+    let pattern = match value
+        .strip_prefix('/')
+        .and_then(|value| value.rsplit_once('/'))
+    {
+        Some((regex, "i")) => format!("(?i){}", regex.replace("\\,", ",")),
+        Some((regex, "")) => regex.replace("\\,", ","),
+        Some((_, flags)) => return Err(anyhow!("unsupported regex flags {flags}")),
+        None => format!("^{}$", regex::escape(value)),
+    };
+    Pattern::new(&pattern).context("param pattern")
+}
+
+/// Converts adblock url pattern (`||domain^`, `|https://`, `*` wildcards) to regex.
+fn url_pattern(scope: &str) -> anyhow::Result<Pattern> {
+    if let Some(regex) = scope
+        .strip_prefix('/')
+        .and_then(|scope| scope.strip_suffix('/'))
+    {
+        return Pattern::new(regex).context("url regex");
+    }
+    let mut regex = String::from("(?i)");
+    let mut rest = scope;
+    if let Some(after_domain_anchor) = rest.strip_prefix("||") {
+        regex.push_str(r"^[a-z][a-z0-9+.-]*://(?:[^/?#]*\.)?");
+        rest = after_domain_anchor;
+    } else if let Some(after_start_anchor) = rest.strip_prefix('|') {
+        regex.push('^');
+        rest = after_start_anchor;
+    }
+    let (rest, end_anchor) = match rest.strip_suffix('|') {
+        Some(rest) => (rest, true),
+        None => (rest, false),
+    };
+    for char in rest.chars() {
+        match char {
+            '*' => regex.push_str(".*"),
+            '^' => regex.push_str(r"(?:[^\w.%-]|$)"),
+            char => regex.push_str(&regex::escape(&char.to_string())),
+        }
+    }
+    if end_anchor {
+        regex.push('$');
+    }
+    Pattern::new(&regex).context("url pattern")
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::{rule_layers, UrlWasher, UrlWasherConfig};
+
+    const FILTER_LIST: &str = r#"
+! Title: Test list
+$removeparam=utm_source
+$removeparam=/^utm_(?:medium|campaign)$/i
+||example.com^$removeparam=ref,document
+||example.org^$removeparam
+@@||example.net^$removeparam=id
+example.com##.banner
+$removeparam=~keep
+"#;
+
+    #[tokio::test]
+    async fn removes_params_of_filters() {
+        let layer = super::import(FILTER_LIST);
+        assert_eq!(layer.rules.len(), 3);
+        let washer = UrlWasher::new(UrlWasherConfig::default())
+            .with_rules(rule_layers::compose(&[layer]).rules);
+        let wash = |url: &str| {
+            let url = Url::parse(url).unwrap();
+            let washer = &washer;
+            async move { washer.wash(&url).await.unwrap().map(String::from) }
+        };
+
+        assert_eq!(
+            wash("https://www.example.com/a?ref=1&utm_source=x&UTM_Medium=y&id=2").await,
+            Some("https://www.example.com/a?id=2".to_string())
+        );
+        assert_eq!(
+            wash("https://example.org/a?id=1").await,
+            Some("https://example.org/a".to_string())
+        );
+        assert_eq!(
+            wash("https://example.net/a?ref=1&utm_campaign=x&id=1").await,
+            Some("https://example.net/a?ref=1&id=1".to_string())
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Recorded redirect locations replayed instead of network, so tests do not depend on live short links.
+//!
+//! Fixtures of urlwasher tests are recorded by `record_fixtures` example, which resolves short links live
+//! and saves their locations to fixture file. Tests only replay them.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::ErrorKind,
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::Context;
+use url::Url;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FixtureMode {
+    /// Serve recorded locations, short links without fixture fail to wash.
+    Replay,
+    /// Resolve short links live and remember their locations.
+    Record,
+}
+
+pub struct RedirectFixtures {
+    mode: FixtureMode,
+    /// Short link -> location header it responded with, sorted so saved file is stable.
+    locations: Mutex<BTreeMap<Url, Url>>,
+}
+
+impl RedirectFixtures {
+    pub fn replay(locations: HashMap<Url, Url>) -> Self {
+        Self {
+            mode: FixtureMode::Replay,
+            locations: Mutex::new(locations.into_iter().collect()),
+        }
+    }
+
+    pub fn record() -> Self {
+        Self {
+            mode: FixtureMode::Record,
+            locations: Default::default(),
+        }
+    }
+
+    /// Loads fixture file to replay.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Ok(Self::replay(HashMap::new()))
+            }
+            Err(err) => return Err(err).with_context(|| format!("read {}", path.display())),
+        };
+        serde_json::from_slice(&bytes)
+            .map(Self::replay)
+            .with_context(|| format!("parse {}", path.display()))
+    }
+
+    /// Writes recorded locations to fixture file, does nothing when replaying.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if self.mode == FixtureMode::Replay {
+            return Ok(());
+        }
+        let serialized = serde_json::to_vec_pretty(&*self.locations.lock().unwrap())
+            .context("serialize fixtures")?;
+        fs::write(path, serialized).with_context(|| format!("write {}", path.display()))
+    }
+
+    pub fn mode(&self) -> FixtureMode {
+        self.mode
+    }
+
+    pub(crate) fn location(&self, url: &Url) -> anyhow::Result<Url> {
+        self.locations
+            .lock()
+            .unwrap()
+            .get(url)
+            .cloned()
+            .with_context(|| format!("missing redirect fixture of {url}"))
+    }
+
+    pub(crate) fn insert(&self, url: Url, location: Url) {
+        self.locations.lock().unwrap().insert(url, location);
+    }
+}
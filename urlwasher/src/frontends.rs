@@ -0,0 +1,121 @@
+//! Rewriting of washed links to privacy friendly frontends, e.g. nitter, invidious or redlib instances.
+//!
+//! Opt-in per service with [`crate::UrlWasherConfig::privacy_frontends`], applied by
+//! [`crate::WashingProgram::UseFrontend`] after tracking params are removed.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontendService {
+    /// Served by nitter.
+    Twitter,
+    /// Served by invidious or piped.
+    Youtube,
+    /// Served by redlib.
+    Reddit,
+}
+
+impl FrontendService {
+    pub const ALL: [FrontendService; 3] = [Self::Twitter, Self::Youtube, Self::Reddit];
+
+    /// Domains whose links can be opened by frontend of this service.
+    pub fn domains(self) -> &'static [&'static str] {
+        match self {
+            Self::Twitter => &["twitter.com", "x.com", "mobile.twitter.com", "mobile.x.com"],
+            Self::Youtube => &[
+                "youtube.com",
+                "www.youtube.com",
+                "m.youtube.com",
+                "youtu.be",
+            ],
+            Self::Reddit => &[
+                "reddit.com",
+                "www.reddit.com",
+                "old.reddit.com",
+                "new.reddit.com",
+            ],
+        }
+    }
+
+    /// Frontend software usually hosting this service, shown next to instance url.
+    pub fn frontend_name(self) -> &'static str {
+        match self {
+            Self::Twitter => "nitter",
+            Self::Youtube => "invidious",
+            Self::Reddit => "redlib",
+        }
+    }
+}
+
+impl Display for FrontendService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Twitter => "Twitter / X",
+            Self::Youtube => "YouTube",
+            Self::Reddit => "Reddit",
+        })
+    }
+}
+
+/// Moves url to frontend instance, keeping its path, query and fragment.
+/// Returns [`None`] if url is not a link of service.
+pub fn rewrite(service: FrontendService, url: &Url, instance: &Url) -> Option<Url> {
+    let domain = url.domain()?;
+    if !service.domains().contains(&domain) {
+        return None;
+    }
+    let mut rewritten = instance.clone();
+    let prefix = instance.path().trim_end_matches('/');
+    if service == FrontendService::Youtube && domain == "youtu.be" {
+        // short links carry video id in path, frontends expect it as `v` param of watch page
+        let video_id = url.path().trim_start_matches('/');
+        rewritten.set_path(&format!("{prefix}/watch"));
+        let mut query = rewritten.query_pairs_mut();
+        query.clear().append_pair("v", video_id);
+        query.extend_pairs(url.query_pairs());
+    } else {
+        rewritten.set_path(&format!("{prefix}{}", url.path()));
+        rewritten.set_query(url.query());
+    }
+    rewritten.set_fragment(url.fragment());
+    Some(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::{rewrite, FrontendService};
+
+    #[test]
+    fn rewrites_to_frontend_instance() {
+        let tests = [
+            (
+                FrontendService::Twitter,
+                "https://x.com/sekurak/status/1737942071431073818",
+                "https://frontend.example/sekurak/status/1737942071431073818",
+            ),
+            (
+                FrontendService::Youtube,
+                "https://youtu.be/lSwnPoo9ZK0?t=65",
+                "https://frontend.example/watch?v=lSwnPoo9ZK0&t=65",
+            ),
+            (
+                FrontendService::Reddit,
+                "https://www.reddit.com/r/rust/comments/abc/title/?context=3",
+                "https://frontend.example/r/rust/comments/abc/title/?context=3",
+            ),
+        ];
+        let instance = Url::parse("https://frontend.example/").unwrap();
+        for (service, url, expected) in tests {
+            let rewritten = rewrite(service, &Url::parse(url).unwrap(), &instance).unwrap();
+            assert_eq!(rewritten.as_str(), expected);
+        }
+        let other = Url::parse("https://music.youtube.com/watch?v=lSwnPoo9ZK0").unwrap();
+        assert_eq!(rewrite(FrontendService::Youtube, &other, &instance), None);
+    }
+}
@@ -0,0 +1,127 @@
+//! Redirects done by page itself instead of `Location` header,
+//! e.g. `<meta http-equiv="refresh" content="0;url=...">` returned with status 200,
+//! or `window.location = "..."` script (opt-in, see [`crate::UrlWasherConfig::detect_script_redirects`]).
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+use url::Url;
+
+/// Bytes of page read when looking for redirect, redirect belongs to `<head>` anyway.
+#[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+const MAX_PAGE_LEN: usize = 64 * 1024;
+
+static META_TAG: OnceLock<Regex> = OnceLock::new();
+static REFRESH_URL: OnceLock<Regex> = OnceLock::new();
+static SCRIPT_REDIRECT: OnceLock<Regex> = OnceLock::new();
+
+/// Reads beginning of page, at most [`MAX_PAGE_LEN`] bytes.
+#[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+pub(crate) async fn read_page(mut resp: reqwest::Response) -> anyhow::Result<String> {
+    let mut page = Vec::new();
+    while page.len() < MAX_PAGE_LEN {
+        match resp.chunk().await? {
+            Some(chunk) => page.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+    page.truncate(MAX_PAGE_LEN);
+    Ok(String::from_utf8_lossy(&page).into_owned())
+}
+
+/// Target of first meta refresh of `html`, relative ones are resolved against `base`.
+/// Only http(s) targets are accepted.
+pub fn meta_refresh(base: &Url, html: &str) -> Option<Url> {
+    let meta_tag = META_TAG.get_or_init(|| {
+        Regex::new(r#"(?is)<meta\s[^>]*?http-equiv\s*=\s*["']?refresh["']?[^>]*>"#).unwrap()
+    });
+    let refresh_url = REFRESH_URL.get_or_init(|| {
+        Regex::new(
+            r#"(?is)\scontent\s*=\s*(?:"[^"]*?url\s*=\s*'?([^"']+)|'[^']*?url\s*=\s*"?([^'"]+))"#,
+        )
+        .unwrap()
+    });
+    let target = meta_tag.find_iter(html).find_map(|tag| {
+        let captures = refresh_url.captures(tag.as_str())?;
+        captures.get(1).or_else(|| captures.get(2))
+    })?;
+    resolve_target(base, &target.as_str().trim().replace("&amp;", "&"))
+}
+
+/// Target of first `location = "..."`, `location.href = "..."`, `location.replace("...")`
+/// or `location.assign("...")` in `html`, relative ones are resolved against `base`.
+///
+/// Heuristic, script is not executed, so targets built at runtime are missed.
+pub fn script_redirect(base: &Url, html: &str) -> Option<Url> {
+    let script_redirect = SCRIPT_REDIRECT.get_or_init(|| {
+        Regex::new(
+            r#"\blocation(?:\.href)?\s*=\s*(?:"([^"]+)"|'([^']+)')|\blocation\.(?:replace|assign)\(\s*(?:"([^"]+)"|'([^']+)')\s*\)"#,
+        )
+        .unwrap()
+    });
+    let captures = script_redirect.captures(html)?;
+    let target = (1..=4).find_map(|group| captures.get(group))?;
+    // urls in scripts are often json encoded
+    resolve_target(base, &target.as_str().replace("\\/", "/"))
+}
+
+fn resolve_target(base: &Url, target: &str) -> Option<Url> {
+    base.join(target)
+        .ok()
+        .filter(|target| matches!(target.scheme(), "http" | "https"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_meta_refresh_target() {
+        let base = Url::parse("https://short.example/abc").unwrap();
+        let target = |html: &str| meta_refresh(&base, html).map(String::from);
+
+        assert_eq!(
+            target(
+                r#"<html><head><meta http-equiv="refresh" content="0;url=https://example.com/a?b=1&amp;c=2"></head></html>"#
+            ),
+            Some("https://example.com/a?b=1&c=2".to_string())
+        );
+        assert_eq!(
+            target(r#"<META CONTENT='5; URL="/landing"' HTTP-EQUIV=Refresh>"#),
+            Some("https://short.example/landing".to_string())
+        );
+        assert_eq!(target(r#"<meta http-equiv="refresh" content="30">"#), None);
+        assert_eq!(
+            target(r#"<meta http-equiv="refresh" content="0;url=javascript:alert(1)">"#),
+            None
+        );
+        assert_eq!(
+            target(r#"<meta name="description" content="url=https://example.com">"#),
+            None
+        );
+    }
+
+    #[test]
+    fn finds_script_redirect_target() {
+        let base = Url::parse("https://short.example/abc").unwrap();
+        let target = |html: &str| script_redirect(&base, html).map(String::from);
+
+        assert_eq!(
+            target(r#"<script>window.location = 'https://example.com/a';</script>"#),
+            Some("https://example.com/a".to_string())
+        );
+        assert_eq!(
+            target(r#"<script>window.location.href="https:\/\/example.com\/b"</script>"#),
+            Some("https://example.com/b".to_string())
+        );
+        assert_eq!(
+            target(r#"<script>location.replace( "/landing" );</script>"#),
+            Some("https://short.example/landing".to_string())
+        );
+        assert_eq!(
+            target(r#"<script>if (location.href == "https://a.com") {}</script>"#),
+            None
+        );
+        assert_eq!(target(r#"<script>location = url;</script>"#), None);
+    }
+}
@@ -0,0 +1,50 @@
+//! Coalescing of concurrent washes of the same url, so e.g. 50 requests for one short link
+//! arriving at once resolve it only once.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use futures_channel::oneshot;
+use futures_util::future::{FutureExt, Shared};
+use url::Url;
+
+/// Resolves when leading wash is done, by dropping its sender.
+pub(crate) type WashDone = Shared<oneshot::Receiver<()>>;
+
+#[derive(Default)]
+pub(crate) struct InFlightWashes(Mutex<HashMap<Url, WashDone>>);
+
+pub(crate) enum Joined<'a> {
+    /// Caller washes url, others wait until guard is dropped.
+    Leader(LeaderGuard<'a>),
+    /// Leader's result is cached once it resolves, unless leader failed.
+    Follower(WashDone),
+}
+
+impl InFlightWashes {
+    pub(crate) fn join(&self, url: &Url) -> Joined<'_> {
+        let mut washes = self.0.lock().unwrap();
+        if let Some(done) = washes.get(url) {
+            return Joined::Follower(done.clone());
+        }
+        let (sender, receiver) = oneshot::channel();
+        washes.insert(url.clone(), receiver.shared());
+        Joined::Leader(LeaderGuard {
+            washes: self,
+            url: url.clone(),
+            _done: sender,
+        })
+    }
+}
+
+pub(crate) struct LeaderGuard<'a> {
+    washes: &'a InFlightWashes,
+    url: Url,
+    _done: oneshot::Sender<()>,
+}
+
+impl Drop for LeaderGuard<'_> {
+    fn drop(&mut self) {
+        // removed before sender is dropped, so woken followers never join finished wash
+        self.washes.0.lock().unwrap().remove(&self.url);
+    }
+}
@@ -1,17 +1,68 @@
-use anyhow::{anyhow, Context};
-use lru::LruCache;
-use reqwest::redirect::Policy;
+use anyhow::anyhow;
+#[cfg(feature = "network")]
+use anyhow::Context;
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use blocklist::{BlockedDestination, Blocklist, BlocklistConfig};
+pub use builder::UrlWasherBuilder;
+#[cfg(any(test, feature = "test-fixtures"))]
+use fixtures::{FixtureMode, RedirectFixtures};
+use frontends::FrontendService;
+use in_flight::{InFlightWashes, Joined};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt::Display, num::NonZeroUsize, sync::OnceLock};
-use tokio::sync::Mutex;
-use tracing::debug;
-use url::Url;
+use sharded_cache::ShardedLruCache;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Display,
+    num::NonZeroUsize,
+    sync::{Arc, OnceLock, RwLock},
+    time::{Duration, Instant},
+};
+use tracing::{debug, debug_span, field, Instrument};
+use url::{form_urlencoded, Url};
+pub use urldebloater_proto::report::WashReport;
 
+pub mod analyzer;
+pub mod blocklist;
+mod builder;
+pub mod clearurls;
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod corpus;
+pub mod doh;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod filter_list;
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod fixtures;
+pub mod frontends;
+pub mod html_redirect;
+mod in_flight;
+mod markdown;
+mod measure;
+pub mod mixer_api;
+#[cfg(feature = "network")]
+pub mod mixer_client;
+pub mod public_suffix;
+pub mod rule_file;
+pub mod rule_layers;
+pub mod rule_source;
+mod sharded_cache;
+pub mod shorteners;
 pub mod text_washer;
+#[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+mod throttle;
+pub mod url_defense;
 
 pub const PUBLIC_MIXER_INSTANCE: &str = "https://urldebloater.makin.cc/";
 
+pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+/// Bump on every change of [`rule_set`], so outdated mixer instances can be told apart.
+pub const RULE_SET_VERSION: u32 = 7;
+
 static DEFAULT_RULE_SET: OnceLock<Vec<DirtyUrlRule>> = OnceLock::new();
+static UNIVERSAL_TRACKERS_RULE: OnceLock<DirtyUrlRule> = OnceLock::new();
+static GLOBAL_WASHER: OnceLock<Arc<UrlWasher>> = OnceLock::new();
 
 pub type RuleName = String;
 
@@ -21,7 +72,17 @@ pub fn rule_set() -> &'static Vec<DirtyUrlRule> {
             DirtyUrlRule {
                 name: "youtu.be".to_string(),
                 domains: vec!["youtu.be".to_string()],
-                washing_programs: vec![WashingProgram::remove_some_params(&["si"])],
+                washing_programs: vec![
+                    WashingProgram::remove_some_params(&["si"]),
+                    WashingProgram::UseFrontend(FrontendService::Youtube),
+                ],
+                ..Default::default()
+            },
+            DirtyUrlRule {
+                name: "youtube.com/redirect".to_string(),
+                domains: vec!["youtube.com".to_string(), "www.youtube.com".to_string()],
+                path_regex: Some(Pattern::new("^/redirect$").unwrap()),
+                washing_programs: vec![WashingProgram::extract_embedded_url(&["q"])],
                 ..Default::default()
             },
             DirtyUrlRule {
@@ -31,7 +92,10 @@ pub fn rule_set() -> &'static Vec<DirtyUrlRule> {
                     "www.youtube.com".to_string(),
                     "music.youtube.com".to_string(),
                 ],
-                washing_programs: vec![WashingProgram::remove_some_params(&["si"])],
+                washing_programs: vec![
+                    WashingProgram::remove_some_params(&["si"]),
+                    WashingProgram::UseFrontend(FrontendService::Youtube),
+                ],
                 ..Default::default()
             },
             #[warn(clippy::needless_update)]
@@ -39,18 +103,32 @@ pub fn rule_set() -> &'static Vec<DirtyUrlRule> {
                 name: "twitter.com".to_string(),
                 domains: vec!["twitter.com".to_string(), "x.com".to_string()],
                 path_pattern: vec![],
-                washing_programs: vec![WashingProgram::RemoveAllParams],
+                washing_programs: vec![
+                    WashingProgram::RemoveAllParams,
+                    WashingProgram::UseFrontend(FrontendService::Twitter),
+                ],
                 ..Default::default()
             },
             DirtyUrlRule {
                 name: "vm.tiktok.com".to_string(),
                 domains: vec!["vm.tiktok.com".to_string()],
+                // params are removed even if link is not resolved, destination is washed by tiktok.com rule too
                 washing_programs: vec![
                     WashingProgram::ResolveRedirection,
                     WashingProgram::RemoveAllParams,
                 ],
                 ..Default::default()
             },
+            DirtyUrlRule {
+                name: "tiktok.com".to_string(),
+                domains: vec![
+                    "tiktok.com".to_string(),
+                    "www.tiktok.com".to_string(),
+                    "m.tiktok.com".to_string(),
+                ],
+                washing_programs: vec![WashingProgram::RemoveAllParams],
+                ..Default::default()
+            },
             DirtyUrlRule {
                 name: "on.soundcloud.com".to_string(),
                 domains: vec!["on.soundcloud.com".to_string()],
@@ -66,252 +144,2226 @@ pub fn rule_set() -> &'static Vec<DirtyUrlRule> {
                 washing_programs: vec![WashingProgram::remove_some_params(&["si"])],
                 ..Default::default()
             },
+            DirtyUrlRule {
+                name: "reddit.com".to_string(),
+                domains: vec![
+                    "reddit.com".to_string(),
+                    "www.reddit.com".to_string(),
+                    "old.reddit.com".to_string(),
+                ],
+                washing_programs: vec![
+                    WashingProgram::remove_some_params(&[
+                        "share_id",
+                        "utm_source",
+                        "utm_medium",
+                        "utm_name",
+                        "utm_term",
+                        "utm_content",
+                        "rdt",
+                    ]),
+                    WashingProgram::UseFrontend(FrontendService::Reddit),
+                ],
+                ..Default::default()
+            },
+            DirtyUrlRule {
+                name: "google.com/url".to_string(),
+                domains: vec!["google.com".to_string(), "www.google.com".to_string()],
+                path_regex: Some(Pattern::new("^/url$").unwrap()),
+                washing_programs: vec![WashingProgram::extract_embedded_url(&["q", "url"])],
+                ..Default::default()
+            },
+            DirtyUrlRule {
+                name: "l.facebook.com".to_string(),
+                domains: vec!["l.facebook.com".to_string(), "lm.facebook.com".to_string()],
+                path_regex: Some(Pattern::new("^/l\\.php$").unwrap()),
+                washing_programs: vec![WashingProgram::extract_embedded_url(&["u"])],
+                ..Default::default()
+            },
+            DirtyUrlRule {
+                name: "safelinks.protection.outlook.com".to_string(),
+                domains: vec!["*.safelinks.protection.outlook.com".to_string()],
+                washing_programs: vec![WashingProgram::extract_embedded_url(&["url"])],
+                ..Default::default()
+            },
+            DirtyUrlRule {
+                name: "urldefense".to_string(),
+                domains: vec![
+                    "urldefense.com".to_string(),
+                    "urldefense.proofpoint.com".to_string(),
+                ],
+                washing_programs: vec![WashingProgram::UnwrapUrlDefense],
+                ..Default::default()
+            },
+            DirtyUrlRule {
+                name: "amazon".to_string(),
+                sites: vec!["amazon.*".to_string()],
+                // product pages only, params of e.g. search are load-bearing
+                path_regex: Some(Pattern::new(AMAZON_PRODUCT_PATH).unwrap()),
+                washing_programs: vec![
+                    WashingProgram::SetSubdomain("www".to_string()),
+                    WashingProgram::RewritePath(PathRewrite {
+                        pattern: Pattern::new(AMAZON_PRODUCT_PATH).unwrap(),
+                        replacement: "/dp/$1".to_string(),
+                    }),
+                    WashingProgram::RemoveAllParams,
+                ],
+                ..Default::default()
+            },
         ]
     })
 }
 
+/// Product page (`/dp/ASIN`, `/gp/product/ASIN`, optionally after product name and followed by `/ref=...`),
+/// capturing its ASIN.
+const AMAZON_PRODUCT_PATH: &str = "^/(?:[^/]+/)?(?:dp|gp/product|gp/aw/d)/([A-Z0-9]{10})(?:/.*)?$";
+
+/// Steps of wash collected for [`UrlWasher::wash_with_report`], including washes of extracted destinations.
+#[derive(Default)]
+struct WashTrace {
+    /// Rule of original url.
+    rule: Option<String>,
+    /// Names of programs ran, see [`WashingProgram::name`].
+    programs: Vec<String>,
+    resolution_hops: usize,
+    /// Short links resolved so far, in order, to detect redirect loops.
+    resolved_links: Vec<Url>,
+    cached: bool,
+}
+
+/// What washing program did to url.
+enum ProgramStep {
+    Washed(Url),
+    /// Short link was resolved to destination, which is washed by its own rules.
+    Resolved {
+        short_link: Url,
+        destination: Url,
+    },
+    /// Destination was extracted from wrapper url, which is washed by its own rules.
+    Extracted(Url),
+}
+
+struct CachedUrl {
+    washed: Url,
+    /// Never expires if [`UrlWasherConfig::cache_ttl_secs`] is not set.
+    expires_at: Option<Instant>,
+}
+
+impl CachedUrl {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Persistent storage of resolved redirects, consulted before resolving short links over network.
+///
+/// Called synchronously while washing, so lookups must be fast (e.g. local database).
+pub trait RedirectStore: Send + Sync {
+    fn location(&self, short_url: &Url) -> Option<Url>;
+    fn insert(&self, short_url: Url, location: Url);
+    fn remove(&self, short_url: &Url);
+    fn clear(&self);
+}
+
+/// Notified of every wash that changed url, e.g. to record history or stats
+/// without wrapping every call site. Closures taking [`WashEvent`] are observers too.
+///
+/// Called synchronously after wash, so it must be fast.
+pub trait WashObserver: Send + Sync {
+    fn on_washed(&self, event: &WashEvent);
+}
+
+impl<F: Fn(&WashEvent) + Send + Sync> WashObserver for F {
+    fn on_washed(&self, event: &WashEvent) {
+        self(event)
+    }
+}
+
+/// Error of [`UrlWasher::wash`] when short link redirects (possibly through other short links)
+/// back to short link already resolved in the same wash. Redirect loops are not cached.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RedirectLoop {
+    /// Last short link before loop, which redirects back to `repeated`.
+    pub url: Url,
+    pub repeated: Url,
+    /// Number of redirects followed before loop was detected.
+    pub hops: usize,
+}
+
+impl Display for RedirectLoop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "redirect loop after {} hops: {} redirects back to {}",
+            self.hops, self.url, self.repeated
+        )
+    }
+}
+
+impl std::error::Error for RedirectLoop {}
+
+/// Error of [`UrlWasher::wash`] when short link redirects off its site,
+/// see [`UrlWasherConfig::same_site_redirects`]. Rejected redirects are not cached.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct OffSiteRedirect {
+    pub url: Url,
+    /// Rejected destination, should not be shown to user.
+    pub destination: Url,
+}
+
+impl Display for OffSiteRedirect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} redirects off site to {}",
+            self.url,
+            self.destination.host_str().unwrap_or_default()
+        )
+    }
+}
+
+impl std::error::Error for OffSiteRedirect {}
+
+/// Wash that changed url, passed to [`WashObserver`] and logged as `urlwasher::washed` debug event.
+pub struct WashEvent<'a> {
+    pub original: &'a Url,
+    pub washed: &'a Url,
+    /// Rule of original url, [`None`] if only params were sorted.
+    pub rule: Option<&'a str>,
+    /// Not measured in wasm, browsers have no monotonic clock usable without js bindings.
+    pub duration: Option<Duration>,
+    pub cached: bool,
+}
+
+/// Washer shared by whole process, so apps with many call sites (bots, plugins) do not have to
+/// pass an instance around or keep duplicate caches.
+///
+/// Created on first use with default config, unless [`set_global_config`] was called before.
+pub fn global() -> Arc<UrlWasher> {
+    GLOBAL_WASHER.get_or_init(Default::default).clone()
+}
+
+/// Cross-site tracking params, removed from urls no other rule matches
+/// if [`UrlWasherConfig::strip_universal_trackers`] is enabled.
+pub fn universal_trackers_rule() -> &'static DirtyUrlRule {
+    UNIVERSAL_TRACKERS_RULE.get_or_init(|| DirtyUrlRule {
+        name: "universal trackers".to_string(),
+        washing_programs: vec![
+            WashingProgram::remove_params_by_prefix(&["utm_"]),
+            WashingProgram::remove_some_params(&["fbclid", "gclid", "msclkid", "igshid", "mc_eid"]),
+        ],
+        ..Default::default()
+    })
+}
+
+/// Configures washer returned by [`global`].
+/// Fails if global washer is already created, call it early during startup.
+pub fn set_global_config(config: UrlWasherConfig) -> anyhow::Result<()> {
+    let mut config = Some(config);
+    GLOBAL_WASHER.get_or_init(|| Arc::new(UrlWasher::new(config.take().unwrap())));
+    match config {
+        Some(_) => Err(anyhow!("global washer is already initialized")),
+        None => Ok(()),
+    }
+}
+
+/// Does not depend on any async runtime, cache lock is never held across await points.
+/// Only network requests (resolving redirects, mixer api) need tokio, required by reqwest outside of wasm.
 pub struct UrlWasher {
-    cache: Mutex<LruCache<Url, Url>>,
-    http_client: reqwest::Client,
+    cache: ShardedLruCache<Url, CachedUrl>,
+    /// Urls no rule matched, see [`UrlWasherConfig::unmatched_cache_capacity`].
+    unmatched_cache: Option<ShardedLruCache<Url, ()>>,
+    in_flight: InFlightWashes,
+    /// Swapped as a whole by [`UrlWasher::replace_rules`], washes keep the set they started with.
+    rules: RwLock<Arc<Vec<DirtyUrlRule>>>,
+    /// See [`UrlWasher::with_redirect_fixtures`].
+    #[cfg(any(test, feature = "test-fixtures"))]
+    redirect_fixtures: Option<Arc<RedirectFixtures>>,
+    /// See [`UrlWasher::with_redirect_store`].
+    redirect_store: Option<Arc<dyn RedirectStore>>,
+    /// See [`UrlWasher::with_observer`].
+    observer: Option<Arc<dyn WashObserver>>,
+    blocklist: Blocklist,
+    http_client: HttpClient,
+    /// See [`UrlWasher::redirect_client`].
+    #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+    tor_client: OnceLock<reqwest::Client>,
+    #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+    redirect_throttle: throttle::DomainThrottle,
     config: UrlWasherConfig,
 }
 
+#[cfg(feature = "network")]
+type HttpClient = reqwest::Client;
+/// Washer built without `network` feature never makes requests.
+#[cfg(not(feature = "network"))]
+type HttpClient = ();
+
 impl Default for UrlWasher {
     fn default() -> Self {
         Self::new(UrlWasherConfig::default())
     }
 }
 
+/// Client builder preconfigured the way washer expects (default user agent, no redirect following).
+///
+/// Use it to customize outbound client (e.g. dns resolver) passed to [`UrlWasher::with_http_client`].
+#[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+pub fn http_client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .user_agent(DEFAULT_USER_AGENT)
+        .redirect(reqwest::redirect::Policy::none())
+}
+
+/// Browser fetch always follows redirects and sets its own user agent,
+/// so in wasm client is only usable for mixer requests.
+#[cfg(all(feature = "network", target_arch = "wasm32"))]
+pub fn http_client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+}
+
 impl UrlWasher {
+    /// Panics if http client cannot be built, e.g. [`UrlWasherConfig::dns_over_https`] is set
+    /// without `doh` feature, see [`UrlWasherBuilder::build`] for fallible version.
     pub fn new(config: UrlWasherConfig) -> Self {
+        Self::builder(config)
+            .build()
+            .expect("Could not build washer")
+    }
+
+    /// Washer with customized http client (user agent, timeouts, proxy) or cache.
+    pub fn builder(config: UrlWasherConfig) -> UrlWasherBuilder {
+        UrlWasherBuilder::new(config)
+    }
+
+    /// `http_client` must not follow redirects, otherwise redirection cannot be resolved.
+    #[cfg(feature = "network")]
+    pub fn with_http_client(config: UrlWasherConfig, http_client: reqwest::Client) -> Self {
+        Self::with_client(config, http_client)
+    }
+
+    fn with_client(mut config: UrlWasherConfig, http_client: HttpClient) -> Self {
+        config.migrate_redirect_policy(rule_set());
         Self {
-            cache: Mutex::new(LruCache::new(NonZeroUsize::new(1024).unwrap())),
-            http_client: reqwest::Client::builder()
-                .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-                .redirect(Policy::none())
-                .build()
-                .unwrap(),
+            cache: ShardedLruCache::new(config.cache_capacity),
+            unmatched_cache: config.unmatched_cache_capacity.map(ShardedLruCache::new),
+            in_flight: InFlightWashes::default(),
+            rules: RwLock::new(Arc::new(rule_set().clone())),
+            #[cfg(any(test, feature = "test-fixtures"))]
+            redirect_fixtures: None,
+            redirect_store: None,
+            observer: None,
+            blocklist: Blocklist::default(),
+            http_client,
+            #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+            tor_client: OnceLock::new(),
+            #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+            redirect_throttle: throttle::DomainThrottle::new(
+                config.redirect_concurrency_per_domain,
+            ),
             config,
         }
     }
 
-    pub async fn wash(&self, url: &Url) -> anyhow::Result<Option<Url>> {
-        if url.scheme() != "http" && url.scheme() != "https" {
-            return Ok(None);
-        }
-        if let Some(cached) = self.cache.lock().await.get(url) {
-            debug!("Serving washed url {} from cache.", url.to_string());
-            return Ok(Some(cached.to_owned()));
+    /// Replaces built-in [`rule_set`], e.g. with one composed by [`rule_layers::compose`].
+    pub fn with_rules(mut self, rules: Vec<DirtyUrlRule>) -> Self {
+        self.config.migrate_redirect_policy(&rules);
+        *self.rules.get_mut().unwrap() = Arc::new(rules);
+        self
+    }
+
+    /// Merges `layer` (e.g. loaded by [`rule_file::load`]) into current rules,
+    /// its rules replace current ones with the same name.
+    pub fn with_rule_layer(mut self, layer: rule_layers::RuleLayer) -> Self {
+        let rules = self.rules.get_mut().unwrap();
+        let current = rule_layers::RuleLayer::new("current", rules.as_ref().clone());
+        *rules = Arc::new(rule_layers::compose(&[current, layer]).rules);
+        self.config.migrate_redirect_policy(rules);
+        self
+    }
+
+    /// Swaps rules of running washer, e.g. with ones fetched by [`rule_source::RemoteRules`].
+    /// Cached results washed by previous rules are forgotten, resolved redirects in store are kept.
+    pub fn replace_rules(&self, rules: Vec<DirtyUrlRule>) {
+        *self.rules.write().unwrap() = Arc::new(rules);
+        self.cache.for_each_shard(|shard| shard.clear());
+        if let Some(unmatched_cache) = &self.unmatched_cache {
+            unmatched_cache.for_each_shard(|shard| shard.clear());
         }
-        let domain = match url.domain() {
-            Some(domain) => domain,
-            None => return Ok(None),
-        };
-        let rules = rule_set();
-        let matching_rule = match rules
-            .iter()
-            .find(|rule| rule.matches_domain(domain) && rule.matches_path(url))
+    }
+
+    /// Replays redirect locations from `fixtures` instead of network, or records them.
+    /// When replaying, short links missing in fixtures fail to wash, so tests never reach live network.
+    #[cfg(any(test, feature = "test-fixtures"))]
+    pub fn with_redirect_fixtures(mut self, fixtures: Arc<RedirectFixtures>) -> Self {
+        self.redirect_fixtures = Some(fixtures);
+        self
+    }
+
+    /// Keeps resolved redirects in `store`, so they outlive in-memory cache.
+    /// Ignored when washing with redirect fixtures.
+    pub fn with_redirect_store(mut self, store: Arc<dyn RedirectStore>) -> Self {
+        self.redirect_store = Some(store);
+        self
+    }
+
+    /// Notifies `observer` of every wash that changed url.
+    pub fn with_observer(mut self, observer: Arc<dyn WashObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Domains that unrolled short links must not lead to, empty until [`UrlWasher::refresh_blocklist`].
+    pub fn blocklist(&self) -> &Blocklist {
+        &self.blocklist
+    }
+
+    /// Fetches feeds of [`UrlWasherConfig::blocklist`], returns how many domains are listed.
+    #[cfg(feature = "network")]
+    pub async fn refresh_blocklist(&self) -> anyhow::Result<usize> {
+        self.blocklist
+            .refresh(&self.http_client, &self.config.blocklist.feeds)
+            .await
+    }
+
+    /// Params of `url` that look like trackers, if no rule matches it
+    /// and [`UrlWasherConfig::detect_unknown_trackers`] is enabled.
+    pub fn possible_trackers(&self, url: &Url) -> Vec<analyzer::PossibleTracker> {
+        if !self.config.detect_unknown_trackers
+            || !url
+                .host_str()
+                .is_some_and(|host| self.config.washes_domain(host))
+            || self.rules().iter().any(|rule| rule.matches(url))
         {
-            Some(r) => r,
-            None => return Ok(None),
-        };
-        let mut laundry = url.to_owned();
-        for washing_program in matching_rule.washing_programs.iter() {
-            laundry = match washing_program {
-                WashingProgram::ResolveRedirection => {
-                    let policy = self
-                        .config
-                        .redirect_policy
-                        .get(&matching_rule.name)
-                        .unwrap_or(&RedirectWashPolicy::Ignore);
-                    match resolve_redirect(
-                        &self.http_client,
-                        laundry,
-                        policy,
-                        &self.config.mixer_instance,
-                    )
-                    .await
-                    {
-                        Ok(Ok(url)) | Ok(Err(url)) => url,
-                        Err(err) => return Err(err),
-                    }
-                }
-                WashingProgram::RemoveSomeParams(params) => remove_query_params(&laundry, params),
-                WashingProgram::RemoveAllParams => {
-                    laundry.set_query(None);
-                    laundry
-                }
-            };
+            return Vec::new();
         }
-        self.cache.lock().await.put(url.to_owned(), laundry.clone());
-        Ok(Some(laundry))
+        analyzer::possible_trackers(url)
     }
-}
 
-fn remove_query_params(url: &Url, params: &[String]) -> Url {
-    let mut debloated_url = url.clone();
-    debloated_url.query_pairs_mut().clear();
-    let debloated_query = url
-        .query_pairs()
-        .filter(|(query_key, _)| params.iter().all(|param| param != query_key));
-    for (query_key, query_value) in debloated_query {
-        debloated_url
-            .query_pairs_mut()
-            .append_pair(&query_key, &query_value);
+    /// Rules of this washer in order they are tried, built-in [`rule_set`] unless replaced.
+    /// Current set only, rules may be replaced meanwhile by [`UrlWasher::replace_rules`].
+    pub fn rules(&self) -> Arc<Vec<DirtyUrlRule>> {
+        self.rules.read().unwrap().clone()
     }
-    if let Some("") = debloated_url.query() {
-        debloated_url.set_query(None);
+
+    /// Rules washing `url` in order, only the first one unless
+    /// [`UrlWasherConfig::apply_all_matching_rules`] is enabled.
+    pub fn matching_rules(&self, url: &Url) -> Vec<DirtyUrlRule> {
+        self.matching_rules_in(&self.rules(), url)
+            .into_iter()
+            .cloned()
+            .collect()
     }
-    debloated_url
-}
 
-async fn resolve_redirect(
-    http_client: &reqwest::Client,
-    url: Url,
-    policy: &RedirectWashPolicy,
-    mixer_instance: &Option<Url>,
-) -> anyhow::Result<Result<Url, Url>> {
-    match policy {
-        RedirectWashPolicy::Ignore => Ok(Err(url)),
-        RedirectWashPolicy::Locally => {
-            let resp = http_client.get(url).send().await?;
-            let location = resp
-                .headers()
-                .get("location")
-                .context("missing location header")?
-                .to_str()
-                .context("invalid location header")?;
-            Url::parse(location).context("parse location url").map(Ok)
-        }
-        RedirectWashPolicy::ViaMixer => {
-            let mixer_instance = mixer_instance
-                .as_ref()
-                .context("undefined mixer instance")?;
-            let mut wash_url = mixer_instance.clone();
-            wash_url.set_path("wash");
-            let resp = http_client
-                .get(wash_url)
-                .query(&[("url", url.to_string())])
-                .send()
-                .await
-                .context("send mixer requewst")?;
-            if !resp.status().is_success() {
-                return Err(anyhow!("Invalid mixer response status: {}", resp.status()));
-            }
-            Url::parse(&resp.text().await.context("read mixer response url")?)
-                .context("parse mixer response url")
-                .map(Ok)
+    fn matching_rules_in<'a>(&self, rules: &'a [DirtyUrlRule], url: &Url) -> Vec<&'a DirtyUrlRule> {
+        let mut matching = rules.iter().filter(|rule| rule.matches(url));
+        let matching: Vec<_> = if self.config.apply_all_matching_rules {
+            matching.collect()
+        } else {
+            matching.next().into_iter().collect()
+        };
+        if matching.is_empty() && self.unrolls_as_shortener(url) {
+            return vec![shorteners::rule()];
         }
+        matching
     }
-}
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct UrlWasherConfig {
-    pub mixer_instance: Option<Url>,
-    pub redirect_policy: HashMap<RuleName, RedirectWashPolicy>,
-}
+    /// Links of shorteners without rules of their own are unrolled by [`shorteners::rule`].
+    fn unrolls_as_shortener(&self, url: &Url) -> bool {
+        self.config.shorteners.enabled && self.config.shorteners.is_short_link(url)
+    }
 
-impl Default for UrlWasherConfig {
-    fn default() -> Self {
-        Self {
-            mixer_instance: Default::default(),
-            redirect_policy: HashMap::from_iter(
-                rule_set()
+    /// First rule that washes `url`.
+    pub fn matching_rule(&self, url: &Url) -> Option<DirtyUrlRule> {
+        self.rules()
+            .iter()
+            .find(|rule| rule.matches(url))
+            .or_else(|| self.unrolls_as_shortener(url).then(shorteners::rule))
+            .cloned()
+    }
+
+    /// Cached wash results (dirty url, washed url), most recently used first in each cache shard.
+    pub fn cache_entries(&self) -> Vec<(Url, Url)> {
+        let mut entries = Vec::new();
+        self.cache.for_each_shard(|shard| {
+            entries.extend(
+                shard
                     .iter()
-                    .filter(|rule| {
-                        rule.washing_programs
-                            .contains(&WashingProgram::ResolveRedirection)
-                    })
-                    .flat_map(|rule| {
-                        rule.domains
-                            .iter()
-                            .map(|domain| (domain.to_owned(), RedirectWashPolicy::Locally))
-                    }),
-            ),
+                    .map(|(dirty, cached)| (dirty.clone(), cached.washed.clone())),
+            )
+        });
+        entries
+    }
+
+    /// Forgets washed version of `url`, e.g. when shortener changed its target.
+    /// Returns removed washed url.
+    pub fn cache_remove(&self, url: &Url) -> Option<Url> {
+        if let Some(store) = &self.redirect_store {
+            store.remove(url);
         }
+        self.cache.shard(url).pop(url).map(|cached| cached.washed)
     }
-}
 
-#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
-pub enum RedirectWashPolicy {
-    /// Do not resolve redirection.
-    Ignore,
-    /// Resolve redirection locally.
-    ///
-    /// Exposes your IP address that can be corellated with you.
-    Locally,
-    /// Resolve redirection using urldebloater-mixer.
+    pub fn cache_clear(&self) {
+        if let Some(store) = &self.redirect_store {
+            store.clear();
+        }
+        self.cache.for_each_shard(|shard| shard.clear());
+        if let Some(unmatched_cache) = &self.unmatched_cache {
+            unmatched_cache.for_each_shard(|shard| shard.clear());
+        }
+    }
+
+    /// Evicts expired entries, returns how many were removed.
     ///
-    /// Exposes link to person who is running mixer instance you set
-    /// (not so scary for tiktoks tho).
-    ViaMixer,
-}
+    /// Expired entries are never served anyway, but without sweeping they stay in memory
+    /// until pushed out by newer ones. Call it periodically in long running processes.
+    pub fn cache_sweep(&self) -> usize {
+        if self.config.cache_ttl_secs.is_none() {
+            return 0;
+        }
+        let now = Instant::now();
+        let mut removed = 0;
+        self.cache.for_each_shard(|shard| {
+            let expired = shard
+                .iter()
+                .filter(|(_, cached)| cached.is_expired(now))
+                .map(|(dirty, _)| dirty.clone())
+                .collect::<Vec<_>>();
+            for dirty in &expired {
+                shard.pop(dirty);
+            }
+            removed += expired.len();
+        });
+        removed
+    }
 
-impl Display for RedirectWashPolicy {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(match self {
-            RedirectWashPolicy::Ignore => "ignore",
-            RedirectWashPolicy::Locally => "locally",
-            RedirectWashPolicy::ViaMixer => "via mixer",
-        })
+    /// Fetches announcements of configured mixer instance.
+    #[cfg(feature = "network")]
+    pub async fn mixer_motd(&self) -> anyhow::Result<Option<mixer_api::MixerMotd>> {
+        match self.config.mixer_client(&self.http_client) {
+            Some(client) => Ok(Some(client.motd().await.context("fetch mixer motd")?)),
+            None => Ok(None),
+        }
     }
-}
 
-#[derive(Default)]
-#[non_exhaustive]
-pub struct DirtyUrlRule {
-    pub name: String,
-    pub domains: Vec<String>,
-    pub path_pattern: Vec<Option<String>>,
-    pub washing_programs: Vec<WashingProgram>,
-}
+    /// Fetches signed directory of public mixer instances.
+    #[cfg(feature = "network")]
+    pub async fn mixer_directory(
+        &self,
+        directory_url: &Url,
+    ) -> anyhow::Result<mixer_api::SignedDirectory> {
+        mixer_api::fetch_directory(&self.http_client, directory_url).await
+    }
 
-impl DirtyUrlRule {
-    pub fn matches_domain(&self, domain: &str) -> bool {
-        self.domains
-            .iter()
-            .any(|dirty_domain| dirty_domain == domain)
+    /// See [`mixer_api::ping`].
+    #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+    pub async fn ping_mixer(
+        &self,
+        mixer_instance: &Url,
+    ) -> anyhow::Result<(mixer_api::MixerVersion, Duration)> {
+        mixer_api::ping(&self.http_client, mixer_instance).await
     }
 
-    pub fn matches_path(&self, url: &Url) -> bool {
-        if self.path_pattern.is_empty() {
-            return true;
+    /// Washes `url`, returning [`None`] if there was nothing to wash.
+    ///
+    /// Runs in `wash` span (with domain of `url`, never whole url), with `rule` span per matching rule
+    /// and `program` span per washing program in it. Redirects are resolved in `resolve_redirect` span,
+    /// recording number of attempts, outcome and latency.
+    pub async fn wash(&self, url: &Url) -> anyhow::Result<Option<Url>> {
+        self.wash_observed(url, &mut WashTrace::default()).await
+    }
+
+    /// Washes `url` in `wash` span, notifying observer if it was changed.
+    async fn wash_observed(&self, url: &Url, trace: &mut WashTrace) -> anyhow::Result<Option<Url>> {
+        let span = debug_span!(
+            "wash",
+            domain = url.host_str(),
+            outcome = field::Empty,
+            cached = field::Empty
+        );
+        let started_at = now();
+        let washed = self
+            .wash_within(url, self.config.max_redirect_hops, false, trace)
+            .instrument(span.clone())
+            .await
+            .map(|washed| self.canonicalize(url, washed));
+        measure::wash(&washed, started_at);
+        span.record("outcome", measure::wash_outcome(&washed));
+        span.record("cached", trace.cached);
+        let washed = washed?;
+        if let Some(washed) = washed.as_ref().filter(|washed| *washed != url) {
+            if trace.cached && self.observer.is_some() {
+                self.fill_cached_rule(url, trace);
+            }
+            let event = WashEvent {
+                original: url,
+                washed,
+                rule: trace.rule.as_deref(),
+                duration: started_at.map(|started_at| started_at.elapsed()),
+                cached: trace.cached,
+            };
+            debug!(
+                target: "urlwasher::washed",
+                original = %event.original,
+                washed = %event.washed,
+                rule = event.rule,
+                duration_us = event.duration.map(|duration| duration.as_micros() as u64),
+                cached = event.cached,
+                "Washed url."
+            );
+            if let Some(observer) = &self.observer {
+                observer.on_washed(&event);
+            }
         }
-        let segments = match url.path_segments() {
-            Some(segments) => segments,
-            None => return false,
-        };
-        segments
-            .zip(&self.path_pattern)
-            .all(|(actual, template)| match template {
-                Some(template) => actual == template,
-                None => true,
-            })
+        Ok(washed)
     }
-}
 
-#[derive(PartialEq, Eq)]
-pub enum WashingProgram {
-    ResolveRedirection,
-    RemoveSomeParams(Vec<String>),
-    RemoveAllParams,
-}
+    /// Rule is not known when result is served from cache, so it is looked up again.
+    fn fill_cached_rule(&self, url: &Url, trace: &mut WashTrace) {
+        if trace.rule.is_none() {
+            trace.rule = self.matching_rule(url).map(|rule| rule.name).or_else(|| {
+                self.config
+                    .strip_universal_trackers
+                    .then(|| universal_trackers_rule().name.clone())
+            });
+        }
+    }
 
-impl WashingProgram {
-    pub fn remove_some_params(values: &[&str]) -> Self {
-        Self::RemoveSomeParams(values.iter().map(|s| String::from(*s)).collect())
+    /// Sorts params of washed url (or `url` itself if there was nothing to wash)
+    /// if [`UrlWasherConfig::sort_query_params`] is enabled.
+    fn canonicalize(&self, url: &Url, washed: Option<Url>) -> Option<Url> {
+        if !self.config.sort_query_params
+            || !url
+                .host_str()
+                .is_some_and(|host| self.config.washes_domain(host))
+        {
+            return washed;
+        }
+        let sorted = sort_query_params(washed.as_ref().unwrap_or(url));
+        (washed.is_some() || sorted != *url).then_some(sorted)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use url::Url;
+    /// Washes `url` like [`UrlWasher::wash`], describing what was changed and why.
+    /// Programs are not known when result is served from cache, see [`WashReport::cached`].
+    pub async fn wash_with_report(&self, url: &Url) -> anyhow::Result<WashReport> {
+        let mut trace = WashTrace::default();
+        let washed = self.wash_observed(url, &mut trace).await?;
+        if trace.cached {
+            self.fill_cached_rule(url, &mut trace);
+        }
+        let possible_trackers = match &washed {
+            Some(_) => Vec::new(),
+            None => self
+                .possible_trackers(url)
+                .into_iter()
+                .map(|tracker| tracker.param)
+                .collect(),
+        };
+        let cleaned = washed.unwrap_or_else(|| url.clone());
+        Ok(WashReport {
+            file: None,
+            original: url.to_string(),
+            cleaned: cleaned.to_string(),
+            rule: trace.rule,
+            removed_params: removed_params(url, &cleaned),
+            resolution_hops: trace.resolution_hops,
+            programs: trace.programs,
+            cached: trace.cached,
+            possible_trackers,
+        })
+    }
 
-    use crate::{UrlWasher, UrlWasherConfig};
+    /// Washes `url` following at most `redirect_hops` redirects.
+    /// Only washes that are not `nested` in other ones wait for concurrent wash of the same short link,
+    /// otherwise redirect loop would wait for itself.
+    async fn wash_within(
+        &self,
+        url: &Url,
+        redirect_hops: u8,
+        nested: bool,
+        trace: &mut WashTrace,
+    ) -> anyhow::Result<Option<Url>> {
+        // parser lowercases scheme, so `HTTPS://` links are washed (and normalized) too
+        if url.scheme() != "http" && url.scheme() != "https" {
+            if !self.config.washes_scheme(url.scheme()) {
+                return Ok(None);
+            }
+            return self.wash_embedded(url, redirect_hops, nested, trace).await;
+        }
+        if !url
+            .host_str()
+            .is_some_and(|host| self.config.washes_domain(host))
+        {
+            debug!("Not washing {url}, its domain is skipped.");
+            return Ok(None);
+        }
+        if self.config.wash_nested_urls {
+            return self.wash_embedded(url, redirect_hops, nested, trace).await;
+        }
+        self.wash_by_rules(url, redirect_hops, nested, trace).await
+    }
+
+    /// Washes http urls embedded in params of `url` (deep link with [`UrlWasherConfig::additional_schemes`],
+    /// or any url with [`UrlWasherConfig::wash_nested_urls`]), then `url` itself by rules matching it.
+    async fn wash_embedded(
+        &self,
+        url: &Url,
+        redirect_hops: u8,
+        nested: bool,
+        trace: &mut WashTrace,
+    ) -> anyhow::Result<Option<Url>> {
+        let mut params = url.query_pairs().into_owned().collect::<Vec<_>>();
+        let mut embedded_washed = false;
+        for (_, value) in &mut params {
+            let Some(embedded) = Url::parse(value)
+                .ok()
+                .filter(|embedded| matches!(embedded.scheme(), "http" | "https"))
+            else {
+                continue;
+            };
+            if let Some(washed) =
+                Box::pin(self.wash_within(&embedded, redirect_hops, true, trace)).await?
+            {
+                *value = washed.to_string();
+                embedded_washed = true;
+            }
+        }
+        let deep_link = embedded_washed.then(|| {
+            let mut deep_link = url.clone();
+            deep_link.query_pairs_mut().clear().extend_pairs(&params);
+            deep_link
+        });
+        let washed = self
+            .wash_by_rules(
+                deep_link.as_ref().unwrap_or(url),
+                redirect_hops,
+                nested,
+                trace,
+            )
+            .await?;
+        Ok(washed.or(deep_link))
+    }
+
+    /// Washes `url` by its matching rules, or universal trackers rule if there are none.
+    async fn wash_by_rules(
+        &self,
+        url: &Url,
+        redirect_hops: u8,
+        nested: bool,
+        trace: &mut WashTrace,
+    ) -> anyhow::Result<Option<Url>> {
+        if let Some(washed) = self.cached(url) {
+            debug!("Serving washed url {} from cache.", url.to_string());
+            measure::cache_hit();
+            trace.cached = true;
+            return Ok(Some(washed));
+        }
+        if let Some(unmatched_cache) = &self.unmatched_cache {
+            if unmatched_cache.shard(url).get(url).is_some() {
+                return Ok(None);
+            }
+        }
+        let rules = self.rules();
+        let matching_rules = self.matching_rules_in(&rules, url);
+        let Some(first_rule) = matching_rules.first() else {
+            if self.config.strip_universal_trackers {
+                let rule = universal_trackers_rule();
+                let washed =
+                    rule.washing_programs
+                        .iter()
+                        .fold(url.to_owned(), |laundry, program| match program {
+                            WashingProgram::RemoveParamsByPrefix(prefixes) => {
+                                remove_params_by_prefix(&laundry, prefixes)
+                            }
+                            WashingProgram::RemoveSomeParams(params) => {
+                                remove_query_params(&laundry, params)
+                            }
+                            _ => unreachable!("universal trackers rule only removes params"),
+                        });
+                // urls left unchanged are remembered as unmatched, result would be the same next time
+                if washed != *url {
+                    trace.rule.get_or_insert_with(|| rule.name.clone());
+                    trace.programs.extend(
+                        rule.washing_programs
+                            .iter()
+                            .map(|program| program.name().to_string()),
+                    );
+                    self.cache_put(url, &washed);
+                    return Ok(Some(washed));
+                }
+            }
+            if let Some(unmatched_cache) = &self.unmatched_cache {
+                unmatched_cache.shard(url).put(url.to_owned(), ());
+            }
+            return Ok(None);
+        };
+        // concurrent washes of short link resolve it once, others are served leader's cached result
+        let _leader = if !nested
+            && matching_rules.iter().any(|rule| {
+                rule.washing_programs
+                    .contains(&WashingProgram::ResolveRedirection)
+            }) {
+            match self.in_flight.join(url) {
+                Joined::Leader(leader) => Some(leader),
+                Joined::Follower(done) => {
+                    let _ = done.await;
+                    return Box::pin(self.wash_by_rules(url, redirect_hops, false, trace)).await;
+                }
+            }
+        } else {
+            None
+        };
+        if trace.rule.is_none() {
+            trace.rule = Some(first_rule.name.clone());
+        }
+        let mut laundry = url.to_owned();
+        let mut redirect_resolved = false;
+        for matching_rule in matching_rules {
+            let rule_span = debug_span!("rule", rule = %matching_rule.name);
+            for washing_program in matching_rule.washing_programs.iter() {
+                trace.programs.push(washing_program.name().to_string());
+                let program_span =
+                    debug_span!(parent: &rule_span, "program", program = washing_program.name());
+                let step = self
+                    .run_program(laundry, washing_program, matching_rule, redirect_hops)
+                    .instrument(program_span)
+                    .await?;
+                laundry = match step {
+                    ProgramStep::Washed(laundry) => laundry,
+                    ProgramStep::Resolved {
+                        short_link,
+                        destination,
+                    } => {
+                        trace.resolved_links.push(short_link);
+                        if trace.resolved_links.contains(&destination) {
+                            let url = trace.resolved_links.pop().unwrap();
+                            return Err(RedirectLoop {
+                                url,
+                                repeated: destination,
+                                hops: trace.resolution_hops + 1,
+                            }
+                            .into());
+                        }
+                        redirect_resolved = true;
+                        trace.resolution_hops += 1;
+                        destination
+                    }
+                    ProgramStep::Extracted(destination) => {
+                        return self
+                            .wash_extracted(url, destination, redirect_hops, trace)
+                            .await
+                    }
+                };
+            }
+            // destination is washed by its own rules
+            if redirect_resolved {
+                break;
+            }
+        }
+        if let Some(domain) = laundry
+            .domain()
+            .filter(|_| redirect_resolved)
+            .and_then(|domain| self.blocklist.matching(domain))
+        {
+            return Err(BlockedDestination {
+                url: laundry,
+                domain,
+                action: self.config.blocklist.action,
+            }
+            .into());
+        }
+        if redirect_resolved {
+            // destination may be another short link, or carry tracking of its own
+            return self
+                .wash_extracted(url, laundry, redirect_hops - 1, trace)
+                .await;
+        }
+        self.cache_put(url, &laundry);
+        Ok(Some(laundry))
+    }
+
+    /// Runs `program` of `rule` on `laundry`, redirect is resolved only if `redirect_hops` are left.
+    async fn run_program(
+        &self,
+        mut laundry: Url,
+        program: &WashingProgram,
+        rule: &DirtyUrlRule,
+        redirect_hops: u8,
+    ) -> anyhow::Result<ProgramStep> {
+        Ok(ProgramStep::Washed(match program {
+            WashingProgram::ResolveRedirection if redirect_hops == 0 => {
+                debug!("Not resolving redirection of {laundry}, hop limit reached.");
+                laundry
+            }
+            WashingProgram::ResolveRedirection => {
+                let policy = self
+                    .config
+                    .redirect_policy
+                    .get(&rule.name)
+                    .unwrap_or(&RedirectWashPolicy::Ignore);
+                let short_link = laundry.clone();
+                #[cfg(any(test, feature = "test-fixtures"))]
+                let resolved = match &self.redirect_fixtures {
+                    Some(fixtures) if *policy != RedirectWashPolicy::Ignore => {
+                        match fixtures.mode() {
+                            FixtureMode::Replay => fixtures.location(&laundry).map(Ok),
+                            FixtureMode::Record => {
+                                let short_url = laundry.clone();
+                                let resolved = self.resolve_redirect(laundry, policy).await;
+                                if let Ok(Ok(location)) = &resolved {
+                                    fixtures.insert(short_url, location.clone());
+                                }
+                                resolved
+                            }
+                        }
+                    }
+                    _ => self.resolve_redirect_stored(laundry, policy).await,
+                };
+                #[cfg(not(any(test, feature = "test-fixtures")))]
+                let resolved = self.resolve_redirect_stored(laundry, policy).await;
+                return Ok(match resolved? {
+                    Ok(destination) => {
+                        self.check_redirect_site(rule, &short_link, &destination)?;
+                        ProgramStep::Resolved {
+                            short_link,
+                            destination,
+                        }
+                    }
+                    Err(laundry) => ProgramStep::Washed(laundry),
+                });
+            }
+            WashingProgram::RemoveSomeParams(params) => remove_query_params(&laundry, params),
+            WashingProgram::RemoveParamsByPrefix(prefixes) => {
+                remove_params_by_prefix(&laundry, prefixes)
+            }
+            WashingProgram::KeepOnlyParams(params) => keep_only_params(&laundry, params),
+            WashingProgram::RemoveAllParams => {
+                laundry.set_query(None);
+                laundry
+            }
+            WashingProgram::SortParams => sort_query_params(&laundry),
+            WashingProgram::NormalizePath(trailing_slash) => {
+                normalize_path(&laundry, *trailing_slash)
+            }
+            WashingProgram::RewritePath(rewrite) => rewrite_path(&laundry, rewrite),
+            WashingProgram::SetSubdomain(subdomain) => set_subdomain(&laundry, subdomain),
+            WashingProgram::StripPathSegments(patterns) => strip_path_segments(&laundry, patterns),
+            WashingProgram::RedactParamValues(params) => redact_query_params(&laundry, params),
+            WashingProgram::RemoveMatchingParams(patterns) => {
+                remove_matching_params(&laundry, patterns)
+            }
+            WashingProgram::RemoveReferralMarketing(_) if self.config.keep_referral_marketing => {
+                laundry
+            }
+            WashingProgram::RemoveReferralMarketing(patterns) => {
+                remove_matching_params(&laundry, patterns)
+            }
+            WashingProgram::RemoveFragment(pattern) => remove_fragment(&laundry, pattern.as_ref()),
+            WashingProgram::RemoveRawMatches(patterns) => remove_raw_matches(&laundry, patterns),
+            WashingProgram::ExtractRedirection(patterns) => {
+                match extract_redirection(&laundry, patterns) {
+                    Some(destination) => return Ok(ProgramStep::Extracted(destination)),
+                    None => laundry,
+                }
+            }
+            WashingProgram::UnwrapUrlDefense => match url_defense::decode(&laundry) {
+                Some(destination) => return Ok(ProgramStep::Extracted(destination)),
+                None => laundry,
+            },
+            WashingProgram::ExtractEmbeddedUrl(params) => {
+                match extract_embedded_url(&laundry, params) {
+                    Some(destination) => return Ok(ProgramStep::Extracted(destination)),
+                    None => laundry,
+                }
+            }
+            WashingProgram::ExtractBase64Url(params) => {
+                match extract_base64_url(&laundry, params) {
+                    Some(destination) => return Ok(ProgramStep::Extracted(destination)),
+                    None => laundry,
+                }
+            }
+            WashingProgram::UseFrontend(service) => {
+                match self.config.privacy_frontends.get(service) {
+                    Some(instance) => {
+                        frontends::rewrite(*service, &laundry, instance).unwrap_or(laundry)
+                    }
+                    None => laundry,
+                }
+            }
+        }))
+    }
+
+    /// Fails if [`UrlWasherConfig::same_site_redirects`] is enabled and `destination` is neither
+    /// on registrable domain of `short_link` nor on [`DirtyUrlRule::redirect_sites`] of its `rule`.
+    fn check_redirect_site(
+        &self,
+        rule: &DirtyUrlRule,
+        short_link: &Url,
+        destination: &Url,
+    ) -> anyhow::Result<()> {
+        if !self.config.same_site_redirects || same_site(short_link, destination) {
+            return Ok(());
+        }
+        if destination.domain().is_some_and(|domain| {
+            rule.redirect_sites
+                .iter()
+                .any(|site| public_suffix::matches_site(site, domain))
+        }) {
+            return Ok(());
+        }
+        Err(OffSiteRedirect {
+            url: short_link.clone(),
+            destination: destination.clone(),
+        }
+        .into())
+    }
+
+    /// Continues washing with destination extracted from wrapper `url`, using destination's own rule.
+    /// Recursion ends, as extracted destination is always shorter than its wrapper
+    /// and redirects are followed at most `redirect_hops` more times.
+    async fn wash_extracted(
+        &self,
+        url: &Url,
+        destination: Url,
+        redirect_hops: u8,
+        trace: &mut WashTrace,
+    ) -> anyhow::Result<Option<Url>> {
+        let washed = Box::pin(self.wash_within(&destination, redirect_hops, true, trace))
+            .await?
+            .unwrap_or(destination);
+        self.cache_put(url, &washed);
+        Ok(Some(washed))
+    }
+
+    fn cache_put(&self, url: &Url, washed: &Url) {
+        // Instant is unavailable in browsers, so it is read only if ttl is configured.
+        let expires_at = self
+            .config
+            .cache_ttl_secs
+            .map(|ttl| Instant::now() + Duration::from_secs(ttl));
+        self.cache.shard(url).put(
+            url.to_owned(),
+            CachedUrl {
+                washed: washed.clone(),
+                expires_at,
+            },
+        );
+    }
+
+    async fn resolve_redirect_stored(
+        &self,
+        url: Url,
+        policy: &RedirectWashPolicy,
+    ) -> anyhow::Result<Result<Url, Url>> {
+        let store = match &self.redirect_store {
+            Some(store) if *policy != RedirectWashPolicy::Ignore => store,
+            _ => return self.resolve_redirect(url, policy).await,
+        };
+        if let Some(location) = store.location(&url) {
+            debug!("Serving redirect of {url} from store.");
+            return Ok(Ok(location));
+        }
+        let short_url = url.clone();
+        let resolved = self.resolve_redirect(url, policy).await;
+        if let Ok(Ok(location)) = &resolved {
+            store.insert(short_url, location.clone());
+        }
+        resolved
+    }
+
+    /// Resolves redirect of `url`, falling back to second strategy of fallback policy if the first one fails.
+    async fn resolve_redirect(
+        &self,
+        url: Url,
+        policy: &RedirectWashPolicy,
+    ) -> anyhow::Result<Result<Url, Url>> {
+        let Some((first, fallback)) = policy.fallback() else {
+            return self.resolve_redirect_by(url, policy).await;
+        };
+        match self.resolve_redirect_by(url.clone(), &first).await {
+            Err(err) => {
+                debug!("Resolving redirect {first} failed, trying {fallback}: {err:#}");
+                self.resolve_redirect_by(url, &fallback).await
+            }
+            resolved => resolved,
+        }
+    }
+
+    async fn resolve_redirect_by(
+        &self,
+        url: Url,
+        policy: &RedirectWashPolicy,
+    ) -> anyhow::Result<Result<Url, Url>> {
+        let http_client = self.redirect_client(policy)?;
+        let mut retries = self.config.redirect_retries;
+        let mut backoff = Duration::from_millis(self.config.redirect_retry_backoff_ms);
+        let span = debug_span!(
+            "resolve_redirect",
+            domain = url.host_str(),
+            %policy,
+            attempts = field::Empty,
+            outcome = field::Empty,
+            latency_ms = field::Empty
+        );
+        let started_at = now();
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let resolved = {
+                #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+                let _permit = self
+                    .redirect_throttle
+                    .acquire(&url, policy)
+                    .instrument(span.clone())
+                    .await;
+                resolve_redirect(http_client, url.clone(), policy, &self.config)
+                    .instrument(span.clone())
+                    .await
+            };
+            match resolved {
+                Err(err) if retries > 0 && is_transient(&err) => {
+                    span.in_scope(|| debug!("Retrying redirect of {url} in {backoff:?}: {err:#}"));
+                    retries -= 1;
+                    sleep(backoff).await;
+                    backoff *= 2;
+                }
+                resolved => {
+                    measure::redirect_resolution(policy, &resolved, started_at);
+                    span.record("attempts", attempts);
+                    span.record("outcome", measure::redirect_outcome(&resolved));
+                    if let Some(started_at) = started_at {
+                        span.record("latency_ms", started_at.elapsed().as_millis() as u64);
+                    }
+                    return resolved;
+                }
+            }
+        }
+    }
+
+    /// Client routed through [`UrlWasherConfig::tor_proxy`] for [`RedirectWashPolicy::ViaTor`],
+    /// built on first use, so it costs nothing to washers that never use tor.
+    #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+    fn redirect_client(&self, policy: &RedirectWashPolicy) -> anyhow::Result<&HttpClient> {
+        if *policy != RedirectWashPolicy::ViaTor {
+            return Ok(&self.http_client);
+        }
+        if let Some(tor_client) = self.tor_client.get() {
+            return Ok(tor_client);
+        }
+        let tor_client = http_client_builder()
+            .proxy(reqwest::Proxy::all(self.config.tor_proxy.as_str()).context("tor proxy")?)
+            .build()
+            .context("build tor client")?;
+        Ok(self.tor_client.get_or_init(|| tor_client))
+    }
+
+    #[cfg(not(all(feature = "network", not(target_arch = "wasm32"))))]
+    fn redirect_client(&self, _policy: &RedirectWashPolicy) -> anyhow::Result<&HttpClient> {
+        Ok(&self.http_client)
+    }
+
+    fn cached(&self, url: &Url) -> Option<Url> {
+        let mut cache = self.cache.shard(url);
+        let cached = cache.get(url)?;
+        if self.config.cache_ttl_secs.is_some() && cached.is_expired(Instant::now()) {
+            cache.pop(url);
+            return None;
+        }
+        Some(cached.washed.clone())
+    }
+}
+
+/// Names of query params present in `original` and missing in `cleaned`, each listed once.
+pub fn removed_params(original: &Url, cleaned: &Url) -> Vec<String> {
+    let mut removed_params = Vec::new();
+    for (key, _) in original.query_pairs() {
+        let kept = cleaned.query_pairs().any(|(clean_key, _)| clean_key == key);
+        if !kept && !removed_params.iter().any(|removed| *removed == key) {
+            removed_params.push(key.into_owned());
+        }
+    }
+    removed_params
+}
+
+/// Removes query params named like any of `params`, remaining params keep their original encoding.
+pub fn remove_query_params(url: &Url, params: &[String]) -> Url {
+    rewrite_query_params(url, ParamAction::Remove, |key| {
+        params.iter().any(|param| param == key)
+    })
+}
+
+/// Removes query params with names starting with any of `prefixes`, e.g. `utm_`.
+pub fn remove_params_by_prefix(url: &Url, prefixes: &[String]) -> Url {
+    rewrite_query_params(url, ParamAction::Remove, |key| {
+        prefixes
+            .iter()
+            .any(|prefix| key.starts_with(prefix.as_str()))
+    })
+}
+
+/// Removes all query params except ones named like any of `params`.
+pub fn keep_only_params(url: &Url, params: &[String]) -> Url {
+    rewrite_query_params(url, ParamAction::Remove, |key| {
+        !params.iter().any(|param| param == key)
+    })
+}
+
+/// Removes query params with names matching any of `patterns`.
+pub fn remove_matching_params(url: &Url, patterns: &[Pattern]) -> Url {
+    rewrite_query_params(url, ParamAction::Remove, |key| {
+        patterns.iter().any(|pattern| pattern.is_match(key))
+    })
+}
+
+/// Sorts query params by name, so the same link shared by different people is byte-identical.
+/// Params with the same name keep their order, all params keep their original encoding.
+pub fn sort_query_params(url: &Url) -> Url {
+    let Some(query) = url.query() else {
+        return url.clone();
+    };
+    let mut pairs = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| (param_key(pair), pair))
+        .collect::<Vec<_>>();
+    pairs.sort_by(|(key, _), (other_key, _)| key.cmp(other_key));
+    let sorted = pairs
+        .into_iter()
+        .map(|(_, pair)| pair)
+        .collect::<Vec<_>>()
+        .join("&");
+    let mut sorted_url = url.clone();
+    sorted_url.set_query((!sorted.is_empty()).then_some(sorted.as_str()));
+    sorted_url
+}
+
+/// Blanks values of query params named like any of `params`, keeping their keys (`ref=abc` becomes `ref=`).
+pub fn redact_query_params(url: &Url, params: &[String]) -> Url {
+    rewrite_query_params(url, ParamAction::Redact, |key| {
+        params.iter().any(|param| param == key)
+    })
+}
+
+/// Removes parts of fragment matching `pattern` (e.g. `:~:text=` directive), or whole fragment
+/// if there is no pattern. Fragment left empty is removed too.
+pub fn remove_fragment(url: &Url, pattern: Option<&Pattern>) -> Url {
+    let mut washed = url.clone();
+    let fragment = match (url.fragment(), pattern) {
+        (Some(fragment), Some(pattern)) => pattern.0.replace_all(fragment, "").into_owned(),
+        _ => String::new(),
+    };
+    washed.set_fragment(Some(fragment.as_str()).filter(|fragment| !fragment.is_empty()));
+    washed
+}
+
+/// Removes parts of whole url matching any of `patterns`, keeps url unchanged if result is not valid url.
+pub fn remove_raw_matches(url: &Url, patterns: &[Pattern]) -> Url {
+    let mut raw = url.to_string();
+    for pattern in patterns {
+        raw = pattern.0.replace_all(&raw, "").into_owned();
+    }
+    Url::parse(&raw).unwrap_or_else(|_| url.clone())
+}
+
+/// Destination url in first present query param named like any of `params`,
+/// e.g. `u` of `https://l.facebook.com/l.php?u={DESTINATION}`. Only http(s) destinations are accepted.
+pub fn extract_embedded_url(url: &Url, params: &[String]) -> Option<Url> {
+    let (_, destination) = url
+        .query_pairs()
+        .find(|(key, _)| params.iter().any(|param| param == key))?;
+    Url::parse(&destination)
+        .ok()
+        .filter(|destination| matches!(destination.scheme(), "http" | "https"))
+}
+
+/// Destination url base64 encoded in first present query param named like any of `params`,
+/// e.g. `dest` of `https://example.com/out?dest=aHR0cHM6Ly9leGFtcGxlLm9yZw`.
+/// Both standard and url-safe alphabets are accepted, padded or not. Only http(s) destinations are accepted.
+pub fn extract_base64_url(url: &Url, params: &[String]) -> Option<Url> {
+    let (_, encoded) = url
+        .query_pairs()
+        .find(|(key, _)| params.iter().any(|param| param == key))?;
+    // unescaped `+` of standard alphabet is decoded to space as part of query
+    let encoded = encoded
+        .trim_end_matches('=')
+        .replace([' ', '-'], "+")
+        .replace('_', "/");
+    let destination = String::from_utf8(STANDARD_NO_PAD.decode(encoded).ok()?).ok()?;
+    Url::parse(destination.trim())
+        .ok()
+        .filter(|destination| matches!(destination.scheme(), "http" | "https"))
+}
+
+/// Destination of redirect wrapper (e.g. `https://www.google.com/url?q={DESTINATION}`), captured by
+/// first group of first matching pattern and percent-decoded.
+pub fn extract_redirection(url: &Url, patterns: &[Pattern]) -> Option<Url> {
+    let captured = patterns
+        .iter()
+        .find_map(|pattern| pattern.0.captures(url.as_str())?.get(1))?;
+    let destination = percent_encoding::percent_decode_str(captured.as_str()).decode_utf8_lossy();
+    Url::parse(&destination)
+        .ok()
+        .filter(|destination| matches!(destination.scheme(), "http" | "https"))
+}
+
+/// Removes path segments matching any of `patterns` and `;key=value` matrix params
+/// of segments matching them, e.g. `/dp/B0/ref=sr_1` or `/page;jsessionid=1`.
+pub fn strip_path_segments(url: &Url, patterns: &[Pattern]) -> Url {
+    let Some(segments) = url.path_segments() else {
+        return url.clone();
+    };
+    let is_matched = |part: &str| patterns.iter().any(|pattern| pattern.is_match(part));
+    let mut stripped_path = String::with_capacity(url.path().len());
+    for segment in segments {
+        let mut parts = segment.split(';');
+        let base = parts.next().unwrap_or_default();
+        if !base.is_empty() && is_matched(base) {
+            continue;
+        }
+        stripped_path.push('/');
+        stripped_path.push_str(base);
+        for matrix_param in parts.filter(|matrix_param| !is_matched(matrix_param)) {
+            stripped_path.push(';');
+            stripped_path.push_str(matrix_param);
+        }
+    }
+    let mut stripped_url = url.clone();
+    stripped_url.set_path(&stripped_path);
+    stripped_url
+}
+
+/// Replaces part of path matching `rewrite.pattern` with `rewrite.replacement`,
+/// which may refer to capture groups of pattern (`$1`). Url is left as it is if pattern does not match.
+pub fn rewrite_path(url: &Url, rewrite: &PathRewrite) -> Url {
+    let path = rewrite
+        .pattern
+        .0
+        .replace(url.path(), rewrite.replacement.as_str());
+    let mut rewritten_url = url.clone();
+    rewritten_url.set_path(&path);
+    rewritten_url
+}
+
+/// Replaces subdomains of url with `subdomain`, or strips them if it is empty,
+/// keeping registrable domain (`amazon.co.uk` of `smile.amazon.co.uk`). Urls without domain are left as they are.
+pub fn set_subdomain(url: &Url, subdomain: &str) -> Url {
+    let Some(site) = url.domain().and_then(public_suffix::registrable_domain) else {
+        return url.clone();
+    };
+    let host = if subdomain.is_empty() {
+        site.to_string()
+    } else {
+        format!("{subdomain}.{site}")
+    };
+    let mut url_with_subdomain = url.clone();
+    match url_with_subdomain.set_host(Some(&host)) {
+        Ok(()) => url_with_subdomain,
+        Err(_) => url.clone(),
+    }
+}
+
+/// Collapses repeated slashes in path (`/a//b` becomes `/a/b`) and treats slash at its end
+/// according to `trailing_slash`. Root path `/` is left as it is.
+pub fn normalize_path(url: &Url, trailing_slash: TrailingSlash) -> Url {
+    let Some(segments) = url.path_segments() else {
+        return url.clone();
+    };
+    let segments = segments
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let Some(last) = segments.last() else {
+        let mut normalized_url = url.clone();
+        normalized_url.set_path("/");
+        return normalized_url;
+    };
+    let slash_end = match trailing_slash {
+        TrailingSlash::Keep => url.path().ends_with('/'),
+        TrailingSlash::Strip => false,
+        // `/report.pdf/` is different file than `/report.pdf`
+        TrailingSlash::Append => !last.contains('.'),
+    };
+    let mut normalized_path = String::with_capacity(url.path().len() + 1);
+    for segment in &segments {
+        normalized_path.push('/');
+        normalized_path.push_str(segment);
+    }
+    if slash_end {
+        normalized_path.push('/');
+    }
+    let mut normalized_url = url.clone();
+    normalized_url.set_path(&normalized_path);
+    normalized_url
+}
+
+/// Whether urls have the same host, or hosts with the same registrable domain.
+fn same_site(url: &Url, other: &Url) -> bool {
+    if url.host_str() == other.host_str() {
+        return true;
+    }
+    let site = url.domain().and_then(public_suffix::registrable_domain);
+    site.is_some() && other.domain().and_then(public_suffix::registrable_domain) == site
+}
+
+#[derive(Clone, Copy)]
+enum ParamAction {
+    Remove,
+    Redact,
+}
+
+/// Decoded name of `name=value` query pair, borrowed if it is not encoded.
+fn param_key(pair: &str) -> Cow<'_, str> {
+    let raw_key = pair.split_once('=').map_or(pair, |(key, _)| key);
+    if !raw_key.contains(['%', '+']) {
+        return Cow::Borrowed(raw_key);
+    }
+    form_urlencoded::parse(raw_key.as_bytes())
+        .next()
+        .map(|(key, _)| key)
+        .unwrap_or_default()
+}
+
+fn rewrite_query_params(url: &Url, action: ParamAction, is_matched: impl Fn(&str) -> bool) -> Url {
+    let Some(query) = url.query() else {
+        return url.clone();
+    };
+    let mut debloated_query = String::with_capacity(query.len());
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let raw_key = pair.split_once('=').map_or(pair, |(key, _)| key);
+        let is_matched = is_matched(&param_key(pair));
+        let kept = match (is_matched, action) {
+            (false, _) => pair,
+            (true, ParamAction::Remove) => continue,
+            (true, ParamAction::Redact) => raw_key,
+        };
+        if !debloated_query.is_empty() {
+            debloated_query.push('&');
+        }
+        debloated_query.push_str(kept);
+        if is_matched {
+            debloated_query.push('=');
+        }
+    }
+    let mut debloated_url = url.clone();
+    debloated_url.set_query((!debloated_query.is_empty()).then_some(debloated_query.as_str()));
+    debloated_url
+}
+
+/// Timeouts and connection errors, worth retrying unlike e.g. missing `Location` header.
+#[cfg(feature = "network")]
+fn is_transient(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|err| err.is_timeout() || err.is_connect())
+    })
+}
+
+#[cfg(not(feature = "network"))]
+fn is_transient(_err: &anyhow::Error) -> bool {
+    false
+}
+
+#[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> Option<Instant> {
+    Some(Instant::now())
+}
+
+/// `Instant::now` panics in browsers.
+#[cfg(target_arch = "wasm32")]
+fn now() -> Option<Instant> {
+    None
+}
+
+/// Browsers have no timer usable without js bindings, so retries are not delayed there.
+#[cfg(not(all(feature = "network", not(target_arch = "wasm32"))))]
+async fn sleep(_duration: Duration) {}
+
+/// Short links are left as they are, there is no way to resolve them.
+#[cfg(not(feature = "network"))]
+async fn resolve_redirect(
+    _http_client: &HttpClient,
+    url: Url,
+    _policy: &RedirectWashPolicy,
+    _config: &UrlWasherConfig,
+) -> anyhow::Result<Result<Url, Url>> {
+    Ok(Err(url))
+}
+
+#[cfg(feature = "network")]
+async fn resolve_redirect(
+    http_client: &reqwest::Client,
+    url: Url,
+    policy: &RedirectWashPolicy,
+    config: &UrlWasherConfig,
+) -> anyhow::Result<Result<Url, Url>> {
+    match policy {
+        RedirectWashPolicy::Ignore => Ok(Err(url)),
+        #[cfg(target_arch = "wasm32")]
+        RedirectWashPolicy::Locally | RedirectWashPolicy::ViaTor => Err(anyhow!(
+            "resolving redirection locally is not supported in wasm, use mixer instead"
+        )),
+        // tor differs only in client, see `UrlWasher::redirect_client`
+        #[cfg(not(target_arch = "wasm32"))]
+        RedirectWashPolicy::Locally | RedirectWashPolicy::ViaTor => {
+            let mut request = match config.redirect_request_method {
+                RedirectRequestMethod::Get => http_client.get(url.clone()),
+                RedirectRequestMethod::Head => http_client.head(url.clone()),
+            };
+            if let Some(timeout) = config.redirect_timeout_ms {
+                request = request.timeout(Duration::from_millis(timeout));
+            }
+            if let Some(profile) = url.domain().and_then(|domain| config.http_profile(domain)) {
+                request = profile.apply(request);
+            }
+            let resp = request.send().await?;
+            if let Some(location) = resp.headers().get("location") {
+                let location = location.to_str().context("invalid location header")?;
+                return Url::parse(location).context("parse location url").map(Ok);
+            }
+            // some shorteners redirect with page instead
+            if !resp.status().is_success()
+                || config.redirect_request_method == RedirectRequestMethod::Head
+            {
+                return Err(anyhow!("missing location header"));
+            }
+            let page = html_redirect::read_page(resp).await?;
+            html_redirect::meta_refresh(&url, &page)
+                .or_else(|| {
+                    config
+                        .detect_script_redirects
+                        .then(|| html_redirect::script_redirect(&url, &page))
+                        .flatten()
+                })
+                .context("missing location header and redirect in page")
+                .map(Ok)
+        }
+        RedirectWashPolicy::ViaMixer => {
+            let client = config
+                .mixer_client(http_client)
+                .context("undefined mixer instance")?;
+            #[cfg(not(target_arch = "wasm32"))]
+            let client = client.timeout(config.redirect_timeout_ms.map(Duration::from_millis));
+            Ok(Ok(client.wash(&url).await?))
+        }
+        RedirectWashPolicy::LocallyThenMixer | RedirectWashPolicy::MixerThenLocally => {
+            unreachable!("fallback policies are resolved strategy by strategy")
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct UrlWasherConfig {
+    pub mixer_instance: Option<Url>,
+    /// Api key of private mixer instance, sent as bearer token with requests to it.
+    #[serde(default)]
+    pub mixer_api_key: Option<String>,
+    /// How short links are resolved, keyed by name of rule resolving them. Rules without policy do not resolve them.
+    /// Older configs keyed it by domain, see [`UrlWasherConfig::migrate_redirect_policy`].
+    pub redirect_policy: HashMap<RuleName, RedirectWashPolicy>,
+    /// Outbound request customization keyed by target domain.
+    /// Profile of `example.com` applies to its subdomains too, unless they have their own.
+    #[serde(default)]
+    pub http_profiles: HashMap<String, HttpProfile>,
+    /// How long washed urls are cached, forever (until evicted by newer ones) if not set.
+    #[serde(default)]
+    pub cache_ttl_secs: Option<u64>,
+    /// Washed urls kept in cache, least recently used ones are evicted above it.
+    #[serde(default = "default_cache_capacity")]
+    pub cache_capacity: NonZeroUsize,
+    /// Urls no rule matched remembered, so rules are not scanned again when they are washed repeatedly.
+    /// Disabled if not set.
+    #[serde(default)]
+    pub unmatched_cache_capacity: Option<NonZeroUsize>,
+    /// Feeds of malicious domains checked against destinations of short links.
+    #[serde(default)]
+    pub blocklist: BlocklistConfig,
+    /// Keep params of referral programs, e.g. affiliate tags of imported ClearURLs rules.
+    #[serde(default)]
+    pub keep_referral_marketing: bool,
+    /// Remove cross-site tracking params (e.g. `utm_*`, `fbclid`) from urls without rules,
+    /// see [`universal_trackers_rule`].
+    #[serde(default)]
+    pub strip_universal_trackers: bool,
+    /// Look for params resembling trackers on domains without rules, see [`UrlWasher::possible_trackers`].
+    #[serde(default)]
+    pub detect_unknown_trackers: bool,
+    /// Frontend instances washed links of service are moved to, e.g. nitter instance for twitter links.
+    #[serde(default)]
+    pub privacy_frontends: HashMap<FrontendService, Url>,
+    /// Redirects followed per washed link, e.g. 2 for `bit.ly` link to `t.co` link to destination.
+    /// Destination of each one is washed by its own rule, so only known short links are followed.
+    #[serde(default = "default_max_redirect_hops")]
+    pub max_redirect_hops: u8,
+    /// Look for redirect scripts (`window.location = "..."`) in pages of short links without `Location` header,
+    /// see [`html_redirect::script_redirect`].
+    #[serde(default)]
+    pub detect_script_redirects: bool,
+    /// Request method used when resolving redirection locally or via tor.
+    #[serde(default)]
+    pub redirect_request_method: RedirectRequestMethod,
+    /// Time limit of each request resolving redirection (page included), unlimited if `null`.
+    #[serde(default = "default_redirect_timeout_ms")]
+    pub redirect_timeout_ms: Option<u64>,
+    /// Retries of redirect resolution failed by timeout or connection error.
+    #[serde(default)]
+    pub redirect_retries: u8,
+    /// Delay before first retry, doubled before each next one.
+    #[serde(default = "default_redirect_retry_backoff_ms")]
+    pub redirect_retry_backoff_ms: u64,
+    /// Short links of the same domain resolved at once locally or via tor, others wait for them,
+    /// so washing text full of links of one shortener does not get washer banned. Unlimited if `null`.
+    #[serde(default = "default_redirect_concurrency_per_domain")]
+    pub redirect_concurrency_per_domain: Option<NonZeroUsize>,
+    /// Wash url with programs of every rule matching it in order, instead of only the first one,
+    /// so e.g. custom rule for `youtube.com` adds to built-in one instead of being shadowed by it.
+    /// Rules after one that unrolled short link or extracted destination are skipped,
+    /// destination is washed by its own rules.
+    #[serde(default)]
+    pub apply_all_matching_rules: bool,
+    /// SOCKS5 proxy of local tor daemon used by [`RedirectWashPolicy::ViaTor`].
+    #[serde(default = "default_tor_proxy")]
+    pub tor_proxy: Url,
+    /// Urls on these domains (and their subdomains) are never washed,
+    /// e.g. internal links whose params are load-bearing.
+    #[serde(default)]
+    pub ignored_domains: Vec<String>,
+    /// Only urls on these domains (and their subdomains) are washed, every domain if empty.
+    /// Ignored domains are skipped even if listed here.
+    #[serde(default)]
+    pub only_domains: Vec<String>,
+    /// Sort params of every washed url by name, also of urls with nothing to wash,
+    /// so the same link yields the same output (e.g. for deduplication in bots).
+    #[serde(default)]
+    pub sort_query_params: bool,
+    /// Schemes washed besides `http` and `https`, e.g. deep links of apps (`myapp`)
+    /// or protocol handlers (`web+` matches every `web+...` scheme).
+    /// Http urls embedded in their params are washed by their own rules,
+    /// deep link itself only by rules matching it, e.g. by `url_pattern`.
+    #[serde(default)]
+    pub additional_schemes: Vec<String>,
+    /// Wash http urls in param values of washed urls, e.g. dirty link in `next` param of login page,
+    /// before url itself is washed by its rules.
+    #[serde(default)]
+    pub wash_nested_urls: bool,
+    /// Unroll links of url shorteners without rules, their policy is keyed by [`shorteners::RULE_NAME`].
+    #[serde(default)]
+    pub shorteners: shorteners::ShortenersConfig,
+    /// Accept destinations of short links only on their own registrable domain
+    /// (or [`DirtyUrlRule::redirect_sites`] of their rule), so compromised shortener
+    /// cannot send users through interstitial of attacker, see [`OffSiteRedirect`].
+    #[serde(default)]
+    pub same_site_redirects: bool,
+    /// Resolve domains of requests with DNS over HTTPS of provider (`doh` feature), instead of system resolver,
+    /// so domains of short links do not leak to resolver of ISP. Applies to http client built by washer,
+    /// ignored in wasm, where browser resolves domains, and by tor, which resolves them itself.
+    #[serde(default)]
+    pub dns_over_https: Option<doh::DohProvider>,
+}
+
+fn default_cache_capacity() -> NonZeroUsize {
+    NonZeroUsize::new(1024).unwrap()
+}
+
+fn default_max_redirect_hops() -> u8 {
+    3
+}
+
+fn default_redirect_timeout_ms() -> Option<u64> {
+    Some(10_000)
+}
+
+fn default_redirect_retry_backoff_ms() -> u64 {
+    250
+}
+
+fn default_redirect_concurrency_per_domain() -> Option<NonZeroUsize> {
+    NonZeroUsize::new(4)
+}
+
+/// `socks5h`, so short link domains are resolved by tor too.
+fn default_tor_proxy() -> Url {
+    Url::parse("socks5h://127.0.0.1:9050").unwrap()
+}
+
+impl UrlWasherConfig {
+    /// Client of configured mixer instance, authenticated with [`Self::mixer_api_key`].
+    #[cfg(feature = "network")]
+    pub fn mixer_client(&self, http_client: &reqwest::Client) -> Option<mixer_client::MixerClient> {
+        let client =
+            mixer_client::MixerClient::new(http_client.clone(), self.mixer_instance.clone()?);
+        Some(client.api_key(self.mixer_api_key.clone()))
+    }
+
+    /// Sets `policy` of every rule in `rules` that resolves redirection,
+    /// so custom rules (e.g. from [`rule_file`]) unroll short links like built-in ones.
+    pub fn set_redirect_policy(&mut self, rules: &[DirtyUrlRule], policy: RedirectWashPolicy) {
+        for rule in rules {
+            if rule
+                .washing_programs
+                .contains(&WashingProgram::ResolveRedirection)
+            {
+                self.redirect_policy.insert(rule.name.clone(), policy);
+            }
+        }
+    }
+
+    /// Moves policies keyed by domain of rule (as configs saved by older versions were) to name of that rule,
+    /// unless rule has its own policy. Returns how many policies were moved.
+    /// Washers migrate their config against their rules, apps saving config should migrate it when loading it.
+    pub fn migrate_redirect_policy(&mut self, rules: &[DirtyUrlRule]) -> usize {
+        let mut migrated = 0;
+        for rule in rules {
+            if self.redirect_policy.contains_key(&rule.name) {
+                continue;
+            }
+            let by_domain = rule.domains.iter().find(|domain| {
+                self.redirect_policy.contains_key(*domain)
+                    && !rules.iter().any(|rule| &rule.name == *domain)
+            });
+            if let Some(policy) = by_domain.and_then(|domain| self.redirect_policy.remove(domain)) {
+                self.redirect_policy.insert(rule.name.clone(), policy);
+                migrated += 1;
+            }
+        }
+        migrated
+    }
+
+    /// Whether urls on `domain` are washed, see [`UrlWasherConfig::ignored_domains`]
+    /// and [`UrlWasherConfig::only_domains`].
+    pub fn washes_domain(&self, domain: &str) -> bool {
+        let listed = |domains: &[String]| {
+            let mut domain = domain.trim_end_matches('.');
+            loop {
+                if domains
+                    .iter()
+                    .any(|listed| listed.eq_ignore_ascii_case(domain))
+                {
+                    return true;
+                }
+                match domain.split_once('.') {
+                    Some((_, parent)) => domain = parent,
+                    None => return false,
+                }
+            }
+        };
+        (self.only_domains.is_empty() || listed(&self.only_domains))
+            && !listed(&self.ignored_domains)
+    }
+
+    /// Whether urls with `scheme` besides `http` and `https` are washed,
+    /// see [`UrlWasherConfig::additional_schemes`].
+    pub fn washes_scheme(&self, scheme: &str) -> bool {
+        self.additional_schemes.iter().any(|listed| {
+            let listed = listed.trim_end_matches(':');
+            if listed.ends_with('+') {
+                scheme.len() > listed.len()
+                    && scheme
+                        .get(..listed.len())
+                        .is_some_and(|family| family.eq_ignore_ascii_case(listed))
+            } else {
+                listed.eq_ignore_ascii_case(scheme)
+            }
+        })
+    }
+
+    pub fn http_profile(&self, domain: &str) -> Option<&HttpProfile> {
+        let mut domain = domain;
+        loop {
+            if let Some(profile) = self.http_profiles.get(domain) {
+                return Some(profile);
+            }
+            domain = domain.split_once('.')?.1;
+        }
+    }
+}
+
+impl Default for UrlWasherConfig {
+    fn default() -> Self {
+        let mut config = Self {
+            mixer_instance: Default::default(),
+            mixer_api_key: None,
+            http_profiles: Default::default(),
+            cache_ttl_secs: None,
+            cache_capacity: default_cache_capacity(),
+            unmatched_cache_capacity: None,
+            blocklist: BlocklistConfig::default(),
+            keep_referral_marketing: false,
+            strip_universal_trackers: false,
+            detect_unknown_trackers: false,
+            privacy_frontends: HashMap::new(),
+            max_redirect_hops: default_max_redirect_hops(),
+            detect_script_redirects: false,
+            redirect_request_method: RedirectRequestMethod::default(),
+            apply_all_matching_rules: false,
+            redirect_timeout_ms: default_redirect_timeout_ms(),
+            redirect_retries: 0,
+            redirect_retry_backoff_ms: default_redirect_retry_backoff_ms(),
+            redirect_concurrency_per_domain: default_redirect_concurrency_per_domain(),
+            tor_proxy: default_tor_proxy(),
+            ignored_domains: Vec::new(),
+            only_domains: Vec::new(),
+            sort_query_params: false,
+            additional_schemes: Vec::new(),
+            wash_nested_urls: false,
+            shorteners: shorteners::ShortenersConfig::default(),
+            same_site_redirects: false,
+            dns_over_https: None,
+            redirect_policy: HashMap::new(),
+        };
+        config.set_redirect_policy(rule_set(), RedirectWashPolicy::Locally);
+        config.set_redirect_policy(
+            std::slice::from_ref(shorteners::rule()),
+            RedirectWashPolicy::Locally,
+        );
+        config
+    }
+}
+
+/// User agent and headers sent when resolving redirection locally.
+///
+/// Some shorteners return different destination to bot-looking clients.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug)]
+pub struct HttpProfile {
+    /// Overrides [`DEFAULT_USER_AGENT`].
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl HttpProfile {
+    #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+    fn apply(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(user_agent) = &self.user_agent {
+            request = request.header(reqwest::header::USER_AGENT, user_agent);
+        }
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        request
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RedirectWashPolicy {
+    /// Do not resolve redirection.
+    Ignore,
+    /// Resolve redirection locally.
+    ///
+    /// Exposes your IP address that can be corellated with you.
+    Locally,
+    /// Resolve redirection using urldebloater-mixer.
+    ///
+    /// Exposes link to person who is running mixer instance you set
+    /// (not so scary for tiktoks tho).
+    ViaMixer,
+    /// Resolve redirection through tor, see [`UrlWasherConfig::tor_proxy`].
+    ///
+    /// Neither your IP address nor link is exposed to anyone you know,
+    /// but tor exit nodes are often blocked or served captchas by shorteners.
+    ViaTor,
+    /// Resolve redirection locally, using mixer if it fails (e.g. shortener blocks your network).
+    LocallyThenMixer,
+    /// Resolve redirection using mixer, locally if it fails (e.g. mixer instance is offline).
+    MixerThenLocally,
+}
+
+impl RedirectWashPolicy {
+    /// Strategy tried first and the one tried if it fails, [`None`] unless policy is fallback one.
+    pub fn fallback(&self) -> Option<(RedirectWashPolicy, RedirectWashPolicy)> {
+        match self {
+            RedirectWashPolicy::LocallyThenMixer => {
+                Some((RedirectWashPolicy::Locally, RedirectWashPolicy::ViaMixer))
+            }
+            RedirectWashPolicy::MixerThenLocally => {
+                Some((RedirectWashPolicy::ViaMixer, RedirectWashPolicy::Locally))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RedirectRequestMethod {
+    #[default]
+    Get,
+    /// Skips downloading page, so less data is used and shorteners are less likely to count a visit.
+    /// Redirects done by page (meta refresh, scripts) are not detected.
+    Head,
+}
+
+impl Display for RedirectWashPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RedirectWashPolicy::Ignore => "ignore",
+            RedirectWashPolicy::Locally => "locally",
+            RedirectWashPolicy::ViaMixer => "via mixer",
+            RedirectWashPolicy::ViaTor => "via tor",
+            RedirectWashPolicy::LocallyThenMixer => "locally, then via mixer",
+            RedirectWashPolicy::MixerThenLocally => "via mixer, then locally",
+        })
+    }
+}
+
+/// Serialized in format of [`rule_file`], empty fields are skipped.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct DirtyUrlRule {
+    pub name: String,
+    /// Exact domains, or all subdomains of domain when prefixed with `*.` (e.g. `*.facebook.com`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domains: Vec<String>,
+    /// Registrable domains (e.g. `tiktok.com`) covering all their hosts,
+    /// or `name.*` for site on any public suffix, see [`public_suffix::matches_site`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sites: Vec<String>,
+    /// Path segments to match, `None` matches any segment.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_pattern: Vec<Option<String>>,
+    /// Matched against url path (e.g. `^/share/` or `^/watch$`), together with [`DirtyUrlRule::path_pattern`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_regex: Option<Pattern>,
+    pub washing_programs: Vec<WashingProgram>,
+    /// Matched against whole url instead of [`DirtyUrlRule::domains`], used by imported rules.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_pattern: Option<Pattern>,
+    /// Urls matching any of patterns are left alone, even if rule matches them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub url_exceptions: Vec<Pattern>,
+    /// Sites (like [`DirtyUrlRule::sites`]) short links of rule may redirect to besides their own,
+    /// checked if [`UrlWasherConfig::same_site_redirects`] is enabled, e.g. `youtube.com` for `youtu.be`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirect_sites: Vec<String>,
+}
+
+impl DirtyUrlRule {
+    pub fn matches(&self, url: &Url) -> bool {
+        let url_matches = match &self.url_pattern {
+            Some(pattern) => pattern.is_match(url.as_str()),
+            None => url.domain().is_some_and(|domain| {
+                self.matches_domain(domain)
+                    || self
+                        .sites
+                        .iter()
+                        .any(|site| public_suffix::matches_site(site, domain))
+            }),
+        };
+        url_matches
+            && self.matches_path(url)
+            && !self
+                .url_exceptions
+                .iter()
+                .any(|exception| exception.is_match(url.as_str()))
+    }
+
+    pub fn matches_domain(&self, domain: &str) -> bool {
+        self.domains
+            .iter()
+            .any(|dirty_domain| match dirty_domain.strip_prefix("*.") {
+                Some(parent) => domain
+                    .strip_suffix(parent)
+                    .is_some_and(|subdomain| subdomain.ends_with('.')),
+                None => dirty_domain == domain,
+            })
+    }
+
+    pub fn matches_path(&self, url: &Url) -> bool {
+        if self
+            .path_regex
+            .as_ref()
+            .is_some_and(|path_regex| !path_regex.is_match(url.path()))
+        {
+            return false;
+        }
+        if self.path_pattern.is_empty() {
+            return true;
+        }
+        let segments = match url.path_segments() {
+            Some(segments) => segments,
+            None => return false,
+        };
+        segments
+            .zip(&self.path_pattern)
+            .all(|(actual, template)| match template {
+                Some(template) => actual == template,
+                None => true,
+            })
+    }
+}
+
+/// Serialized by [`WashingProgram::name`], e.g. `{ "remove_some_params": ["si"] }`, patterns are regexes.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum WashingProgram {
+    ResolveRedirection,
+    RemoveSomeParams(Vec<String>),
+    /// Removes params with names starting with any of prefixes, e.g. `utm_` for all `utm_*` params.
+    RemoveParamsByPrefix(Vec<String>),
+    /// Removes all params except listed ones, for sites that keep inventing new trackers.
+    KeepOnlyParams(Vec<String>),
+    RemoveAllParams,
+    /// Keeps listed params, but blanks their values,
+    /// for pages that break without the param while its value is the tracking payload.
+    RedactParamValues(Vec<String>),
+    /// Removes params with names matching any of patterns.
+    RemoveMatchingParams(Vec<Pattern>),
+    /// Removes path segments and matrix params matching any of patterns, see [`strip_path_segments`].
+    StripPathSegments(Vec<Pattern>),
+    /// Params of referral programs (e.g. affiliate tags), which some users want to keep
+    /// to support creators, see [`UrlWasherConfig::keep_referral_marketing`].
+    RemoveReferralMarketing(Vec<Pattern>),
+    /// Moves link to privacy frontend of service, if user picked its instance
+    /// in [`UrlWasherConfig::privacy_frontends`].
+    UseFrontend(FrontendService),
+    /// Removes tracking from fragment, see [`remove_fragment`]. Whole fragment is removed if `None`.
+    RemoveFragment(Option<Pattern>),
+    /// Removes parts of url matching any of patterns, see [`remove_raw_matches`].
+    RemoveRawMatches(Vec<Pattern>),
+    /// Replaces redirect wrapper with its destination, see [`extract_redirection`].
+    /// Destination is washed by its own rule, remaining programs are skipped.
+    ExtractRedirection(Vec<Pattern>),
+    /// Replaces redirect wrapper with destination from one of params, see [`extract_embedded_url`].
+    /// Destination is washed by its own rule, remaining programs are skipped.
+    ExtractEmbeddedUrl(Vec<String>),
+    /// Replaces redirect wrapper with destination base64 encoded in one of params, see [`extract_base64_url`].
+    /// Destination is washed by its own rule, remaining programs are skipped.
+    ExtractBase64Url(Vec<String>),
+    /// Replaces Proofpoint URL Defense link with its destination, see [`url_defense::decode`].
+    UnwrapUrlDefense,
+    /// Sorts remaining params by name, see [`sort_query_params`].
+    SortParams,
+    /// Collapses repeated slashes in path and strips or appends trailing slash, see [`normalize_path`].
+    NormalizePath(TrailingSlash),
+    /// Rewrites path to canonical form, e.g. amazon product page to `/dp/ASIN`, see [`rewrite_path`].
+    RewritePath(PathRewrite),
+    /// Replaces subdomains of registrable domain, e.g. `smile.amazon.de` becomes `www.amazon.de`
+    /// with `www`, see [`set_subdomain`].
+    SetSubdomain(String),
+}
+
+/// Path rewritten by [`WashingProgram::RewritePath`], e.g. `{ "pattern": "^/item/(\\d+)/.*$", "replacement": "/item/$1" }`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PathRewrite {
+    pub pattern: Pattern,
+    /// Replaces part of path matched by pattern, `$1` refers to its first capture group.
+    pub replacement: String,
+}
+
+/// Slash at the end of path normalized by [`WashingProgram::NormalizePath`].
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailingSlash {
+    #[default]
+    Keep,
+    Strip,
+    /// Appended unless last segment looks like file name (contains `.`).
+    Append,
+}
+
+/// Regex compared by its source, so rules holding it stay comparable.
+#[derive(Clone, Debug)]
+pub struct Pattern(regex::Regex);
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        regex::Regex::new(pattern).map(Self)
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+impl Serialize for Pattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Pattern::new(&pattern).map_err(serde::de::Error::custom)
+    }
+}
+
+impl WashingProgram {
+    /// Name used in rule files and wash reports, e.g. `remove_some_params`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            WashingProgram::ResolveRedirection => "resolve_redirection",
+            WashingProgram::RemoveSomeParams(_) => "remove_some_params",
+            WashingProgram::RemoveParamsByPrefix(_) => "remove_params_by_prefix",
+            WashingProgram::KeepOnlyParams(_) => "keep_only_params",
+            WashingProgram::RemoveAllParams => "remove_all_params",
+            WashingProgram::RedactParamValues(_) => "redact_param_values",
+            WashingProgram::RemoveMatchingParams(_) => "remove_matching_params",
+            WashingProgram::StripPathSegments(_) => "strip_path_segments",
+            WashingProgram::RemoveReferralMarketing(_) => "remove_referral_marketing",
+            WashingProgram::UseFrontend(_) => "use_frontend",
+            WashingProgram::RemoveFragment(_) => "remove_fragment",
+            WashingProgram::RemoveRawMatches(_) => "remove_raw_matches",
+            WashingProgram::ExtractRedirection(_) => "extract_redirection",
+            WashingProgram::ExtractEmbeddedUrl(_) => "extract_embedded_url",
+            WashingProgram::ExtractBase64Url(_) => "extract_base64_url",
+            WashingProgram::UnwrapUrlDefense => "unwrap_url_defense",
+            WashingProgram::SortParams => "sort_params",
+            WashingProgram::NormalizePath(_) => "normalize_path",
+            WashingProgram::RewritePath(_) => "rewrite_path",
+            WashingProgram::SetSubdomain(_) => "set_subdomain",
+        }
+    }
+
+    pub fn remove_some_params(values: &[&str]) -> Self {
+        Self::RemoveSomeParams(values.iter().map(|s| String::from(*s)).collect())
+    }
+
+    pub fn remove_params_by_prefix(prefixes: &[&str]) -> Self {
+        Self::RemoveParamsByPrefix(prefixes.iter().map(|s| String::from(*s)).collect())
+    }
+
+    pub fn extract_embedded_url(params: &[&str]) -> Self {
+        Self::ExtractEmbeddedUrl(params.iter().map(|s| String::from(*s)).collect())
+    }
+
+    pub fn extract_base64_url(params: &[&str]) -> Self {
+        Self::ExtractBase64Url(params.iter().map(|s| String::from(*s)).collect())
+    }
+
+    pub fn keep_only_params(values: &[&str]) -> Self {
+        Self::KeepOnlyParams(values.iter().map(|s| String::from(*s)).collect())
+    }
+
+    pub fn redact_param_values(values: &[&str]) -> Self {
+        Self::RedactParamValues(values.iter().map(|s| String::from(*s)).collect())
+    }
+
+    /// Panics on invalid pattern, meant for built-in rules.
+    pub fn remove_matching_params(patterns: &[&str]) -> Self {
+        Self::RemoveMatchingParams(
+            patterns
+                .iter()
+                .map(|pattern| Pattern::new(pattern).expect("invalid param pattern"))
+                .collect(),
+        )
+    }
+
+    /// Panics on invalid pattern, meant for built-in rules.
+    pub fn strip_path_segments(patterns: &[&str]) -> Self {
+        Self::StripPathSegments(
+            patterns
+                .iter()
+                .map(|pattern| Pattern::new(pattern).expect("invalid path segment pattern"))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        num::NonZeroUsize,
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    use url::Url;
+
+    use crate::{
+        blocklist::{parse_feed, BlockedDestination},
+        fixtures::RedirectFixtures,
+        keep_only_params, normalize_path, redact_query_params, remove_fragment,
+        remove_params_by_prefix, remove_query_params, rewrite_path, set_subdomain,
+        sort_query_params, strip_path_segments, DirtyUrlRule, HttpProfile, OffSiteRedirect,
+        PathRewrite, Pattern, RedirectLoop, RedirectStore, TrailingSlash, UrlWasher,
+        UrlWasherConfig, WashingProgram,
+    };
+
+    #[test]
+    fn configures_global_washer_once() {
+        let config = UrlWasherConfig {
+            keep_referral_marketing: true,
+            ..Default::default()
+        };
+        crate::set_global_config(config.clone()).unwrap();
+        assert!(crate::set_global_config(UrlWasherConfig::default()).is_err());
+        assert!(Arc::ptr_eq(&crate::global(), &crate::global()));
+        assert_eq!(crate::global().config, config);
+    }
+
+    #[test]
+    fn migrates_redirect_policy_keyed_by_domain() {
+        use crate::RedirectWashPolicy;
+
+        let config = UrlWasherConfig::default();
+        assert_eq!(
+            config.redirect_policy.get("vm.tiktok.com"),
+            Some(&RedirectWashPolicy::Locally)
+        );
+
+        let rules = vec![
+            DirtyUrlRule {
+                name: "shortener".to_string(),
+                domains: vec!["sho.rt".to_string(), "s.rt".to_string()],
+                washing_programs: vec![WashingProgram::ResolveRedirection],
+                ..Default::default()
+            },
+            DirtyUrlRule {
+                name: "link".to_string(),
+                domains: vec!["lnk.to".to_string()],
+                washing_programs: vec![WashingProgram::ResolveRedirection],
+                ..Default::default()
+            },
+        ];
+        let mut config: UrlWasherConfig = serde_json::from_str(
+            r#"{
+                "mixer_instance": null,
+                "redirect_policy": { "s.rt": "ViaTor", "lnk.to": "ViaMixer", "link": "Locally" }
+            }"#,
+        )
+        .unwrap();
+        let washer = UrlWasher::new(config.clone()).with_rules(rules.clone());
+        assert_eq!(
+            washer.config.redirect_policy.get("shortener"),
+            Some(&RedirectWashPolicy::ViaTor)
+        );
+        assert_eq!(config.migrate_redirect_policy(&rules), 1);
+        assert_eq!(config.migrate_redirect_policy(&rules), 0);
+        assert_eq!(config.redirect_policy, washer.config.redirect_policy);
+        // rule with its own policy keeps it, stale domain entry is left alone
+        assert_eq!(
+            config.redirect_policy.get("link"),
+            Some(&RedirectWashPolicy::Locally)
+        );
+        assert!(config.redirect_policy.contains_key("lnk.to"));
+    }
 
     #[tokio::test]
     async fn test_cleaning() {
-        let washer = UrlWasher::new(UrlWasherConfig::default());
+        let fixtures_path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/redirects.json");
+        let fixtures = Arc::new(RedirectFixtures::load(&fixtures_path).unwrap());
+        let washer = UrlWasher::new(UrlWasherConfig::default()).with_redirect_fixtures(fixtures);
         let tests = [
             (
                 "https://youtu.be/lSwnPoo9ZK0?si=TrackingParamValue&t=65",
@@ -330,14 +2382,22 @@ mod tests {
                 "https://www.tiktok.com/@i0ki.clips/video/7297742182851611936",
             ),
             (
-                "https://on.soundcloud.com/VLwCL",
-                "https://soundcloud.com/djwipeoutnxc/i-c-right-thru-2-u",
+                "https://on.soundcloud.com/VLwCL",
+                "https://soundcloud.com/djwipeoutnxc/i-c-right-thru-2-u",
+            ),
+            (
+                "https://www.amazon.com/Echo-Dot-5th-Gen/dp/B09B8V1LZ3/ref=sr_1_1?crid=2M3&keywords=echo&qid=1700000000&sprefix=echo%2Caps%2C150&sr=8-1&th=1",
+                "https://www.amazon.com/dp/B09B8V1LZ3",
+            ),
+            (
+                "https://www.amazon.co.uk/gp/product/B07PGL2ZSL/ref=ppx_yo_dt_b_asin_title_o00_s00?ie=UTF8&psc=1&tag=abc-21",
+                "https://www.amazon.co.uk/dp/B07PGL2ZSL",
             ),
         ];
 
         for (dirty, clean) in tests {
-            let dirty_url = Url::parse(&dirty).expect(dirty);
-            let clean_url = Url::parse(&clean).expect(clean);
+            let dirty_url = Url::parse(dirty).expect(dirty);
+            let clean_url = Url::parse(clean).expect(clean);
             assert_eq!(
                 clean_url.to_string(),
                 washer
@@ -350,4 +2410,1072 @@ mod tests {
             );
         }
     }
+
+    #[tokio::test]
+    async fn cache_can_be_inspected_and_purged() {
+        let washer = UrlWasher::default();
+        let dirty = Url::parse("https://youtu.be/lSwnPoo9ZK0?si=TRACKING").unwrap();
+        let clean = Url::parse("https://youtu.be/lSwnPoo9ZK0").unwrap();
+        washer.wash(&dirty).await.unwrap();
+
+        assert_eq!(washer.cache_entries(), vec![(dirty.clone(), clean.clone())]);
+        assert_eq!(washer.cache_remove(&dirty), Some(clean));
+        assert_eq!(washer.cache_remove(&dirty), None);
+        washer.wash(&dirty).await.unwrap();
+        washer.cache_clear();
+        assert!(washer.cache_entries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn evicts_cache_entries_above_capacity() {
+        let washer = UrlWasher::new(UrlWasherConfig {
+            cache_capacity: NonZeroUsize::new(2).unwrap(),
+            ..Default::default()
+        });
+        for video in ["a", "b", "c"] {
+            let dirty = Url::parse(&format!("https://youtu.be/{video}?si=TRACKING")).unwrap();
+            washer.wash(&dirty).await.unwrap();
+        }
+
+        let cached = washer.cache_entries();
+        assert_eq!(cached.len(), 2);
+        assert!(!cached
+            .iter()
+            .any(|(dirty, _)| dirty.as_str() == "https://youtu.be/a?si=TRACKING"));
+    }
+
+    #[tokio::test]
+    async fn applies_all_matching_rules_if_enabled() {
+        let mut rules = crate::rule_set().clone();
+        rules.push(DirtyUrlRule {
+            name: "custom youtube.com".to_string(),
+            domains: vec!["www.youtube.com".to_string()],
+            washing_programs: vec![WashingProgram::remove_some_params(&["pp"])],
+            ..Default::default()
+        });
+        let dirty = Url::parse("https://www.youtube.com/watch?v=1&si=x&pp=y").unwrap();
+
+        let washer = UrlWasher::default().with_rules(rules.clone());
+        let washed = washer.wash(&dirty).await.unwrap();
+        assert_eq!(
+            washed.unwrap().as_str(),
+            "https://www.youtube.com/watch?v=1&pp=y"
+        );
+
+        let config = UrlWasherConfig {
+            apply_all_matching_rules: true,
+            ..Default::default()
+        };
+        let washer = UrlWasher::new(config).with_rules(rules);
+        let report = washer.wash_with_report(&dirty).await.unwrap();
+        assert_eq!(report.cleaned, "https://www.youtube.com/watch?v=1");
+        assert_eq!(
+            report.rule.as_deref(),
+            Some("youtube.com & music.youtube.com")
+        );
+        assert_eq!(
+            report.programs,
+            ["remove_some_params", "use_frontend", "remove_some_params"]
+        );
+        // redirect wrapper still hands destination over to its own rules
+        let washed = washer
+            .wash(
+                &Url::parse("https://www.youtube.com/redirect?q=https://youtu.be/a?si%3Dx&pp=y")
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(washed.unwrap().as_str(), "https://youtu.be/a");
+    }
+
+    #[tokio::test]
+    async fn strips_universal_trackers_on_sites_without_rules() {
+        let dirty = Url::parse("https://shop.example/item?id=1&utm_source=x&fbclid=y").unwrap();
+        let clean = Url::parse("https://shop.example/item?id=1").unwrap();
+        assert_eq!(UrlWasher::default().wash(&dirty).await.unwrap(), None);
+
+        let config = UrlWasherConfig {
+            strip_universal_trackers: true,
+            unmatched_cache_capacity: NonZeroUsize::new(8),
+            ..Default::default()
+        };
+        let washer = UrlWasher::new(config);
+        let report = washer.wash_with_report(&dirty).await.unwrap();
+        assert_eq!(report.cleaned, clean.as_str());
+        assert_eq!(report.rule.as_deref(), Some("universal trackers"));
+        assert_eq!(report.removed_params, ["utm_source", "fbclid"]);
+        assert_eq!(washer.wash(&clean).await.unwrap(), None);
+        assert_eq!(washer.wash(&dirty).await.unwrap(), Some(clean));
+        // sites with rules are washed by them only
+        let washed = washer
+            .wash(&Url::parse("https://youtu.be/a?si=x&gclid=y").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(washed.unwrap().as_str(), "https://youtu.be/a?gclid=y");
+    }
+
+    #[tokio::test]
+    async fn skips_ignored_and_unlisted_domains() {
+        let config = UrlWasherConfig {
+            ignored_domains: vec!["youtu.be".to_string()],
+            ..Default::default()
+        };
+        let washer = UrlWasher::new(config);
+        for url in ["https://youtu.be/a?si=x", "https://WWW.YouTu.be./a?si=x"] {
+            let url = Url::parse(url).unwrap();
+            assert_eq!(washer.wash(&url).await.unwrap(), None);
+        }
+        let spotify = Url::parse("https://open.spotify.com/track/1?si=x").unwrap();
+        assert!(washer.wash(&spotify).await.unwrap().is_some());
+
+        let config = UrlWasherConfig {
+            only_domains: vec!["spotify.com".to_string(), "youtu.be".to_string()],
+            ignored_domains: vec!["youtu.be".to_string()],
+            ..Default::default()
+        };
+        assert!(config.washes_domain("open.spotify.com"));
+        assert!(!config.washes_domain("youtu.be"));
+        assert!(!config.washes_domain("notspotify.com"));
+        let washer = UrlWasher::new(config);
+        assert!(washer.wash(&spotify).await.unwrap().is_some());
+        let reddit = Url::parse("https://www.reddit.com/r/rust?share_id=x").unwrap();
+        assert_eq!(washer.wash(&reddit).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn washes_urls_embedded_in_additional_schemes() {
+        let deep_link = Url::parse("myapp://open?url=https://youtu.be/a?si%3Dx&id=1").unwrap();
+        assert_eq!(UrlWasher::default().wash(&deep_link).await.unwrap(), None);
+
+        let config = UrlWasherConfig {
+            additional_schemes: vec!["MyApp".to_string(), "web+".to_string()],
+            ..Default::default()
+        };
+        assert!(config.washes_scheme("web+mastodon"));
+        assert!(!config.washes_scheme("web+"));
+        assert!(!config.washes_scheme("ftp"));
+        let washer = UrlWasher::new(config);
+        let washed = washer.wash(&deep_link).await.unwrap();
+        assert_eq!(
+            washed.unwrap().as_str(),
+            "myapp://open?url=https%3A%2F%2Fyoutu.be%2Fa&id=1"
+        );
+        let clean = Url::parse("web+share://open?text=hello").unwrap();
+        assert_eq!(washer.wash(&clean).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn washes_urls_nested_in_params() {
+        let login = Url::parse(
+            "https://example.com/login?next=https%3A%2F%2Fyoutu.be%2Fabc%3Fsi%3DXYZ&lang=en",
+        )
+        .unwrap();
+        assert_eq!(UrlWasher::default().wash(&login).await.unwrap(), None);
+
+        let washer = UrlWasher::new(UrlWasherConfig {
+            wash_nested_urls: true,
+            ..Default::default()
+        });
+        let washed = washer.wash(&login).await.unwrap();
+        assert_eq!(
+            washed.unwrap().as_str(),
+            "https://example.com/login?next=https%3A%2F%2Fyoutu.be%2Fabc&lang=en"
+        );
+        // nested url is washed before outer one by its own rule
+        let nested = Url::parse(
+            "https://youtu.be/a?si=x&redirect=https%3A%2F%2Fx.com%2Fa%2Fstatus%2F1%3Fs%3D46",
+        )
+        .unwrap();
+        let washed = washer.wash(&nested).await.unwrap();
+        assert_eq!(
+            washed.unwrap().as_str(),
+            "https://youtu.be/a?redirect=https%3A%2F%2Fx.com%2Fa%2Fstatus%2F1"
+        );
+        let clean = Url::parse("https://example.com/login?next=%2Fhome").unwrap();
+        assert_eq!(washer.wash(&clean).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn notifies_observer_of_changed_urls() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let observer = {
+            let events = events.clone();
+            move |event: &crate::WashEvent| {
+                events.lock().unwrap().push((
+                    event.original.to_string(),
+                    event.washed.to_string(),
+                    event.rule.map(str::to_string),
+                    event.cached,
+                ));
+                assert!(event.duration.is_some());
+            }
+        };
+        let washer = UrlWasher::default().with_observer(Arc::new(observer));
+        let dirty = Url::parse("https://youtu.be/a?si=x").unwrap();
+        washer.wash(&dirty).await.unwrap();
+        washer.wash_with_report(&dirty).await.unwrap();
+        washer
+            .wash(&Url::parse("https://youtu.be/a").unwrap())
+            .await
+            .unwrap();
+
+        let washed = (
+            "https://youtu.be/a?si=x".to_string(),
+            "https://youtu.be/a".to_string(),
+            Some("youtu.be".to_string()),
+        );
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                (washed.0.clone(), washed.1.clone(), washed.2.clone(), false),
+                (washed.0, washed.1, washed.2, true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn traces_rules_and_programs_of_wash() {
+        use tracing::{span, Subscriber};
+        use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+        /// Names of created spans, with names of their parents if any.
+        struct SpanTree(Arc<Mutex<Vec<String>>>);
+
+        impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanTree {
+            fn on_new_span(&self, _: &span::Attributes, id: &span::Id, ctx: Context<S>) {
+                let span = ctx.span(id).unwrap();
+                let name = match span.parent() {
+                    Some(parent) => format!("{} in {}", span.name(), parent.name()),
+                    None => span.name().to_string(),
+                };
+                self.0.lock().unwrap().push(name);
+            }
+        }
+
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        let _subscriber = tracing_subscriber::registry()
+            .with(SpanTree(spans.clone()))
+            .set_default();
+        let dirty = Url::parse("https://open.spotify.com/track/1?si=x").unwrap();
+        UrlWasher::default().wash(&dirty).await.unwrap();
+
+        assert_eq!(
+            *spans.lock().unwrap(),
+            ["wash", "rule in wash", "program in rule"]
+        );
+    }
+
+    #[tokio::test]
+    async fn remembers_urls_without_matching_rule() {
+        let washer = UrlWasher::new(UrlWasherConfig {
+            unmatched_cache_capacity: NonZeroUsize::new(16),
+            ..Default::default()
+        });
+        let clean = Url::parse("https://example.com/a?id=1").unwrap();
+        assert_eq!(washer.wash(&clean).await.unwrap(), None);
+        assert_eq!(washer.wash(&clean).await.unwrap(), None);
+        assert!(washer
+            .unmatched_cache
+            .as_ref()
+            .unwrap()
+            .shard(&clean)
+            .contains(&clean));
+        assert!(washer.cache_entries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn expired_cache_entries_are_swept() {
+        let washer = UrlWasher::new(UrlWasherConfig {
+            cache_ttl_secs: Some(0),
+            ..Default::default()
+        });
+        let dirty = Url::parse("https://youtu.be/lSwnPoo9ZK0?si=TRACKING").unwrap();
+        washer.wash(&dirty).await.unwrap();
+
+        assert_eq!(washer.cache_entries().len(), 1);
+        assert_eq!(washer.cache_sweep(), 1);
+        assert!(washer.cache_entries().is_empty());
+    }
+
+    #[derive(Default)]
+    pub(crate) struct MemoryRedirectStore(Mutex<HashMap<Url, Url>>);
+
+    impl RedirectStore for MemoryRedirectStore {
+        fn location(&self, short_url: &Url) -> Option<Url> {
+            self.0.lock().unwrap().get(short_url).cloned()
+        }
+
+        fn insert(&self, short_url: Url, location: Url) {
+            self.0.lock().unwrap().insert(short_url, location);
+        }
+
+        fn remove(&self, short_url: &Url) {
+            self.0.lock().unwrap().remove(short_url);
+        }
+
+        fn clear(&self) {
+            self.0.lock().unwrap().clear();
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_redirects_from_store() {
+        let short = Url::parse("https://vm.tiktok.com/ZGJoJs8jb/").unwrap();
+        let location = Url::parse("https://www.tiktok.com/@i0ki.clips/video/1").unwrap();
+        let store = Arc::new(MemoryRedirectStore::default());
+        store.insert(short.clone(), location.clone());
+        let washer = UrlWasher::default().with_redirect_store(store.clone());
+
+        assert_eq!(washer.wash(&short).await.unwrap(), Some(location));
+        washer.cache_remove(&short);
+        assert_eq!(store.location(&short), None);
+    }
+
+    #[tokio::test]
+    async fn reports_what_was_washed() {
+        let short = Url::parse("https://vm.tiktok.com/ZGJoJs8jb/").unwrap();
+        let store = Arc::new(MemoryRedirectStore::default());
+        store.insert(
+            short.clone(),
+            Url::parse("https://www.tiktok.com/@i0ki.clips/video/1?_r=1&u_code=x").unwrap(),
+        );
+        let washer = UrlWasher::default().with_redirect_store(store);
+
+        let report = washer.wash_with_report(&short).await.unwrap();
+        assert_eq!(report.cleaned, "https://www.tiktok.com/@i0ki.clips/video/1");
+        assert_eq!(report.rule.as_deref(), Some("vm.tiktok.com"));
+        assert_eq!(
+            report.programs,
+            vec![
+                "resolve_redirection",
+                "remove_all_params",
+                "remove_all_params"
+            ]
+        );
+        assert_eq!(report.resolution_hops, 1);
+        assert!(!report.cached);
+
+        let report = washer.wash_with_report(&short).await.unwrap();
+        assert!(report.cached);
+        assert_eq!(report.rule.as_deref(), Some("vm.tiktok.com"));
+
+        let dirty = Url::parse("https://youtu.be/lSwnPoo9ZK0?si=TRACKING&t=65").unwrap();
+        let report = washer.wash_with_report(&dirty).await.unwrap();
+        assert_eq!(report.removed_params, vec!["si"]);
+    }
+
+    #[tokio::test]
+    async fn strips_short_link_params_if_not_resolved() {
+        use crate::RedirectWashPolicy;
+
+        let mut config = UrlWasherConfig::default();
+        config
+            .redirect_policy
+            .insert("vm.tiktok.com".to_string(), RedirectWashPolicy::Ignore);
+        let washer = UrlWasher::new(config);
+        let short = Url::parse("https://vm.tiktok.com/ZGJoJs8jb/?_r=1&u_code=x").unwrap();
+        let report = washer.wash_with_report(&short).await.unwrap();
+        assert_eq!(report.cleaned, "https://vm.tiktok.com/ZGJoJs8jb/");
+        assert_eq!(report.resolution_hops, 0);
+    }
+
+    #[tokio::test]
+    async fn washes_resolved_destination_by_its_own_rule() {
+        use crate::RedirectWashPolicy;
+
+        let short = Url::parse("https://short.example/a").unwrap();
+        let store = Arc::new(MemoryRedirectStore::default());
+        store.insert(
+            short.clone(),
+            Url::parse("https://www.youtube.com/watch?v=lSwnPoo9ZK0&si=TRACKING").unwrap(),
+        );
+        let mut rules = vec![DirtyUrlRule {
+            name: "short.example".to_string(),
+            domains: vec!["short.example".to_string()],
+            washing_programs: vec![WashingProgram::ResolveRedirection],
+            ..Default::default()
+        }];
+        rules.extend(crate::rule_set().iter().cloned());
+        let mut config = UrlWasherConfig::default();
+        config
+            .redirect_policy
+            .insert("short.example".to_string(), RedirectWashPolicy::Locally);
+        let washer = UrlWasher::new(config)
+            .with_rules(rules)
+            .with_redirect_store(store);
+
+        let report = washer.wash_with_report(&short).await.unwrap();
+        assert_eq!(
+            report.cleaned,
+            "https://www.youtube.com/watch?v=lSwnPoo9ZK0"
+        );
+        assert_eq!(report.rule.as_deref(), Some("short.example"));
+        assert_eq!(
+            report.programs,
+            vec!["resolve_redirection", "remove_some_params", "use_frontend"]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_off_site_redirects_if_enabled() {
+        let short = Url::parse("https://vm.tiktok.com/ZGJoJs8jb/").unwrap();
+        let store = Arc::new(MemoryRedirectStore::default());
+        let config = UrlWasherConfig {
+            same_site_redirects: true,
+            ..Default::default()
+        };
+        let washer = UrlWasher::new(config.clone()).with_redirect_store(store.clone());
+        store.insert(
+            short.clone(),
+            Url::parse("https://www.tiktok.com/@a/video/1?is_from_webapp=1").unwrap(),
+        );
+        let washed = washer.wash(&short).await.unwrap();
+        assert_eq!(
+            washed.unwrap().as_str(),
+            "https://www.tiktok.com/@a/video/1"
+        );
+
+        let interstitial = Url::parse("https://tiktok.evil.example/continue").unwrap();
+        store.insert(short.clone(), interstitial.clone());
+        let washer = UrlWasher::new(config.clone()).with_redirect_store(store.clone());
+        let err = washer.wash(&short).await.unwrap_err();
+        let off_site = err.downcast::<OffSiteRedirect>().unwrap();
+        assert_eq!(off_site.destination, interstitial);
+        let washed = UrlWasher::default()
+            .with_redirect_store(store.clone())
+            .wash(&short)
+            .await
+            .unwrap();
+        assert_eq!(washed, Some(interstitial.clone()));
+
+        let mut rules = crate::rule_set().clone();
+        let tiktok = rules
+            .iter_mut()
+            .find(|rule| rule.name == "vm.tiktok.com")
+            .unwrap();
+        tiktok.redirect_sites = vec!["evil.example".to_string()];
+        let washer = UrlWasher::new(config)
+            .with_rules(rules)
+            .with_redirect_store(store);
+        assert_eq!(washer.wash(&short).await.unwrap(), Some(interstitial));
+    }
+
+    #[tokio::test]
+    async fn follows_chained_short_links_up_to_hop_limit() {
+        let short = Url::parse("https://vm.tiktok.com/ZGJoJs8jb/").unwrap();
+        let chained = Url::parse("https://on.soundcloud.com/abc").unwrap();
+        let store = Arc::new(MemoryRedirectStore::default());
+        store.insert(short.clone(), chained.clone());
+        store.insert(
+            chained.clone(),
+            Url::parse("https://soundcloud.com/artist/track?si=1").unwrap(),
+        );
+
+        let washer = UrlWasher::default().with_redirect_store(store.clone());
+        assert_eq!(
+            washer.wash(&short).await.unwrap().unwrap().as_str(),
+            "https://soundcloud.com/artist/track"
+        );
+        let washer = UrlWasher::new(UrlWasherConfig {
+            max_redirect_hops: 1,
+            ..Default::default()
+        })
+        .with_redirect_store(store.clone());
+        assert_eq!(washer.wash(&short).await.unwrap(), Some(chained.clone()));
+
+        // redirect loop is detected instead of following it until hops are used up
+        store.insert(chained.clone(), short.clone());
+        let washer = UrlWasher::default().with_redirect_store(store.clone());
+        let err = washer.wash(&short).await.unwrap_err();
+        let redirect_loop = err.downcast::<RedirectLoop>().unwrap();
+        assert_eq!(redirect_loop.url, chained);
+        assert_eq!(redirect_loop.repeated, short);
+        assert_eq!(redirect_loop.hops, 2);
+        // short link redirecting to itself is a loop too
+        store.insert(short.clone(), short.clone());
+        let err = washer.wash(&short).await.unwrap_err();
+        assert_eq!(err.downcast::<RedirectLoop>().unwrap().hops, 1);
+    }
+
+    #[cfg(feature = "network")]
+    const REDIRECT_RESPONSE: &[u8] =
+        b"HTTP/1.1 301 Moved Permanently\r\nLocation: https://example.com/a\r\nContent-Length: 0\r\n\r\n";
+
+    /// Serves raw HTTP responses on local port, `respond` gets index and head of each request.
+    /// Connections without response are held open for a while, so their requests time out.
+    #[cfg(feature = "network")]
+    fn spawn_redirect_server(
+        mut respond: impl FnMut(usize, &str) -> Option<&'static [u8]> + Send + 'static,
+    ) -> u16 {
+        use std::{
+            io::{Read, Write},
+            time::Duration,
+        };
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for (i, mut stream) in listener.incoming().flatten().enumerate() {
+                let mut buf = [0; 1024];
+                let len = stream.read(&mut buf).unwrap_or_default();
+                match respond(i, &String::from_utf8_lossy(&buf[..len])) {
+                    Some(response) => {
+                        let _ = stream.write_all(response);
+                    }
+                    None => {
+                        std::thread::spawn(move || {
+                            std::thread::sleep(Duration::from_secs(2));
+                            drop(stream);
+                        });
+                    }
+                }
+            }
+        });
+        port
+    }
+
+    /// Washer with only rule of `domain`, resolving its short links with `policy`.
+    #[cfg(feature = "network")]
+    fn short_link_washer(
+        domain: &str,
+        mut config: UrlWasherConfig,
+        policy: crate::RedirectWashPolicy,
+    ) -> UrlWasher {
+        config.redirect_policy.insert(domain.to_string(), policy);
+        UrlWasher::new(config).with_rules(vec![DirtyUrlRule {
+            name: domain.to_string(),
+            domains: vec![domain.to_string()],
+            washing_programs: vec![WashingProgram::ResolveRedirection],
+            ..Default::default()
+        }])
+    }
+
+    #[cfg(feature = "network")]
+    #[tokio::test]
+    async fn coalesces_concurrent_washes_of_short_link() {
+        use std::{
+            sync::atomic::{AtomicUsize, Ordering},
+            time::Duration,
+        };
+
+        use crate::RedirectWashPolicy;
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let port = spawn_redirect_server({
+            let requests = requests.clone();
+            move |_, _| {
+                requests.fetch_add(1, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(200));
+                Some(REDIRECT_RESPONSE)
+            }
+        });
+        let washer = short_link_washer(
+            "localhost",
+            UrlWasherConfig::default(),
+            RedirectWashPolicy::Locally,
+        );
+        let short = Url::parse(&format!("http://localhost:{port}/abc")).unwrap();
+
+        let washed = futures_util::future::join_all((0..10).map(|_| washer.wash(&short))).await;
+        for washed in washed {
+            assert_eq!(washed.unwrap().unwrap().as_str(), "https://example.com/a");
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(feature = "network")]
+    #[tokio::test]
+    async fn resolves_redirection_via_tor_proxy() {
+        use std::io::{Read, Write};
+
+        use crate::RedirectWashPolicy;
+
+        // minimal SOCKS5 server, remembering domain requested through it
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let proxied_domain = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut greeting = [0; 3];
+            stream.read_exact(&mut greeting).unwrap();
+            stream.write_all(&[5, 0]).unwrap();
+            let mut request = [0; 5];
+            stream.read_exact(&mut request).unwrap();
+            assert_eq!(request[3], 3, "domain should be resolved by proxy");
+            let mut domain = vec![0; request[4] as usize + 2];
+            stream.read_exact(&mut domain).unwrap();
+            domain.truncate(domain.len() - 2);
+            stream.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).unwrap();
+            stream.write_all(REDIRECT_RESPONSE).unwrap();
+            String::from_utf8(domain).unwrap()
+        });
+        let config = UrlWasherConfig {
+            tor_proxy: Url::parse(&format!("socks5h://127.0.0.1:{port}")).unwrap(),
+            ..Default::default()
+        };
+        let washer = short_link_washer("short.onion", config, RedirectWashPolicy::ViaTor);
+
+        let washed = washer
+            .wash(&Url::parse("http://short.onion/abc").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(washed.unwrap().as_str(), "https://example.com/a");
+        assert_eq!(proxied_domain.join().unwrap(), "short.onion");
+    }
+
+    #[cfg(feature = "network")]
+    #[tokio::test]
+    async fn retries_timed_out_redirect_resolution() {
+        use crate::RedirectWashPolicy;
+
+        // first two requests hang, so only retried wash gets an answer
+        let port = spawn_redirect_server(|i, _| (i >= 2).then_some(REDIRECT_RESPONSE));
+        let mut config = UrlWasherConfig {
+            redirect_timeout_ms: Some(200),
+            redirect_retry_backoff_ms: 10,
+            ..Default::default()
+        };
+        let short = Url::parse(&format!("http://localhost:{port}/abc")).unwrap();
+
+        let washer = short_link_washer("localhost", config.clone(), RedirectWashPolicy::Locally);
+        assert!(washer.wash(&short).await.is_err());
+
+        config.redirect_retries = 1;
+        let washer = short_link_washer("localhost", config, RedirectWashPolicy::Locally);
+        let washed = washer.wash(&short).await.unwrap();
+        assert_eq!(washed.unwrap().as_str(), "https://example.com/a");
+    }
+
+    #[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn falls_back_to_other_redirect_strategy() {
+        use crate::RedirectWashPolicy;
+
+        // mixer washing every link to `/a`, serving also short link `/moved` redirecting to `/b`
+        let port = spawn_redirect_server(|_, request| {
+            Some(if request.starts_with("GET /wash?") {
+                b"HTTP/1.1 200 OK\r\nContent-Length: 21\r\n\r\nhttps://example.com/a"
+            } else if request.starts_with("GET /moved ") {
+                b"HTTP/1.1 301 Moved Permanently\r\nLocation: https://example.com/b\r\nContent-Length: 0\r\n\r\n"
+            } else {
+                b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n"
+            })
+        });
+        let offline = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let offline_port = offline.local_addr().unwrap().port();
+        drop(offline);
+        let washer = |policy, mixer_port| {
+            let config = UrlWasherConfig {
+                mixer_instance: Some(
+                    Url::parse(&format!("http://127.0.0.1:{mixer_port}/")).unwrap(),
+                ),
+                ..Default::default()
+            };
+            short_link_washer("localhost", config, policy)
+        };
+        let short = Url::parse(&format!("http://localhost:{port}/abc")).unwrap();
+        let moved = Url::parse(&format!("http://localhost:{port}/moved")).unwrap();
+
+        let washer_locally = washer(RedirectWashPolicy::LocallyThenMixer, port);
+        let washed = washer_locally.wash(&short).await.unwrap();
+        assert_eq!(washed.unwrap().as_str(), "https://example.com/a");
+        let washed = washer_locally.wash(&moved).await.unwrap();
+        assert_eq!(washed.unwrap().as_str(), "https://example.com/b");
+
+        let washer_via_mixer = washer(RedirectWashPolicy::MixerThenLocally, offline_port);
+        let washed = washer_via_mixer.wash(&moved).await.unwrap();
+        assert_eq!(washed.unwrap().as_str(), "https://example.com/b");
+        assert!(washer_via_mixer.wash(&short).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn unrolls_links_of_generic_shorteners() {
+        let short = Url::parse("https://bit.ly/3xYzAbC").unwrap();
+        let store = Arc::new(MemoryRedirectStore::default());
+        store.insert(
+            short.clone(),
+            Url::parse("https://youtu.be/a?si=x").unwrap(),
+        );
+        let default = UrlWasher::default().with_redirect_store(store.clone());
+        assert_eq!(default.wash(&short).await.unwrap(), None);
+
+        let mut config = UrlWasherConfig::default();
+        config.shorteners.enabled = true;
+        let washer = UrlWasher::new(config).with_redirect_store(store);
+        assert_eq!(
+            washer.matching_rule(&short).unwrap().name,
+            crate::shorteners::RULE_NAME
+        );
+        let washed = washer.wash(&short).await.unwrap();
+        assert_eq!(washed.unwrap().as_str(), "https://youtu.be/a");
+        // link of unknown shortener is left alone unless detection is enabled
+        let unknown = Url::parse("https://xy.io/aB3dE").unwrap();
+        assert!(washer.matching_rule(&unknown).is_none());
+    }
+
+    #[tokio::test]
+    async fn refuses_blocklisted_destinations() {
+        let short = Url::parse("https://vm.tiktok.com/ZGJoJs8jb/").unwrap();
+        let store = Arc::new(MemoryRedirectStore::default());
+        store.insert(
+            short.clone(),
+            Url::parse("https://www.tiktok.com/@i0ki.clips/video/1").unwrap(),
+        );
+        let washer = UrlWasher::default().with_redirect_store(store);
+        washer
+            .blocklist()
+            .replace(parse_feed("# comment\n0.0.0.0 tiktok.com\n").collect());
+
+        let err = washer.wash(&short).await.unwrap_err();
+        let blocked = err.downcast_ref::<BlockedDestination>().unwrap();
+        assert_eq!(blocked.domain, "tiktok.com");
+        assert!(washer.cache_entries().is_empty());
+    }
+
+    #[test]
+    fn http_profile_applies_to_subdomains() {
+        let mut config = UrlWasherConfig::default();
+        let profile = HttpProfile {
+            user_agent: Some("curl/8.0".to_string()),
+            ..Default::default()
+        };
+        config
+            .http_profiles
+            .insert("tiktok.com".to_string(), profile.clone());
+        assert_eq!(config.http_profile("vm.tiktok.com"), Some(&profile));
+        assert_eq!(config.http_profile("tiktok.com"), Some(&profile));
+        assert_eq!(config.http_profile("nottiktok.com"), None);
+    }
+
+    #[test]
+    fn removes_encoded_query_params() {
+        let params = ["si".to_string()];
+        let tests = [
+            ("https://a.com/?s%69=1&t=1%3A2", "https://a.com/?t=1%3A2"),
+            ("https://a.com/?t=a+b&si&&x=", "https://a.com/?t=a+b&x="),
+            ("https://a.com/?si=1", "https://a.com/"),
+            ("https://a.com/", "https://a.com/"),
+        ];
+        for (dirty, clean) in tests {
+            let washed = remove_query_params(&Url::parse(dirty).unwrap(), &params);
+            assert_eq!(
+                washed.as_str(),
+                clean,
+                "Invalid wash result of dirty url {dirty}"
+            );
+        }
+    }
+
+    #[test]
+    fn rewrites_paths() {
+        let rewrite = PathRewrite {
+            pattern: Pattern::new("^/item/(\\d+)/.*$").unwrap(),
+            replacement: "/item/$1".to_string(),
+        };
+        let washed = rewrite_path(
+            &Url::parse("https://a.com/item/12/cool-name?x=1").unwrap(),
+            &rewrite,
+        );
+        assert_eq!(washed.as_str(), "https://a.com/item/12?x=1");
+        let washed = rewrite_path(
+            &Url::parse("https://a.com/other/12/name").unwrap(),
+            &rewrite,
+        );
+        assert_eq!(washed.as_str(), "https://a.com/other/12/name");
+    }
+
+    #[test]
+    fn sets_subdomains() {
+        let tests = [
+            (
+                "https://amazon.de/dp/B0",
+                "www",
+                "https://www.amazon.de/dp/B0",
+            ),
+            (
+                "https://smile.amazon.co.uk/dp/B0",
+                "www",
+                "https://www.amazon.co.uk/dp/B0",
+            ),
+            ("https://m.a.com/x?y=1", "", "https://a.com/x?y=1"),
+            ("https://127.0.0.1/x", "www", "https://127.0.0.1/x"),
+        ];
+        for (dirty, subdomain, clean) in tests {
+            let washed = set_subdomain(&Url::parse(dirty).unwrap(), subdomain);
+            assert_eq!(washed.as_str(), clean);
+        }
+        let program: WashingProgram =
+            serde_json::from_str(r#"{ "set_subdomain": "www" }"#).unwrap();
+        assert!(program == WashingProgram::SetSubdomain("www".to_string()));
+    }
+
+    #[tokio::test]
+    async fn canonicalizes_amazon_product_urls() {
+        let tests = [
+            (
+                "https://www.amazon.de/dp/B09B8V1LZ3/ref=sr_1_1?tag=abc-21&th=1",
+                "https://www.amazon.de/dp/B09B8V1LZ3",
+            ),
+            (
+                "https://www.amazon.com/Echo-Dot-5th-Gen/dp/B09B8V1LZ3/ref=sr_1_1?crid=2M3&keywords=echo&sr=8-1",
+                "https://www.amazon.com/dp/B09B8V1LZ3",
+            ),
+            (
+                "https://amazon.de/gp/product/B09B8V1LZ3?psc=1",
+                "https://www.amazon.de/dp/B09B8V1LZ3",
+            ),
+            (
+                "https://smile.amazon.co.uk/gp/aw/d/B09B8V1LZ3/ref=ox_sc_act_title_1",
+                "https://www.amazon.co.uk/dp/B09B8V1LZ3",
+            ),
+        ];
+        let washer = UrlWasher::default();
+        for (dirty, clean) in tests {
+            let washed = washer.wash(&Url::parse(dirty).unwrap()).await.unwrap();
+            assert_eq!(washed.unwrap().as_str(), clean, "{dirty}");
+        }
+
+        let search = Url::parse("https://www.amazon.de/s?k=echo&ref=nb_sb_noss").unwrap();
+        assert_eq!(washer.wash(&search).await.unwrap(), None);
+    }
+
+    #[test]
+    fn strips_path_segments() {
+        let patterns = [
+            Pattern::new("^ref=").unwrap(),
+            Pattern::new("^jsessionid=").unwrap(),
+        ];
+        let tests = [
+            (
+                "https://www.amazon.com/dp/B0/ref=sr_1_1?th=1",
+                "https://www.amazon.com/dp/B0?th=1",
+            ),
+            (
+                "https://a.com/shop;jsessionid=F00;lang=en/item",
+                "https://a.com/shop;lang=en/item",
+            ),
+            ("https://a.com/", "https://a.com/"),
+        ];
+        for (dirty, clean) in tests {
+            let washed = strip_path_segments(&Url::parse(dirty).unwrap(), &patterns);
+            assert_eq!(washed.as_str(), clean);
+        }
+    }
+
+    #[test]
+    fn matches_wildcard_subdomains() {
+        let rule = DirtyUrlRule {
+            domains: vec!["*.facebook.com".to_string()],
+            ..Default::default()
+        };
+        assert!(rule.matches_domain("l.facebook.com"));
+        assert!(rule.matches_domain("lm.facebook.com"));
+        assert!(rule.matches_domain("a.b.facebook.com"));
+        assert!(!rule.matches_domain("facebook.com"));
+        assert!(!rule.matches_domain("notfacebook.com"));
+    }
+
+    #[test]
+    fn matches_path_regex() {
+        let rule = DirtyUrlRule {
+            domains: vec!["www.instagram.com".to_string()],
+            path_regex: Some(Pattern::new("^/share/").unwrap()),
+            ..Default::default()
+        };
+        let matches = |url: &str| rule.matches(&Url::parse(url).unwrap());
+        assert!(matches("https://www.instagram.com/share/abc?igsh=1"));
+        assert!(!matches("https://www.instagram.com/p/abc?igsh=1"));
+        assert!(!matches("https://www.instagram.com/shared/abc"));
+    }
+
+    #[tokio::test]
+    async fn removes_params_matching_pattern() {
+        let rule = DirtyUrlRule {
+            name: "newsletter".to_string(),
+            domains: vec!["shop.example".to_string()],
+            washing_programs: vec![WashingProgram::remove_matching_params(&[
+                "^(?:mc_[ce]id|pk_.+|_hs.+)$",
+            ])],
+            ..Default::default()
+        };
+        let washer = UrlWasher::new(UrlWasherConfig::default()).with_rules(vec![rule]);
+        let washed = washer
+            .wash(
+                &Url::parse("https://shop.example/?mc_cid=1&mc_eid=2&pk_campaign=x&_hsenc=y&id=3")
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(washed.as_str(), "https://shop.example/?id=3");
+    }
+
+    #[tokio::test]
+    async fn washes_base64_encoded_destination() {
+        let rule = DirtyUrlRule {
+            name: "out.example".to_string(),
+            domains: vec!["out.example".to_string()],
+            washing_programs: vec![WashingProgram::extract_base64_url(&["dest"])],
+            ..Default::default()
+        };
+        let destination_rule = DirtyUrlRule {
+            name: "youtu.be".to_string(),
+            domains: vec!["youtu.be".to_string()],
+            washing_programs: vec![WashingProgram::remove_some_params(&["si"])],
+            ..Default::default()
+        };
+        let washer =
+            UrlWasher::new(UrlWasherConfig::default()).with_rules(vec![rule, destination_rule]);
+        let wash = |url: &str| {
+            let url = Url::parse(url).unwrap();
+            let washer = &washer;
+            async move { washer.wash(&url).await.unwrap().map(String::from) }
+        };
+
+        // https://youtu.be/lSwnPoo9ZK0?si=TrackingParamValue
+        assert_eq!(
+            wash("https://out.example/?dest=aHR0cHM6Ly95b3V0dS5iZS9sU3duUG9vOVpLMD9zaT1UcmFja2luZ1BhcmFtVmFsdWU%3D&src=mail").await,
+            Some("https://youtu.be/lSwnPoo9ZK0".to_string())
+        );
+        // https://a.com/?q=>>> in url-safe alphabet, unpadded
+        assert_eq!(
+            wash("https://out.example/?dest=aHR0cHM6Ly9hLmNvbS8_cT0-Pj4").await,
+            Some("https://a.com/?q=%3E%3E%3E".to_string())
+        );
+        // javascript:alert(1)
+        assert_eq!(
+            wash("https://out.example/?dest=amF2YXNjcmlwdDphbGVydCgxKQ").await,
+            Some("https://out.example/?dest=amF2YXNjcmlwdDphbGVydCgxKQ".to_string())
+        );
+    }
+
+    #[test]
+    fn removes_fragment() {
+        let url = Url::parse("https://a.com/doc#intro:~:text=hello").unwrap();
+        let directive = Pattern::new(":~:.*").unwrap();
+        assert_eq!(
+            remove_fragment(&url, Some(&directive)).as_str(),
+            "https://a.com/doc#intro"
+        );
+        assert_eq!(remove_fragment(&url, None).as_str(), "https://a.com/doc");
+        let url = Url::parse("https://a.com/doc#:~:text=hello").unwrap();
+        assert_eq!(
+            remove_fragment(&url, Some(&directive)).as_str(),
+            "https://a.com/doc"
+        );
+    }
+
+    #[test]
+    fn keeps_only_allowed_params() {
+        let params = ["v".to_string(), "t".to_string()];
+        let washed = keep_only_params(
+            &Url::parse("https://youtube.com/watch?v=abc&pp=x&t=65&feature=share").unwrap(),
+            &params,
+        );
+        assert_eq!(washed.as_str(), "https://youtube.com/watch?v=abc&t=65");
+    }
+
+    #[test]
+    fn removes_params_by_prefix() {
+        let prefixes = ["utm_".to_string()];
+        let washed = remove_params_by_prefix(
+            &Url::parse("https://a.com/?utm_source=x&id=1&utm_medium=y&autm_=z").unwrap(),
+            &prefixes,
+        );
+        assert_eq!(washed.as_str(), "https://a.com/?id=1&autm_=z");
+    }
+
+    #[test]
+    fn redacts_query_param_values() {
+        let params = ["ref".to_string()];
+        let tests = [
+            ("https://a.com/?ref=abc&id=1", "https://a.com/?ref=&id=1"),
+            (
+                "https://a.com/?id=1&re%66=abc",
+                "https://a.com/?id=1&re%66=",
+            ),
+            ("https://a.com/?ref", "https://a.com/?ref="),
+        ];
+        for (dirty, clean) in tests {
+            let washed = redact_query_params(&Url::parse(dirty).unwrap(), &params);
+            assert_eq!(washed.as_str(), clean);
+        }
+    }
+
+    #[test]
+    fn sorts_query_params() {
+        let sorted = sort_query_params(
+            &Url::parse("https://a.com/?q=a%20b&%62=2&id=2&a=1&id=1&&c").unwrap(),
+        );
+        assert_eq!(
+            sorted.as_str(),
+            "https://a.com/?a=1&%62=2&c&id=2&id=1&q=a%20b"
+        );
+        let url = Url::parse("https://a.com/").unwrap();
+        assert_eq!(sort_query_params(&url), url);
+    }
+
+    #[test]
+    fn normalizes_path_slashes() {
+        let tests = [
+            (
+                "https://a.com//a///b/?x=1",
+                TrailingSlash::Keep,
+                "https://a.com/a/b/?x=1",
+            ),
+            (
+                "https://a.com/a//b",
+                TrailingSlash::Keep,
+                "https://a.com/a/b",
+            ),
+            (
+                "https://a.com/a//b/",
+                TrailingSlash::Strip,
+                "https://a.com/a/b",
+            ),
+            (
+                "https://a.com/a//b",
+                TrailingSlash::Append,
+                "https://a.com/a/b/",
+            ),
+            (
+                "https://a.com/a/b.pdf",
+                TrailingSlash::Append,
+                "https://a.com/a/b.pdf",
+            ),
+            ("https://a.com//", TrailingSlash::Strip, "https://a.com/"),
+        ];
+        for (dirty, trailing_slash, clean) in tests {
+            let normalized = normalize_path(&Url::parse(dirty).unwrap(), trailing_slash);
+            assert_eq!(normalized.as_str(), clean);
+        }
+        let program: WashingProgram =
+            serde_json::from_str(r#"{ "normalize_path": "append" }"#).unwrap();
+        assert!(program == WashingProgram::NormalizePath(TrailingSlash::Append));
+    }
+
+    #[tokio::test]
+    async fn sorts_params_of_every_washed_url_if_enabled() {
+        let washer = UrlWasher::new(UrlWasherConfig {
+            sort_query_params: true,
+            ..Default::default()
+        });
+        let washed = washer
+            .wash(&Url::parse("https://open.spotify.com/track/1?si=x&z=1&a=2").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            washed.unwrap().as_str(),
+            "https://open.spotify.com/track/1?a=2&z=1"
+        );
+        let report = washer
+            .wash_with_report(&Url::parse("https://shop.example/?b=1&a=2").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(report.cleaned, "https://shop.example/?a=2&b=1");
+        assert!(report.removed_params.is_empty());
+        let sorted = Url::parse("https://shop.example/?a=2&b=1").unwrap();
+        assert_eq!(washer.wash(&sorted).await.unwrap(), None);
+    }
 }
@@ -0,0 +1,101 @@
+//! Finding markdown code, so urls in documentation examples can be left as they were written.
+
+use std::ops::Range;
+
+/// Byte ranges of fenced code blocks and inline code spans, in order.
+/// Unclosed fence runs to the end of text, like in CommonMark.
+pub(crate) fn code_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    // fence char, fence length and offset of opening line
+    let mut open_fence: Option<(char, usize, usize)> = None;
+    let mut prose_start = 0;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start_matches(' ');
+        let indented = line.len() - trimmed.len() > 3;
+        let fence_char = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'));
+        let fence_len = fence_char.map_or(0, |fence_char| {
+            trimmed.chars().take_while(|c| *c == fence_char).count()
+        });
+        match open_fence {
+            None if !indented && fence_len >= 3 => {
+                ranges.extend(inline_code_ranges(&text[prose_start..offset], prose_start));
+                open_fence = fence_char.map(|fence_char| (fence_char, fence_len, offset));
+            }
+            Some((open_char, open_len, start))
+                if !indented
+                    && fence_char == Some(open_char)
+                    && fence_len >= open_len
+                    && trimmed[fence_len..].trim().is_empty() =>
+            {
+                ranges.push(start..offset + line.len());
+                open_fence = None;
+                prose_start = offset + line.len();
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+    match open_fence {
+        Some((_, _, start)) => ranges.push(start..text.len()),
+        None => ranges.extend(inline_code_ranges(&text[prose_start..], prose_start)),
+    }
+    ranges
+}
+
+/// Code span opens with backtick run and closes with run of the same length,
+/// unmatched runs are literal backticks.
+fn inline_code_ranges(text: &str, base: usize) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let backtick_run = |from: usize| bytes[from..].iter().take_while(|b| **b == b'`').count();
+    let mut ranges = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] != b'`' {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        let opening_len = backtick_run(start);
+        index += opening_len;
+        let mut search = index;
+        while search < bytes.len() {
+            if bytes[search] != b'`' {
+                search += 1;
+                continue;
+            }
+            let closing_len = backtick_run(search);
+            search += closing_len;
+            if closing_len == opening_len {
+                ranges.push(base + start..base + search);
+                index = search;
+                break;
+            }
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::code_ranges;
+
+    #[test]
+    fn finds_code_spans_and_fences() {
+        let text = "see `a` and ``b ` c``\n```sh\ncurl x\n```\nafter `d`\n~~~\nunclosed";
+        let code = code_ranges(text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            code,
+            vec![
+                "`a`",
+                "``b ` c``",
+                "```sh\ncurl x\n```\n",
+                "`d`",
+                "~~~\nunclosed"
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Counters and histograms of washes recorded through [`metrics`] crate (`metrics` feature),
+//! exported by whatever recorder app installs, e.g. prometheus exporter of mixer.
+//!
+//! - `urlwasher_washes_total` labelled by `outcome` (`washed`, `unchanged` or `failed`)
+//! - `urlwasher_wash_duration_seconds`
+//! - `urlwasher_cache_hits_total`
+//! - `urlwasher_redirect_resolutions_total` labelled by `policy` and `outcome` (`resolved`, `unresolved` or `failed`)
+//! - `urlwasher_redirect_resolution_duration_seconds` labelled by `policy`
+//!
+//! Durations are not recorded in wasm, see [`crate::WashEvent::duration`].
+
+use std::time::Instant;
+
+use url::Url;
+
+use crate::RedirectWashPolicy;
+
+/// Outcome label of wash, also recorded in its tracing span.
+pub(crate) fn wash_outcome(washed: &anyhow::Result<Option<Url>>) -> &'static str {
+    match washed {
+        Ok(Some(_)) => "washed",
+        Ok(None) => "unchanged",
+        Err(_) => "failed",
+    }
+}
+
+/// Outcome label of redirect resolution, also recorded in its tracing span.
+pub(crate) fn redirect_outcome(resolved: &anyhow::Result<Result<Url, Url>>) -> &'static str {
+    match resolved {
+        Ok(Ok(_)) => "resolved",
+        Ok(Err(_)) => "unresolved",
+        Err(_) => "failed",
+    }
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn wash(washed: &anyhow::Result<Option<Url>>, started_at: Option<Instant>) {
+    let outcome = wash_outcome(washed);
+    metrics::counter!("urlwasher_washes_total", "outcome" => outcome).increment(1);
+    if let Some(started_at) = started_at {
+        metrics::histogram!("urlwasher_wash_duration_seconds").record(started_at.elapsed());
+    }
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn cache_hit() {
+    metrics::counter!("urlwasher_cache_hits_total").increment(1);
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn redirect_resolution(
+    policy: &RedirectWashPolicy,
+    resolved: &anyhow::Result<Result<Url, Url>>,
+    started_at: Option<Instant>,
+) {
+    let outcome = redirect_outcome(resolved);
+    let policy = policy.to_string();
+    metrics::counter!(
+        "urlwasher_redirect_resolutions_total",
+        "policy" => policy.clone(),
+        "outcome" => outcome
+    )
+    .increment(1);
+    if let Some(started_at) = started_at {
+        metrics::histogram!("urlwasher_redirect_resolution_duration_seconds", "policy" => policy)
+            .record(started_at.elapsed());
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn wash(_washed: &anyhow::Result<Option<Url>>, _started_at: Option<Instant>) {}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn cache_hit() {}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn redirect_resolution(
+    _policy: &RedirectWashPolicy,
+    _resolved: &anyhow::Result<Result<Url, Url>>,
+    _started_at: Option<Instant>,
+) {
+}
@@ -0,0 +1,55 @@
+//! Types exposed by mixer http api, see [`crate::mixer_client`] for typed client of all its endpoints.
+
+#[cfg(feature = "network")]
+use anyhow::{anyhow, Context};
+#[cfg(feature = "network")]
+use url::Url;
+
+#[cfg(feature = "network")]
+use crate::mixer_client::MixerClient;
+pub use urldebloater_proto::{
+    directory::{DirectoryEntry, InstancePolicy, MixerDirectory, SignedDirectory},
+    mixer::{ApiKeyUsage, MixerMotd, MixerVersion, WashQuery},
+};
+
+#[cfg(feature = "network")]
+pub async fn fetch_motd(
+    http_client: &reqwest::Client,
+    mixer_instance: &Url,
+) -> anyhow::Result<MixerMotd> {
+    let client = MixerClient::new(http_client.clone(), mixer_instance.clone());
+    client.motd().await.context("fetch mixer motd")
+}
+
+/// Fetches directory of public mixer instances, its signature is verified by caller.
+#[cfg(feature = "network")]
+pub async fn fetch_directory(
+    http_client: &reqwest::Client,
+    directory_url: &Url,
+) -> anyhow::Result<SignedDirectory> {
+    let resp = http_client
+        .get(directory_url.clone())
+        .send()
+        .await
+        .context("send mixer directory request")?;
+    if !resp.status().is_success() {
+        return Err(anyhow!(
+            "Invalid mixer directory response status: {}",
+            resp.status()
+        ));
+    }
+    resp.json().await.context("read mixer directory")
+}
+
+/// Measures round trip of mixer `/version` request, e.g. to let users pick the closest instance.
+/// Unavailable in browsers, they have no [`std::time::Instant`].
+#[cfg(all(feature = "network", not(target_arch = "wasm32")))]
+pub async fn ping(
+    http_client: &reqwest::Client,
+    mixer_instance: &Url,
+) -> anyhow::Result<(MixerVersion, std::time::Duration)> {
+    let client = MixerClient::new(http_client.clone(), mixer_instance.clone());
+    let started_at = std::time::Instant::now();
+    let version = client.version().await.context("fetch mixer version")?;
+    Ok((version, started_at.elapsed()))
+}
@@ -0,0 +1,292 @@
+//! Typed client of mixer http api, used by washer for [`crate::RedirectWashPolicy::ViaMixer`]
+//! and usable by other tools, e.g. to wash many urls with one instance.
+
+use std::fmt::Display;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+use futures_util::{stream, StreamExt};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use url::Url;
+
+use crate::mixer_api::{ApiKeyUsage, MixerMotd, MixerVersion, WashQuery};
+
+/// Concurrency of [`MixerClient::wash_batch`] polite to public instances.
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 8;
+
+/// Client of one mixer instance.
+#[derive(Clone)]
+pub struct MixerClient {
+    http_client: reqwest::Client,
+    instance: Url,
+    api_key: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    timeout: Option<Duration>,
+}
+
+/// Error of mixer request.
+#[derive(Debug)]
+pub enum MixerError {
+    /// Request could not be sent or response could not be read, e.g. instance is down.
+    Request(reqwest::Error),
+    /// Mixer refused request, `message` is body of its response (e.g. `too many requests`).
+    Status { status: StatusCode, message: String },
+    /// Response is not what endpoint responds with, e.g. url is not a valid url.
+    InvalidResponse(String),
+    /// Endpoints cannot be resolved relative to instance url, e.g. `mailto:` url.
+    InvalidInstance(url::ParseError),
+}
+
+impl MixerError {
+    /// Rate limit or daily quota of instance (or api key) is exceeded, request can be retried later.
+    pub fn is_rate_limited(&self) -> bool {
+        self.status() == Some(StatusCode::TOO_MANY_REQUESTS)
+    }
+
+    /// Instance is in maintenance mode, `message` explains downtime.
+    pub fn is_maintenance(&self) -> bool {
+        self.status() == Some(StatusCode::SERVICE_UNAVAILABLE)
+    }
+
+    /// Instance requires api key, or the one sent is invalid.
+    pub fn is_unauthorized(&self) -> bool {
+        self.status() == Some(StatusCode::UNAUTHORIZED)
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            MixerError::Request(err) => err.status(),
+            MixerError::Status { status, .. } => Some(*status),
+            MixerError::InvalidResponse(_) | MixerError::InvalidInstance(_) => None,
+        }
+    }
+}
+
+impl Display for MixerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MixerError::Request(err) => write!(f, "mixer request failed: {err}"),
+            MixerError::Status { status, message } => {
+                write!(f, "mixer responded with {status}: {message}")
+            }
+            MixerError::InvalidResponse(reason) => write!(f, "invalid mixer response: {reason}"),
+            MixerError::InvalidInstance(err) => write!(f, "invalid mixer instance: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for MixerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MixerError::Request(err) => Some(err),
+            MixerError::InvalidInstance(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for MixerError {
+    fn from(err: reqwest::Error) -> Self {
+        MixerError::Request(err)
+    }
+}
+
+impl MixerClient {
+    /// `instance` is base url of mixer, endpoints are resolved relative to it,
+    /// so instance under path must end with slash (`https://example.com/mixer/`).
+    pub fn new(http_client: reqwest::Client, instance: Url) -> Self {
+        Self {
+            http_client,
+            instance,
+            api_key: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            timeout: None,
+        }
+    }
+
+    /// Api key of private instance, sent as bearer token with every request.
+    pub fn api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    /// Time limit of each request, unlimited by default. Browser fetch has no timeout.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn instance(&self) -> &Url {
+        &self.instance
+    }
+
+    /// Washes `url` with rules (and short link resolution) of instance.
+    pub async fn wash(&self, url: &Url) -> Result<Url, MixerError> {
+        let query = WashQuery {
+            url: url.to_string(),
+        };
+        let washed = self
+            .send(self.get("wash")?.query(&query))
+            .await?
+            .text()
+            .await?;
+        Url::parse(&washed).map_err(|err| MixerError::InvalidResponse(format!("{washed}: {err}")))
+    }
+
+    /// Washes `urls` sending at most `concurrency` requests at once, results are in order of `urls`.
+    pub async fn wash_batch(
+        &self,
+        urls: &[Url],
+        concurrency: usize,
+    ) -> Vec<Result<Url, MixerError>> {
+        stream::iter(urls)
+            .map(|url| self.wash(url))
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    pub async fn version(&self) -> Result<MixerVersion, MixerError> {
+        self.get_json("version").await
+    }
+
+    /// Announcements of instance operator.
+    pub async fn motd(&self) -> Result<MixerMotd, MixerError> {
+        self.get_json("motd").await
+    }
+
+    /// Usage of api key client sends, see [`crate::mixer_api::ApiKeyUsage`].
+    pub async fn usage(&self) -> Result<ApiKeyUsage, MixerError> {
+        self.get_json("usage").await
+    }
+
+    fn get(&self, endpoint: &str) -> Result<RequestBuilder, MixerError> {
+        let url = self
+            .instance
+            .join(endpoint)
+            .map_err(MixerError::InvalidInstance)?;
+        let mut request = self.http_client.get(url);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        Ok(request)
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T, MixerError> {
+        let resp = self.send(self.get(endpoint)?).await?;
+        let body = resp.bytes().await?;
+        serde_json::from_slice(&body).map_err(|err| MixerError::InvalidResponse(err.to_string()))
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<reqwest::Response, MixerError> {
+        let resp = request.send().await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let message = resp.text().await.unwrap_or_default();
+            return Err(MixerError::Status { status, message });
+        }
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    /// Mixer washing urls by dropping their query, rate limiting urls with `limited` in them.
+    /// Usage is served only to requests with `secret` api key.
+    async fn serve_mixer() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let instance = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut request = vec![0; 4096];
+                    let len = stream.read(&mut request).await.unwrap();
+                    let request = String::from_utf8_lossy(&request[..len]).to_string();
+                    let target = request.split(' ').nth(1).unwrap_or_default();
+                    let target = Url::parse("http://mixer").unwrap().join(target).unwrap();
+                    let authorized = request
+                        .to_ascii_lowercase()
+                        .contains("authorization: bearer secret");
+                    let (status, body) = match target.path() {
+                        "/wash" => {
+                            let (_, url) =
+                                target.query_pairs().find(|(key, _)| key == "url").unwrap();
+                            if url.contains("limited") {
+                                ("429 Too Many Requests", "too many requests".to_string())
+                            } else {
+                                let mut url = Url::parse(&url).unwrap();
+                                url.set_query(None);
+                                ("200 OK", url.to_string())
+                            }
+                        }
+                        "/usage" if authorized => (
+                            "200 OK",
+                            r#"{ "name": "friends", "requests_today": 1, "daily_quota": null, "requests_total": 2 }"#
+                                .to_string(),
+                        ),
+                        "/usage" => ("401 Unauthorized", "missing api key".to_string()),
+                        "/motd" => (
+                            "200 OK",
+                            r#"{ "motd": "hi", "maintenance": null }"#.to_string(),
+                        ),
+                        _ => ("404 Not Found", String::new()),
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                });
+            }
+        });
+        instance
+    }
+
+    #[tokio::test]
+    async fn washes_urls_with_mixer() {
+        let client = MixerClient::new(
+            crate::http_client_builder().build().unwrap(),
+            serve_mixer().await,
+        )
+        .timeout(Some(Duration::from_secs(5)));
+        let urls = [
+            "https://a.com/1?si=x",
+            "https://limited.com/",
+            "https://b.com/2?si=y",
+        ]
+        .map(|url| Url::parse(url).unwrap());
+
+        let washed = client.wash_batch(&urls, 2).await;
+        assert_eq!(washed[0].as_ref().unwrap().as_str(), "https://a.com/1");
+        let err = washed[1].as_ref().unwrap_err();
+        assert!(err.is_rate_limited());
+        assert_eq!(
+            err.to_string(),
+            "mixer responded with 429 Too Many Requests: too many requests"
+        );
+        assert_eq!(washed[2].as_ref().unwrap().as_str(), "https://b.com/2");
+
+        assert_eq!(client.motd().await.unwrap().motd.as_deref(), Some("hi"));
+        assert_eq!(
+            client.version().await.unwrap_err().status(),
+            Some(StatusCode::NOT_FOUND)
+        );
+        assert!(client.usage().await.unwrap_err().is_unauthorized());
+        let client = client.api_key(Some("secret".to_string()));
+        assert_eq!(client.usage().await.unwrap().name, "friends");
+    }
+}
@@ -0,0 +1,117 @@
+//! Matching of rule [`crate::DirtyUrlRule::sites`] by registrable domain (eTLD+1),
+//! using [Public Suffix List](https://publicsuffix.org/).
+//!
+//! List is not bundled, apps load it with [`set_list`] (e.g. fetched with [`fetch`]).
+//! Until then registrable domain is guessed: last two labels, or three if second-level domain
+//! of country looks generic (`co.uk`, `com.br`).
+
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Context};
+use publicsuffix::{List, Psl};
+#[cfg(feature = "network")]
+use url::Url;
+
+pub const LIST_URL: &str = "https://publicsuffix.org/list/public_suffix_list.dat";
+
+static LIST: OnceLock<List> = OnceLock::new();
+
+/// Second-level domains treated as suffixes when list is not loaded.
+const COUNTRY_SECOND_LEVELS: &[&str] = &["ac", "co", "com", "edu", "gov", "net", "org", "ne"];
+
+/// Sets list used by all washers, fails if it was already set.
+pub fn set_list(list: List) -> anyhow::Result<()> {
+    LIST.set(list)
+        .map_err(|_| anyhow!("public suffix list is already set"))
+}
+
+pub fn parse(text: &str) -> anyhow::Result<List> {
+    text.parse()
+        .map_err(|err| anyhow!("{err:?}"))
+        .context("parse public suffix list")
+}
+
+/// Downloads list, e.g. from [`LIST_URL`].
+#[cfg(feature = "network")]
+pub async fn fetch(http_client: &reqwest::Client, url: &Url) -> anyhow::Result<List> {
+    let resp = http_client
+        .get(url.clone())
+        .send()
+        .await
+        .with_context(|| format!("fetch public suffix list {url}"))?;
+    if !resp.status().is_success() {
+        return Err(anyhow!(
+            "Invalid status of public suffix list {url}: {}",
+            resp.status()
+        ));
+    }
+    parse(&resp.text().await.context("read public suffix list")?)
+}
+
+/// Registrable domain of `domain`, e.g. `tiktok.com` of `vm.tiktok.com`.
+/// [`None`] if `domain` is public suffix itself.
+pub fn registrable_domain(domain: &str) -> Option<&str> {
+    let domain = domain.trim_end_matches('.');
+    let registrable_len = match LIST.get() {
+        Some(list) => list.domain(domain.as_bytes())?.as_bytes().len(),
+        None => guess_registrable_domain(domain)?.len(),
+    };
+    Some(&domain[domain.len() - registrable_len..])
+}
+
+fn guess_registrable_domain(domain: &str) -> Option<&str> {
+    let labels = domain.split('.').collect::<Vec<_>>();
+    let mut kept = 2;
+    if labels.len() >= 2
+        && labels[labels.len() - 1].len() == 2
+        && COUNTRY_SECOND_LEVELS.contains(&labels[labels.len() - 2])
+    {
+        kept = 3;
+    }
+    if labels.len() < kept {
+        return None;
+    }
+    let suffix_len = labels[labels.len() - kept..]
+        .iter()
+        .map(|label| label.len())
+        .sum::<usize>()
+        + kept
+        - 1;
+    Some(&domain[domain.len() - suffix_len..])
+}
+
+/// Whether `domain` belongs to `site`: registrable domain (`tiktok.com`),
+/// or registrable domain on any public suffix (`tiktok.*`, matching e.g. `tiktok.co.uk`).
+pub fn matches_site(site: &str, domain: &str) -> bool {
+    let Some(registrable) = registrable_domain(domain) else {
+        return false;
+    };
+    match site.strip_suffix(".*") {
+        Some(name) => registrable
+            .split_once('.')
+            .is_some_and(|(registrable_name, _)| registrable_name == name),
+        None => registrable == site,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_sites() {
+        assert!(matches_site("tiktok.com", "vm.tiktok.com"));
+        assert!(matches_site("tiktok.com", "tiktok.com"));
+        assert!(!matches_site("tiktok.com", "tiktok.com.evil.net"));
+        assert!(matches_site("tiktok.*", "www.tiktok.co.uk"));
+        assert!(matches_site("tiktok.*", "vt.tiktok.de"));
+        assert!(!matches_site("tiktok.*", "tiktok.evil.net"));
+        assert!(!matches_site("co.uk", "co.uk"));
+
+        let list = parse("// ===BEGIN ICANN DOMAINS===\ncom\nuk\nco.uk\n// ===END ICANN DOMAINS===\n// ===BEGIN PRIVATE DOMAINS===\ngithub.io\n// ===END PRIVATE DOMAINS===\n").unwrap();
+        assert_eq!(
+            list.domain(b"user.github.io").unwrap().as_bytes(),
+            b"user.github.io"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Rules defined by user in JSON file, loaded at runtime on top of built-in ones.
+//!
+//! ```json
+//! {
+//!   "version": 2,
+//!   "rules": [
+//!     {
+//!       "name": "example.com",
+//!       "domains": ["example.com", "www.example.com"],
+//!       "washing_programs": [{ "remove_some_params": ["ref", "campaign"] }]
+//!     }
+//!   ],
+//!   "patches": [{ "name": "youtu.be", "domains": ["youtu.be", "www.youtu.be"] }],
+//!   "disabled": ["reddit.com"]
+//! }
+//! ```
+//!
+//! Rules are serialized [`DirtyUrlRule`]s.
+//! Rule with the same name as built-in one replaces it, while patch changes only fields it sets,
+//! see [`crate::rule_layers`]. Optional version lets subscribers reject outdated rule sets.
+
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::{
+    rule_layers::{RuleLayer, RulePatch},
+    DirtyUrlRule, RuleName,
+};
+
+pub const LAYER_NAME: &str = "file";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    #[serde(default)]
+    version: Option<u32>,
+    #[serde(default)]
+    rules: Vec<DirtyUrlRule>,
+    #[serde(default)]
+    patches: Vec<RulePatch>,
+    /// Names of built-in rules to drop.
+    #[serde(default)]
+    disabled: Vec<RuleName>,
+}
+
+/// Reads rule file, see [module docs](self) for format.
+pub fn load(path: &Path) -> anyhow::Result<RuleLayer> {
+    let json = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    import(&json).with_context(|| format!("load rules from {}", path.display()))
+}
+
+/// Converts rule file contents to rule layer, to be composed after built-in rules.
+pub fn import(json: &[u8]) -> anyhow::Result<RuleLayer> {
+    let file: RuleFile = serde_json::from_slice(json).context("deserialize rules")?;
+    for rule in &file.rules {
+        if rule.domains.is_empty() && rule.sites.is_empty() && rule.url_pattern.is_none() {
+            anyhow::bail!(
+                "rule {}: one of domains, sites or url_pattern must be set",
+                rule.name
+            );
+        }
+    }
+    let mut layer = RuleLayer::new(LAYER_NAME, file.rules);
+    layer.disabled = file.disabled;
+    layer.patches = file.patches;
+    layer.version = file.version;
+    Ok(layer)
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::*;
+    use crate::{UrlWasher, UrlWasherConfig};
+
+    #[tokio::test]
+    async fn merges_file_rules_with_builtin() {
+        let layer = import(
+            br#"{
+                "rules": [
+                    {
+                        "name": "example.com",
+                        "domains": ["example.com"],
+                        "washing_programs": [{ "remove_matching_params": ["^ref_"] }]
+                    }
+                ],
+                "disabled": ["youtu.be"]
+            }"#,
+        )
+        .unwrap();
+        let url_washer = UrlWasher::new(UrlWasherConfig::default()).with_rule_layer(layer);
+
+        let washed = url_washer
+            .wash(&Url::parse("https://example.com/a?ref_src=x&id=1").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(washed.unwrap().as_str(), "https://example.com/a?id=1");
+        let washed = url_washer
+            .wash(&Url::parse("https://open.spotify.com/track/1?si=x").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(washed.unwrap().as_str(), "https://open.spotify.com/track/1");
+        let washed = url_washer
+            .wash(&Url::parse("https://youtu.be/abc?si=x").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(washed, None);
+
+        assert!(import(br#"{ "rules": [{ "name": "a", "washing_programs": [] }] }"#).is_err());
+    }
+
+    #[test]
+    fn round_trips_builtin_rules() {
+        let json = serde_json::to_vec(&serde_json::json!({ "rules": crate::rule_set() })).unwrap();
+        let layer = import(&json).unwrap();
+        assert_eq!(layer.rules.len(), crate::rule_set().len());
+        assert_eq!(
+            serde_json::to_value(&layer.rules).unwrap(),
+            serde_json::to_value(crate::rule_set()).unwrap()
+        );
+        assert!(layer
+            .rules
+            .iter()
+            .zip(crate::rule_set())
+            .all(|(imported, builtin)| imported.washing_programs == builtin.washing_programs));
+    }
+}
@@ -0,0 +1,361 @@
+//! Composition of rule set from layers, e.g. built-in defaults, remote subscriptions and user overrides.
+//!
+//! Layers are applied in order, each one in three steps:
+//! 1. its disabled rules are removed,
+//! 2. its rule replaces rule with the same name from earlier layers (keeping position of replaced rule),
+//!    new rules are appended in order of appearance,
+//! 3. its patches change only fields they set in rules composed so far.
+//!
+//! Composition never fails, problems (e.g. patch of unknown rule) are reported as [`Conflict`]s.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{DirtyUrlRule, Pattern, RuleName, WashingProgram};
+
+#[derive(Clone)]
+pub struct RuleLayer {
+    /// Shown in [`RuleOrigin`], e.g. "default", pack name or "user".
+    pub name: String,
+    pub rules: Vec<DirtyUrlRule>,
+    /// Names of rules from earlier layers removed from effective rule set.
+    pub disabled: Vec<RuleName>,
+    /// Changes of single fields of rules from earlier layers, e.g. extra domain of built-in rule.
+    pub patches: Vec<RulePatch>,
+    /// Version of layer contents, [`crate::RULE_SET_VERSION`] for built-in rules.
+    /// Remote rule set older than the one washer runs is rejected, see [`crate::rule_source`].
+    pub version: Option<u32>,
+}
+
+impl RuleLayer {
+    pub fn new(name: impl Into<String>, rules: Vec<DirtyUrlRule>) -> Self {
+        Self {
+            name: name.into(),
+            rules,
+            disabled: Vec::new(),
+            patches: Vec::new(),
+            version: None,
+        }
+    }
+
+    /// Layer of built-in [`crate::rule_set`].
+    pub fn builtin() -> Self {
+        let mut layer = Self::new("default", crate::rule_set().clone());
+        layer.version = Some(crate::RULE_SET_VERSION);
+        layer
+    }
+}
+
+/// Fields replacing ones of rule named [`RulePatch::name`], unset fields are kept.
+/// Serialized in format of [`crate::rule_file`].
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RulePatch {
+    pub name: RuleName,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domains: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sites: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_pattern: Option<Vec<Option<String>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_regex: Option<Pattern>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub washing_programs: Option<Vec<WashingProgram>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_pattern: Option<Pattern>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_exceptions: Option<Vec<Pattern>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_sites: Option<Vec<String>>,
+}
+
+impl RulePatch {
+    pub fn apply(&self, rule: &mut DirtyUrlRule) {
+        fn set<T: Clone>(field: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+        set(&mut rule.domains, &self.domains);
+        set(&mut rule.sites, &self.sites);
+        set(&mut rule.path_pattern, &self.path_pattern);
+        if let Some(path_regex) = &self.path_regex {
+            rule.path_regex = Some(path_regex.clone());
+        }
+        set(&mut rule.washing_programs, &self.washing_programs);
+        if let Some(url_pattern) = &self.url_pattern {
+            rule.url_pattern = Some(url_pattern.clone());
+        }
+        set(&mut rule.url_exceptions, &self.url_exceptions);
+        set(&mut rule.redirect_sites, &self.redirect_sites);
+    }
+}
+
+/// Where effective rule comes from.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RuleOrigin {
+    pub rule: RuleName,
+    /// Layer defining effective version of rule.
+    pub layer: String,
+    /// Earlier layers that defined rule with the same name, in order.
+    pub overridden_layers: Vec<String>,
+    /// Layers that patched rule after it was defined, in order.
+    pub patched_by: Vec<String>,
+}
+
+/// Problem found while composing layers, composed rule set is still usable.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Conflict {
+    /// Layer defines more rules with the same name, the last one is used.
+    DuplicateRule { rule: RuleName, layer: String },
+    /// Rule disabled or patched by layer is not defined by earlier layers, so layer does not change it.
+    MissingRule { rule: RuleName, layer: String },
+    /// Earlier rule matches every url on `domain`, so rule never washes them
+    /// unless [`crate::UrlWasherConfig::apply_all_matching_rules`] is enabled.
+    ShadowedDomain {
+        domain: String,
+        rule: RuleName,
+        shadowed_by: RuleName,
+    },
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Conflict::DuplicateRule { rule, layer } => {
+                write!(f, "layer {layer} defines rule {rule} more than once")
+            }
+            Conflict::MissingRule { rule, layer } => {
+                write!(f, "layer {layer} changes undefined rule {rule}")
+            }
+            Conflict::ShadowedDomain {
+                domain,
+                rule,
+                shadowed_by,
+            } => write!(f, "rule {rule} is shadowed by {shadowed_by} on {domain}"),
+        }
+    }
+}
+
+/// Rule set composed from layers, ready to be used by [`crate::UrlWasher::with_rules`].
+pub struct RuleSet {
+    pub rules: Vec<DirtyUrlRule>,
+    pub origins: Vec<RuleOrigin>,
+    /// Highest version of composed layers.
+    pub version: Option<u32>,
+    pub conflicts: Vec<Conflict>,
+}
+
+pub fn compose(layers: &[RuleLayer]) -> RuleSet {
+    let mut composed: Vec<(DirtyUrlRule, RuleOrigin)> = Vec::new();
+    let mut conflicts = Vec::new();
+    for layer in layers {
+        for disabled in &layer.disabled {
+            let before = composed.len();
+            composed.retain(|(rule, _)| rule.name != *disabled);
+            if composed.len() == before {
+                conflicts.push(Conflict::MissingRule {
+                    rule: disabled.clone(),
+                    layer: layer.name.clone(),
+                });
+            }
+        }
+        for (index, rule) in layer.rules.iter().enumerate() {
+            if layer.rules[..index]
+                .iter()
+                .any(|earlier| earlier.name == rule.name)
+            {
+                conflicts.push(Conflict::DuplicateRule {
+                    rule: rule.name.clone(),
+                    layer: layer.name.clone(),
+                });
+            }
+            match composed
+                .iter_mut()
+                .find(|(existing, _)| existing.name == rule.name)
+            {
+                Some((existing, origin)) => {
+                    *existing = rule.clone();
+                    let overridden = std::mem::replace(&mut origin.layer, layer.name.clone());
+                    // rule defined twice by the same layer does not override itself
+                    if overridden != layer.name {
+                        origin.overridden_layers.push(overridden);
+                    }
+                    origin.patched_by.clear();
+                }
+                None => composed.push((
+                    rule.clone(),
+                    RuleOrigin {
+                        rule: rule.name.clone(),
+                        layer: layer.name.clone(),
+                        overridden_layers: Vec::new(),
+                        patched_by: Vec::new(),
+                    },
+                )),
+            }
+        }
+        for patch in &layer.patches {
+            match composed
+                .iter_mut()
+                .find(|(existing, _)| existing.name == patch.name)
+            {
+                Some((existing, origin)) => {
+                    patch.apply(existing);
+                    origin.patched_by.push(layer.name.clone());
+                }
+                None => conflicts.push(Conflict::MissingRule {
+                    rule: patch.name.clone(),
+                    layer: layer.name.clone(),
+                }),
+            }
+        }
+    }
+    let (rules, origins): (Vec<_>, Vec<_>) = composed.into_iter().unzip();
+    conflicts.extend(shadowed_domains(&rules));
+    RuleSet {
+        rules,
+        origins,
+        version: layers.iter().filter_map(|layer| layer.version).max(),
+        conflicts,
+    }
+}
+
+/// Domains of rules already matched as a whole by earlier rules.
+fn shadowed_domains(rules: &[DirtyUrlRule]) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    for (index, rule) in rules.iter().enumerate() {
+        for domain in &rule.domains {
+            let shadowed_by = rules[..index].iter().find(|earlier| {
+                earlier.url_pattern.is_none()
+                    && earlier.path_pattern.is_empty()
+                    && earlier.path_regex.is_none()
+                    && earlier.url_exceptions.is_empty()
+                    && earlier.domains.contains(domain)
+            });
+            if let Some(shadowed_by) = shadowed_by {
+                conflicts.push(Conflict::ShadowedDomain {
+                    domain: domain.clone(),
+                    rule: rule.name.clone(),
+                    shadowed_by: shadowed_by.name.clone(),
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+/// Reports layer each effective rule comes from, in order of effective rule set.
+pub fn diff(layers: &[RuleLayer]) -> Vec<RuleOrigin> {
+    compose(layers).origins
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WashingProgram;
+
+    fn rule(name: &str, domain: &str) -> DirtyUrlRule {
+        DirtyUrlRule {
+            name: name.to_string(),
+            domains: vec![domain.to_string()],
+            washing_programs: vec![WashingProgram::RemoveAllParams],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn later_layers_take_precedence() {
+        let mut user = RuleLayer::new("user", vec![rule("a", "user.a.com"), rule("d", "d.com")]);
+        user.disabled.push("b".to_string());
+        let layers = [
+            RuleLayer::new("default", vec![rule("a", "a.com"), rule("b", "b.com")]),
+            RuleLayer::new("pack", vec![rule("c", "c.com"), rule("a", "pack.a.com")]),
+            user,
+        ];
+
+        let composed = compose(&layers);
+        let domains = composed
+            .rules
+            .iter()
+            .map(|rule| rule.domains[0].as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(domains, ["user.a.com", "c.com", "d.com"]);
+        assert_eq!(
+            composed.origins[0],
+            RuleOrigin {
+                rule: "a".to_string(),
+                layer: "user".to_string(),
+                overridden_layers: vec!["default".to_string(), "pack".to_string()],
+                patched_by: Vec::new(),
+            }
+        );
+        assert_eq!(
+            diff(&layers)
+                .iter()
+                .map(|origin| origin.layer.as_str())
+                .collect::<Vec<_>>(),
+            ["user", "pack", "user"]
+        );
+    }
+
+    #[test]
+    fn patches_fields_and_reports_conflicts() {
+        let mut user = RuleLayer::new("user", vec![rule("c", "a.com"), rule("c", "c.com")]);
+        user.disabled.push("missing".to_string());
+        user.patches = vec![
+            RulePatch {
+                name: "a".to_string(),
+                domains: Some(vec!["a.com".to_string(), "www.a.com".to_string()]),
+                ..Default::default()
+            },
+            RulePatch {
+                name: "gone".to_string(),
+                ..Default::default()
+            },
+        ];
+        let mut default = RuleLayer::new("default", vec![rule("a", "a.com")]);
+        default.version = Some(2);
+        let mut remote = RuleLayer::new("remote", Vec::new());
+        remote.version = Some(3);
+
+        let composed = compose(&[default, remote, user]);
+        assert_eq!(composed.rules[0].domains, ["a.com", "www.a.com"]);
+        assert!(composed.rules[0].washing_programs == [WashingProgram::RemoveAllParams]);
+        assert_eq!(composed.origins[0].patched_by, ["user"]);
+        assert_eq!(composed.rules[1].domains, ["c.com"]);
+        assert_eq!(composed.version, Some(3));
+        assert_eq!(
+            composed.conflicts,
+            [
+                Conflict::MissingRule {
+                    rule: "missing".to_string(),
+                    layer: "user".to_string(),
+                },
+                Conflict::DuplicateRule {
+                    rule: "c".to_string(),
+                    layer: "user".to_string(),
+                },
+                Conflict::MissingRule {
+                    rule: "gone".to_string(),
+                    layer: "user".to_string(),
+                },
+            ]
+        );
+
+        let shadowed = compose(&[RuleLayer::new(
+            "user",
+            vec![rule("a", "a.com"), rule("b", "a.com")],
+        )]);
+        assert_eq!(
+            shadowed.conflicts,
+            [Conflict::ShadowedDomain {
+                domain: "a.com".to_string(),
+                rule: "b".to_string(),
+                shadowed_by: "a".to_string(),
+            }]
+        );
+        assert_eq!(compose(&[RuleLayer::builtin()]).conflicts, []);
+    }
+}
@@ -0,0 +1,252 @@
+//! Where washer's rules come from, so new tracking params can be shipped without new releases.
+//!
+//! Remote rule set is served in [`crate::rule_file`] format (e.g. by mixer at `/rules`)
+//! and composed after built-in rules, like rule file is.
+//! [`RemoteRules::refresh`] fetches it only if it changed since last refresh (`ETag`, `Last-Modified`)
+//! and swaps it into running washers, refreshing it periodically is up to the app.
+//! Rule set with lower version than the last applied one (e.g. from stale mirror) is rejected.
+//!
+//! Redirect policy is configured by rule name, so short link rules new to washer
+//! are not resolved until their policy is set, see [`crate::UrlWasherConfig::set_redirect_policy`].
+
+use std::path::PathBuf;
+#[cfg(feature = "network")]
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "network")]
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "network")]
+use tracing::warn;
+use url::Url;
+
+#[cfg(feature = "network")]
+use crate::UrlWasher;
+use crate::{rule_file, rule_layers, DirtyUrlRule};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleSource {
+    /// Built-in [`crate::rule_set`].
+    #[default]
+    BuiltIn,
+    /// Rule file composed after built-in rules.
+    File(PathBuf),
+    /// Rule set fetched from url, built-in rules are used until it is fetched by [`RemoteRules`].
+    Remote(Url),
+}
+
+impl RuleSource {
+    /// Rules washer starts with, fails if rule file cannot be loaded.
+    pub fn initial_rules(&self) -> anyhow::Result<Vec<DirtyUrlRule>> {
+        match self {
+            RuleSource::BuiltIn | RuleSource::Remote(_) => Ok(crate::rule_set().clone()),
+            RuleSource::File(path) => Ok(with_builtin(rule_file::load(path)?)),
+        }
+    }
+}
+
+fn with_builtin(layer: rule_layers::RuleLayer) -> Vec<DirtyUrlRule> {
+    compose_with_builtin(layer).rules
+}
+
+fn compose_with_builtin(layer: rule_layers::RuleLayer) -> rule_layers::RuleSet {
+    rule_layers::compose(&[rule_layers::RuleLayer::builtin(), layer])
+}
+
+/// Subscription of remote rule set, remembers version of last fetched one.
+#[cfg(feature = "network")]
+pub struct RemoteRules {
+    url: Url,
+    applied: Mutex<Applied>,
+}
+
+/// Last applied rule set.
+#[cfg(feature = "network")]
+#[derive(Default)]
+struct Applied {
+    /// Response headers sent back on next refresh, so unchanged rule set is not downloaded again.
+    etag: Option<String>,
+    last_modified: Option<String>,
+    version: Option<u32>,
+}
+
+#[cfg(feature = "network")]
+impl RemoteRules {
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            applied: Mutex::default(),
+        }
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Fetches rule set and replaces rules of `washers` with it, returns how many rules they have now.
+    /// [`None`] if rule set did not change since last refresh.
+    /// Invalid rule set is rejected, so washers keep their current rules.
+    pub async fn refresh(
+        &self,
+        http_client: &reqwest::Client,
+        washers: &[Arc<UrlWasher>],
+    ) -> anyhow::Result<Option<usize>> {
+        let mut request = http_client.get(self.url.clone());
+        {
+            let applied = self.applied.lock().unwrap();
+            if let Some(etag) = &applied.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &applied.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let resp = request
+            .send()
+            .await
+            .with_context(|| format!("fetch rules {}", self.url))?;
+        if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        if !resp.status().is_success() {
+            return Err(anyhow!(
+                "Invalid status of rules {}: {}",
+                self.url,
+                resp.status()
+            ));
+        }
+        let header = |name: reqwest::header::HeaderName| {
+            let value = resp.headers().get(name)?.to_str().ok()?;
+            Some(value.to_string())
+        };
+        let etag = header(reqwest::header::ETAG);
+        let last_modified = header(reqwest::header::LAST_MODIFIED);
+        let json = resp
+            .bytes()
+            .await
+            .with_context(|| format!("read rules {}", self.url))?;
+        let mut layer =
+            rule_file::import(&json).with_context(|| format!("load rules {}", self.url))?;
+        layer.name = "remote".to_string();
+        let version = layer.version;
+        if let (Some(version), Some(applied)) = (version, self.applied.lock().unwrap().version) {
+            if version < applied {
+                return Err(anyhow!(
+                    "Rules {} have version {version}, older than applied {applied}",
+                    self.url
+                ));
+            }
+        }
+        let rule_set = compose_with_builtin(layer);
+        for conflict in &rule_set.conflicts {
+            warn!("Rules {}: {conflict}", self.url);
+        }
+        let len = rule_set.rules.len();
+        for washer in washers {
+            washer.replace_rules(rule_set.rules.clone());
+        }
+        *self.applied.lock().unwrap() = Applied {
+            etag,
+            last_modified,
+            version,
+        };
+        Ok(Some(len))
+    }
+}
+
+#[cfg(all(test, feature = "network"))]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::UrlWasherConfig;
+
+    /// Serves rule sets in order, answering `304` to requests carrying etag of the one in turn.
+    async fn serve_rules(rule_sets: &'static [&'static str]) -> (Url, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/rules", listener.local_addr().unwrap())).unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let served = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![0; 4096];
+                let len = stream.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..len]).to_ascii_lowercase();
+                let index = served
+                    .fetch_add(1, Ordering::SeqCst)
+                    .min(rule_sets.len() - 1);
+                let body = rule_sets[index];
+                let etag = format!("\"{}\"", body.len());
+                let response = if request.contains(&format!("if-none-match: {etag}")) {
+                    "HTTP/1.1 304 Not Modified\r\nconnection: close\r\n\r\n".to_string()
+                } else {
+                    format!(
+                        "HTTP/1.1 200 OK\r\netag: {etag}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                        body.len()
+                    )
+                };
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn swaps_fetched_rules_into_running_washers() {
+        const EXAMPLE_RULE: &str = r#"{
+            "version": 2,
+            "rules": [
+                {
+                    "name": "example.com",
+                    "domains": ["example.com"],
+                    "washing_programs": [{ "remove_some_params": ["ref"] }]
+                }
+            ]
+        }"#;
+        const INVALID_RULE: &str = r#"{ "rules": [{ "name": "nowhere" }] }"#;
+        const OLDER_RULES: &str = r#"{ "version": 1, "rules": [] }"#;
+        let (url, requests) =
+            serve_rules(&[EXAMPLE_RULE, EXAMPLE_RULE, INVALID_RULE, OLDER_RULES]).await;
+        let remote = RemoteRules::new(url);
+        let washers = [
+            Arc::new(UrlWasher::default()),
+            Arc::new(UrlWasher::new(UrlWasherConfig::default())),
+        ];
+        let http_client = crate::http_client_builder().build().unwrap();
+        let dirty = Url::parse("https://example.com/a?ref=x").unwrap();
+        assert_eq!(washers[0].wash(&dirty).await.unwrap(), None);
+
+        let len = remote.refresh(&http_client, &washers).await.unwrap();
+        assert_eq!(len, Some(crate::rule_set().len() + 1));
+        for washer in &washers {
+            let washed = washer.wash(&dirty).await.unwrap();
+            assert_eq!(washed.unwrap().as_str(), "https://example.com/a");
+        }
+        // second request carries etag of the first one
+        assert_eq!(remote.refresh(&http_client, &washers).await.unwrap(), None);
+        // third request gets rule without domains, current rules are kept
+        assert!(remote.refresh(&http_client, &washers).await.is_err());
+        // fourth one is older than applied one
+        assert!(remote.refresh(&http_client, &washers).await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+        assert!(washers[1].matching_rule(&dirty).is_some());
+    }
+
+    #[test]
+    fn loads_initial_rules_of_source() {
+        let remote = RuleSource::Remote(Url::parse("https://example.com/rules").unwrap());
+        assert_eq!(
+            remote.initial_rules().unwrap().len(),
+            crate::rule_set().len()
+        );
+        let missing = RuleSource::File(PathBuf::from("/nonexistent/rules.json"));
+        assert!(missing.initial_rules().is_err());
+    }
+}
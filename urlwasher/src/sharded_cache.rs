@@ -0,0 +1,68 @@
+//! LRU cache split into independently locked shards, so parallel washes of different urls
+//! (e.g. in mixer) do not wait for each other.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash},
+    num::NonZeroUsize,
+    sync::{Mutex, MutexGuard},
+};
+
+use lru::LruCache;
+
+/// Small caches stay a single exact LRU, sharding them would evict entries too early.
+const MIN_SHARD_CAPACITY: usize = 64;
+const MAX_SHARDS: usize = 16;
+
+pub(crate) struct ShardedLruCache<K, V> {
+    shards: Box<[Mutex<LruCache<K, V>>]>,
+    hasher: RandomState,
+}
+
+impl<K: Hash + Eq, V> ShardedLruCache<K, V> {
+    /// Capacity is split evenly between shards, so least recently used entry is evicted per shard.
+    pub(crate) fn new(capacity: NonZeroUsize) -> Self {
+        let shard_count = (capacity.get() / MIN_SHARD_CAPACITY).clamp(1, MAX_SHARDS);
+        let shard_capacity = NonZeroUsize::new(capacity.get().div_ceil(shard_count)).unwrap();
+        Self {
+            shards: (0..shard_count)
+                .map(|_| Mutex::new(LruCache::new(shard_capacity)))
+                .collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Locks shard holding `key`.
+    pub(crate) fn shard(&self, key: &K) -> MutexGuard<'_, LruCache<K, V>> {
+        let index = self.hasher.hash_one(key) as usize % self.shards.len();
+        self.shards[index].lock().unwrap()
+    }
+
+    /// Locks shards one by one, never all of them at once.
+    pub(crate) fn for_each_shard(&self, mut f: impl FnMut(&mut LruCache<K, V>)) {
+        for shard in self.shards.iter() {
+            f(&mut shard.lock().unwrap());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_capacity_between_shards() {
+        let cache = ShardedLruCache::new(NonZeroUsize::new(1024).unwrap());
+        assert_eq!(cache.shards.len(), MAX_SHARDS);
+        for key in 0..2048 {
+            cache.shard(&key).put(key, ());
+        }
+        let mut len = 0;
+        cache.for_each_shard(|shard| len += shard.len());
+        assert_eq!(len, 1024);
+        assert!(cache.shard(&2047).contains(&2047));
+
+        let cache = ShardedLruCache::<u32, ()>::new(NonZeroUsize::new(2).unwrap());
+        assert_eq!(cache.shards.len(), 1);
+    }
+}
@@ -0,0 +1,133 @@
+//! Generic unrolling of url shorteners without rules of their own, see [`crate::UrlWasherConfig::shorteners`].
+//!
+//! Links of [`KNOWN_SHORTENERS`] (and domains added in config) are resolved by [`rule`],
+//! so one redirect policy (keyed by [`RULE_NAME`]) applies to all of them.
+//! Unknown shorteners are recognized by shape of their links, e.g. `https://xy.io/aB3dE`,
+//! short domain with single short path segment mixing letters and digits (or cases), without query.
+
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{public_suffix, DirtyUrlRule, WashingProgram};
+
+/// Name of [`rule`], key of its redirect policy.
+pub const RULE_NAME: &str = "url shorteners";
+
+pub const KNOWN_SHORTENERS: &[&str] = &[
+    "bit.ly",
+    "buff.ly",
+    "cutt.ly",
+    "goo.gl",
+    "is.gd",
+    "lnkd.in",
+    "ow.ly",
+    "rb.gy",
+    "rebrand.ly",
+    "s.id",
+    "shorturl.at",
+    "t.co",
+    "t.ly",
+    "tiny.cc",
+    "tinyurl.com",
+    "v.gd",
+];
+
+/// Longest name (registrable domain without suffix, `bit` of `bit.ly`) of domain treated as unknown shortener.
+const MAX_UNKNOWN_NAME_LEN: usize = 5;
+const UNKNOWN_PATH_LEN: std::ops::RangeInclusive<usize> = 4..=12;
+
+static SHORTENERS_RULE: OnceLock<DirtyUrlRule> = OnceLock::new();
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Default, Debug)]
+#[serde(default)]
+pub struct ShortenersConfig {
+    /// Unroll links of shorteners no rule matches.
+    pub enabled: bool,
+    /// Shortener domains besides [`KNOWN_SHORTENERS`], their subdomains are matched too.
+    pub domains: Vec<String>,
+    /// Also unroll links looking like short links of unknown shorteners, may unroll links that are not short.
+    pub detect_unknown: bool,
+}
+
+impl ShortenersConfig {
+    /// Whether `url` is link of known shortener, or looks like one if [`ShortenersConfig::detect_unknown`] is set.
+    pub fn is_short_link(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.trim_end_matches('.');
+        let known = KNOWN_SHORTENERS
+            .iter()
+            .copied()
+            .chain(self.domains.iter().map(String::as_str))
+            .any(|domain| {
+                host.strip_suffix(domain)
+                    .is_some_and(|sub| sub.is_empty() || sub.ends_with('.'))
+            });
+        known || (self.detect_unknown && looks_short(host, url))
+    }
+}
+
+/// Rule resolving redirection of shorteners, applied to urls no other rule matches.
+pub fn rule() -> &'static DirtyUrlRule {
+    SHORTENERS_RULE.get_or_init(|| DirtyUrlRule {
+        name: RULE_NAME.to_string(),
+        domains: KNOWN_SHORTENERS.iter().map(ToString::to_string).collect(),
+        washing_programs: vec![WashingProgram::ResolveRedirection],
+        ..Default::default()
+    })
+}
+
+fn looks_short(host: &str, url: &Url) -> bool {
+    if url.query().is_some() || url.domain().is_none() {
+        return false;
+    }
+    let short_name = public_suffix::registrable_domain(host)
+        .and_then(|registrable| registrable.split_once('.'))
+        .is_some_and(|(name, _)| name.len() <= MAX_UNKNOWN_NAME_LEN);
+    let Some(slug) = url.path().strip_prefix('/') else {
+        return false;
+    };
+    let has = |pattern: fn(&char) -> bool| slug.chars().any(|c| pattern(&c));
+    short_name
+        && UNKNOWN_PATH_LEN.contains(&slug.len())
+        && slug.chars().all(|c| c.is_ascii_alphanumeric())
+        && has(char::is_ascii_alphabetic)
+        && (has(char::is_ascii_digit)
+            || (has(char::is_ascii_lowercase) && has(char::is_ascii_uppercase)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_short_links() {
+        let config = ShortenersConfig {
+            enabled: true,
+            domains: vec!["sho.rt".to_string()],
+            detect_unknown: false,
+        };
+        let is_short =
+            |config: &ShortenersConfig, url: &str| config.is_short_link(&Url::parse(url).unwrap());
+        assert!(is_short(&config, "https://bit.ly/3xYzAbC"));
+        assert!(is_short(&config, "https://www.bit.ly/3xYzAbC"));
+        assert!(is_short(&config, "https://go.sho.rt/a"));
+        assert!(!is_short(&config, "https://notbit.ly/3xYzAbC"));
+        assert!(!is_short(&config, "https://xy.io/aB3dE"));
+
+        let config = ShortenersConfig {
+            detect_unknown: true,
+            ..config
+        };
+        assert!(is_short(&config, "https://xy.io/aB3dE"));
+        assert!(is_short(&config, "https://qr.ab.co.uk/x7k2m"));
+        assert!(!is_short(&config, "https://xy.io/about"));
+        assert!(!is_short(&config, "https://xy.io/aB3dE?ref=x"));
+        assert!(!is_short(&config, "https://xy.io/a/B3dE"));
+        assert!(!is_short(&config, "https://example.com/aB3dE"));
+        assert!(!is_short(&config, "https://127.0.0.1/aB3dE"));
+    }
+}
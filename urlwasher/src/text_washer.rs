@@ -1,15 +1,63 @@
+use std::sync::Arc;
+
 use tracing::{debug, error};
 use url::Url;
 
-use crate::UrlWasher;
+use crate::{
+    analyzer::PossibleTracker,
+    blocklist::{BlockedAction, BlockedDestination},
+    markdown, UrlWasher, WashReport,
+};
 
-#[derive(Default)]
+/// Washes urls found in text.
+///
+/// Washer is shared, so text washing can use the same cache as washing of single urls.
+#[derive(Default, Clone)]
 pub struct TextWasher {
-    pub url_washer: UrlWasher,
+    pub url_washer: Arc<UrlWasher>,
+    /// Leave urls inside markdown code spans and fenced code blocks as they are,
+    /// e.g. documentation examples that show tracking params on purpose.
+    pub skip_code: bool,
+}
+
+/// Url found in text that was replaced by its washed version.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct WashedUrl {
+    pub original: String,
+    pub washed: String,
+    /// What washing changed and why, see [`UrlWasher::wash_with_report`].
+    pub report: WashReport,
+}
+
+pub struct WashedText {
+    pub text: String,
+    /// Urls found in text, including ones that did not need washing.
+    pub url_count: usize,
+    /// Urls that changed, in order of appearance.
+    pub washed_urls: Vec<WashedUrl>,
+    /// Urls leading to blocklisted domains. Flagged ones are washed in text, refused ones are left as they were.
+    pub blocked: Vec<BlockedDestination>,
+    /// Unchanged urls with params that look like trackers, see [`UrlWasher::possible_trackers`].
+    pub possible_trackers: Vec<UrlTrackers>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UrlTrackers {
+    pub url: String,
+    pub trackers: Vec<PossibleTracker>,
 }
 
 impl TextWasher {
     pub async fn wash(&self, text: &str) -> String {
+        self.wash_detailed(text).await.text
+    }
+
+    pub async fn wash_detailed(&self, text: &str) -> WashedText {
+        let code_ranges = if self.skip_code {
+            markdown::code_ranges(text)
+        } else {
+            Vec::new()
+        };
         let mut original_separators = Vec::new();
         let wash_tasks = text
             .split(|c: char| {
@@ -19,36 +67,119 @@ impl TextWasher {
                 }
                 is_whitespace
             })
-            .map(|part| async move {
-                if !part.starts_with("http://") && !part.starts_with("https://") {
-                    return part.to_string();
-                }
-                let url = match Url::parse(part) {
-                    Ok(url) => url,
-                    Err(_) => return part.to_string(),
-                };
-                debug!("Washing part of text: {url}");
-                match self.url_washer.wash(&url).await {
-                    Ok(Some(clean_url)) => clean_url.to_string(),
-                    Ok(None) => part.to_string(),
-                    Err(err) => {
-                        error!("Could not wash url '{}': {:?}", part, err);
-                        part.to_string()
+            .map(|part| {
+                let offset = part.as_ptr() as usize - text.as_ptr() as usize;
+                let in_code = code_ranges
+                    .iter()
+                    .any(|range| range.start < offset + part.len() && offset < range.end);
+                async move {
+                    if in_code {
+                        return (part, None);
                     }
+                    (part, Some(self.wash_part(part).await))
                 }
             })
             .collect::<Vec<_>>();
         let mut patched = String::new();
+        let mut washed_urls = Vec::new();
+        let mut blocked = Vec::new();
+        let mut possible_trackers = Vec::new();
+        let mut url_count = 0;
         for (index, task) in wash_tasks.into_iter().enumerate() {
-            patched.push_str(&task.await);
-            if let Some(separator) = original_separators.get(index) {
+            if let Some(separator) = index
+                .checked_sub(1)
+                .and_then(|index| original_separators.get(index))
+            {
                 patched.push(*separator);
             }
+            let (part, washed) = task.await;
+            let Some(washed) = washed else {
+                patched.push_str(part);
+                continue;
+            };
+            if is_url(part) {
+                url_count += 1;
+            }
+            let washed = match washed {
+                Ok(washed) => washed,
+                Err(destination) => {
+                    let washed = (destination.action == BlockedAction::Flag).then(|| WashReport {
+                        original: part.to_string(),
+                        cleaned: destination.url.to_string(),
+                        ..Default::default()
+                    });
+                    blocked.push(destination);
+                    washed
+                }
+            };
+            match (part, washed) {
+                (part, Some(report)) if report.cleaned != part => {
+                    patched.push_str(&report.cleaned);
+                    washed_urls.push(WashedUrl {
+                        original: part.to_string(),
+                        washed: report.cleaned.clone(),
+                        report,
+                    });
+                }
+                (part, _) => {
+                    patched.push_str(part);
+                    if let Some(trackers) = self.possible_trackers(part) {
+                        possible_trackers.push(trackers);
+                    }
+                }
+            }
+        }
+        WashedText {
+            text: patched,
+            url_count,
+            washed_urls,
+            blocked,
+            possible_trackers,
+        }
+    }
+
+    fn possible_trackers(&self, part: &str) -> Option<UrlTrackers> {
+        if !is_url(part) {
+            return None;
+        }
+        let url = Url::parse(part).ok()?;
+        let trackers = self.url_washer.possible_trackers(&url);
+        (!trackers.is_empty()).then(|| UrlTrackers {
+            url: part.to_string(),
+            trackers,
+        })
+    }
+
+    /// Report of washed url, [`None`] if part is not an url or there was nothing to wash.
+    async fn wash_part(&self, part: &str) -> Result<Option<WashReport>, BlockedDestination> {
+        if !is_url(part) {
+            return Ok(None);
+        }
+        let Ok(url) = Url::parse(part) else {
+            return Ok(None);
+        };
+        debug!("Washing part of text: {url}");
+        match self.url_washer.wash_with_report(&url).await {
+            Ok(report) => Ok((report.cleaned != report.original).then_some(report)),
+            Err(err) => match err.downcast::<BlockedDestination>() {
+                Ok(destination) => Err(destination),
+                Err(err) => {
+                    error!("Could not wash url '{}': {:?}", part, err);
+                    Ok(None)
+                }
+            },
         }
-        patched
     }
 }
 
+/// Some apps copy links with odd scheme casing (`HTTP://`, `HtTpS://`), they are washable too.
+fn is_url(part: &str) -> bool {
+    ["http://", "https://"].iter().any(|scheme| {
+        part.get(..scheme.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::TextWasher;
@@ -63,4 +194,51 @@ mod tests {
         https://music.youtube.com/watch?v=OCAuoCSWIOQ
         ipsum", cleaned);
     }
+
+    #[tokio::test]
+    pub async fn reports_washed_urls() {
+        let text_washer = TextWasher::default();
+        let washed = text_washer
+            .wash_detailed("https://youtu.be/lSwnPoo9ZK0?si=TRACKING https://youtu.be/lSwnPoo9ZK0")
+            .await;
+        let [washed_url] = washed.washed_urls.as_slice() else {
+            panic!("expected one washed url, got {:?}", washed.washed_urls);
+        };
+        assert_eq!(
+            washed_url.original,
+            "https://youtu.be/lSwnPoo9ZK0?si=TRACKING"
+        );
+        assert_eq!(washed_url.washed, "https://youtu.be/lSwnPoo9ZK0");
+        assert_eq!(washed_url.report.removed_params, vec!["si"]);
+        assert_eq!(washed_url.report.rule.as_deref(), Some("youtu.be"));
+        assert_eq!(washed.url_count, 2);
+    }
+
+    #[tokio::test]
+    pub async fn skips_markdown_code() {
+        let text_washer = TextWasher {
+            skip_code: true,
+            ..Default::default()
+        };
+        let washed = text_washer
+            .wash_detailed("Run `curl https://youtu.be/lSwnPoo9ZK0?si=EXAMPLE` to see https://youtu.be/lSwnPoo9ZK0?si=TRACKING\n```\nhttps://youtu.be/lSwnPoo9ZK0?si=EXAMPLE\n```")
+            .await;
+        assert_eq!(
+            washed.text,
+            "Run `curl https://youtu.be/lSwnPoo9ZK0?si=EXAMPLE` to see https://youtu.be/lSwnPoo9ZK0\n```\nhttps://youtu.be/lSwnPoo9ZK0?si=EXAMPLE\n```"
+        );
+        assert_eq!(washed.url_count, 1);
+    }
+
+    #[tokio::test]
+    pub async fn washes_urls_with_uppercase_scheme() {
+        let text_washer = TextWasher::default();
+        let cleaned = text_washer
+            .wash("see HTTPS://youtu.be/lSwnPoo9ZK0?si=TRACKING and HtTp://youtu.be/lSwnPoo9ZK0?si=TRACKING")
+            .await;
+        assert_eq!(
+            cleaned,
+            "see https://youtu.be/lSwnPoo9ZK0 and http://youtu.be/lSwnPoo9ZK0"
+        );
+    }
 }
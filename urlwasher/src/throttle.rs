@@ -0,0 +1,112 @@
+//! Limit of short links of the same domain resolved at once, so texts full of e.g. `vm.tiktok.com` links
+//! do not hit shortener with dozens of requests at once, see [`crate::UrlWasherConfig::redirect_concurrency_per_domain`].
+
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use url::Url;
+
+use crate::RedirectWashPolicy;
+
+pub(crate) struct DomainThrottle {
+    /// Unlimited if not set.
+    limit: Option<NonZeroUsize>,
+    semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl DomainThrottle {
+    pub(crate) fn new(limit: Option<NonZeroUsize>) -> Self {
+        Self {
+            limit,
+            semaphores: Mutex::default(),
+        }
+    }
+
+    /// Waits until request resolving `url` can be sent, it is sent until permit is dropped.
+    /// Only requests sent by washer itself are throttled, mixer throttles its own.
+    pub(crate) async fn acquire(
+        &self,
+        url: &Url,
+        policy: &RedirectWashPolicy,
+    ) -> Option<OwnedSemaphorePermit> {
+        let limit = self.limit?;
+        if !matches!(
+            policy,
+            RedirectWashPolicy::Locally | RedirectWashPolicy::ViaTor
+        ) {
+            return None;
+        }
+        let domain = url.host_str()?;
+        let semaphore = {
+            let mut semaphores = self.semaphores.lock().unwrap();
+            if !semaphores.contains_key(domain) {
+                // domains without requests in progress are forgotten, so map does not grow with every domain seen.
+                // Semaphores cloned by callers still waiting for their permit are kept, otherwise
+                // next caller would get fresh one and exceed the limit.
+                semaphores.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+            }
+            semaphores
+                .entry(domain.to_string())
+                .or_insert_with(|| Arc::new(Semaphore::new(limit.get())))
+                .clone()
+        };
+        semaphore.acquire_owned().await.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn limits_requests_per_domain() {
+        let throttle = DomainThrottle::new(NonZeroUsize::new(1));
+        let short = Url::parse("https://vm.tiktok.com/a/").unwrap();
+        let other = Url::parse("https://vm.tiktok.com/b/").unwrap();
+        let policy = RedirectWashPolicy::Locally;
+
+        let permit = throttle.acquire(&short, &policy).await;
+        assert!(permit.is_some());
+        let waiting = throttle.acquire(&other, &policy);
+        tokio::pin!(waiting);
+        assert!(futures_util::poll!(&mut waiting).is_pending());
+        // other domains and mixer requests are not throttled
+        let soundcloud = Url::parse("https://on.soundcloud.com/a").unwrap();
+        assert!(throttle.acquire(&soundcloud, &policy).await.is_some());
+        assert!(throttle
+            .acquire(&other, &RedirectWashPolicy::ViaMixer)
+            .await
+            .is_none());
+
+        drop(permit);
+        assert!(waiting.await.is_some());
+        assert_eq!(throttle.semaphores.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn forgets_only_semaphores_without_callers() {
+        let throttle = DomainThrottle::new(NonZeroUsize::new(1));
+        let policy = RedirectWashPolicy::Locally;
+        let short = Url::parse("https://vm.tiktok.com/a/").unwrap();
+        drop(throttle.acquire(&short, &policy).await);
+        // caller that cloned semaphore, but did not acquire its permit yet
+        let cloned = throttle.semaphores.lock().unwrap()["vm.tiktok.com"].clone();
+
+        let soundcloud = Url::parse("https://on.soundcloud.com/a").unwrap();
+        assert!(throttle.acquire(&soundcloud, &policy).await.is_some());
+        assert!(Arc::ptr_eq(
+            &throttle.semaphores.lock().unwrap()["vm.tiktok.com"],
+            &cloned
+        ));
+
+        drop(cloned);
+        let twitter = Url::parse("https://t.co/a").unwrap();
+        assert!(throttle.acquire(&twitter, &policy).await.is_some());
+        let semaphores = throttle.semaphores.lock().unwrap();
+        assert_eq!(semaphores.keys().collect::<Vec<_>>(), vec!["t.co"]);
+    }
+}
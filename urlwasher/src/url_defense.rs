@@ -0,0 +1,96 @@
+//! Decoding of Proofpoint URL Defense links (`urldefense.proofpoint.com/v1`, `/v2` and `urldefense.com/v3`),
+//! done locally, as destination is encoded in the link itself.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use url::Url;
+
+/// Characters encoding run length of `**` tokens in v3 links, starting from 2.
+const V3_RUN_LENGTHS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Destination of URL Defense link, [`None`] if `url` is not one or it is malformed.
+pub fn decode(url: &Url) -> Option<Url> {
+    let destination = match url.path() {
+        "/v1/url" => query_param(url, "u")?,
+        "/v2/url" => decode_v2(&query_param(url, "u")?),
+        // encoded destination may contain `?`, so it is split between path and query of wrapper
+        path if path.starts_with("/v3/__") => decode_v3(url.as_str())?,
+        _ => return None,
+    };
+    Url::parse(&destination)
+        .ok()
+        .filter(|destination| matches!(destination.scheme(), "http" | "https"))
+}
+
+fn query_param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// `-XX` is hex encoded byte and `_` is `/`.
+fn decode_v2(encoded: &str) -> String {
+    let encoded = encoded.replace('_', "/").replace('-', "%");
+    percent_encoding::percent_decode_str(&encoded)
+        .decode_utf8_lossy()
+        .into_owned()
+}
+
+/// `/v3/__{URL}__;{REPLACED}!...`, characters of url replaced with `*` are base64 encoded in `REPLACED`,
+/// `**` followed by run length character replaces run of them.
+fn decode_v3(url: &str) -> Option<String> {
+    let (_, rest) = url.split_once("/v3/__")?;
+    let (encoded_url, rest) = rest.split_once("__;")?;
+    let replaced = rest.split('!').next().unwrap_or_default();
+    let replaced = URL_SAFE_NO_PAD
+        .decode(replaced.trim_end_matches('='))
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())?;
+    let mut replaced = replaced.chars();
+    let mut decoded = String::with_capacity(encoded_url.len());
+    let mut chars = encoded_url.chars().peekable();
+    while let Some(char) = chars.next() {
+        if char != '*' {
+            decoded.push(char);
+            continue;
+        }
+        if chars.peek() == Some(&'*') {
+            chars.next();
+            let run_length = V3_RUN_LENGTHS.find(chars.next()?)? + 2;
+            for _ in 0..run_length {
+                decoded.push(replaced.next()?);
+            }
+        } else {
+            decoded.push(replaced.next()?);
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_all_versions() {
+        let decode = |url: &str| decode(&Url::parse(url).unwrap()).map(String::from);
+        assert_eq!(
+            decode(
+                "https://urldefense.proofpoint.com/v1/url?u=https%3A%2F%2Fexample.com%2Fa&k=abc"
+            ),
+            Some("https://example.com/a".to_string())
+        );
+        assert_eq!(
+            decode("https://urldefense.proofpoint.com/v2/url?u=https-3A__example.com_a-3Fb-3D1&d=DwMFaQ&c=x"),
+            Some("https://example.com/a?b=1".to_string())
+        );
+        assert_eq!(
+            decode("https://urldefense.com/v3/__https://example.com/a?b=1*c=2__;Jg!!token$"),
+            Some("https://example.com/a?b=1&c=2".to_string())
+        );
+        assert_eq!(
+            decode("https://urldefense.com/v3/__https://example.com/**Aa__;JiY!!token$"),
+            Some("https://example.com/&&a".to_string())
+        );
+        assert_eq!(decode("https://urldefense.com/v3/"), None);
+    }
+}
@@ -0,0 +1,67 @@
+use std::{rc::Rc, sync::Arc};
+
+use url::Url;
+use urlwasher::{text_washer::TextWasher, RedirectWashPolicy, UrlWasherConfig};
+use wasm_bindgen::prelude::*;
+
+/// Washer exposed to JavaScript as `UrlWasher` class.
+///
+/// Rules are evaluated offline, short links are unrolled only via mixer instance.
+#[wasm_bindgen(js_name = UrlWasher)]
+pub struct JsUrlWasher {
+    text_washer: Rc<TextWasher>,
+}
+
+#[wasm_bindgen(js_class = UrlWasher)]
+impl JsUrlWasher {
+    /// Short links are left as is without `mixerInstance`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(mixer_instance: Option<String>) -> Result<JsUrlWasher, JsError> {
+        let mixer_instance = mixer_instance
+            .map(|mixer_instance| Url::parse(&mixer_instance))
+            .transpose()
+            .map_err(|err| JsError::new(&format!("invalid mixer instance: {err}")))?;
+        let redirect_policy = match mixer_instance {
+            Some(_) => RedirectWashPolicy::ViaMixer,
+            None => RedirectWashPolicy::Ignore,
+        };
+        let mut config = UrlWasherConfig {
+            mixer_instance,
+            ..Default::default()
+        };
+        config
+            .redirect_policy
+            .values_mut()
+            .for_each(|policy| *policy = redirect_policy);
+        Ok(Self {
+            text_washer: Rc::new(TextWasher {
+                url_washer: Arc::new(urlwasher::UrlWasher::new(config)),
+                skip_code: false,
+            }),
+        })
+    }
+
+    /// Resolves to clean url, or the same url if there was nothing to wash.
+    pub fn wash(&self, url: String) -> js_sys::Promise {
+        let text_washer = self.text_washer.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            let url =
+                Url::parse(&url).map_err(|err| JsError::new(&format!("invalid url: {err}")))?;
+            let washed = text_washer
+                .url_washer
+                .wash(&url)
+                .await
+                .map_err(|err| JsError::new(&format!("{err:#}")))?;
+            Ok(JsValue::from(washed.unwrap_or(url).to_string()))
+        })
+    }
+
+    /// Resolves to text with all urls washed.
+    #[wasm_bindgen(js_name = washText)]
+    pub fn wash_text(&self, text: String) -> js_sys::Promise {
+        let text_washer = self.text_washer.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            Ok(JsValue::from(text_washer.wash(&text).await))
+        })
+    }
+}